@@ -0,0 +1,88 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Measures the throughput of a network, e.g. a local testnet started with the `testnet` bin,
+//! by uploading and then reading back small files with a number of requests in flight at once.
+
+use safenode::client::{Client, ClientEvent, Files};
+
+use bls::SecretKey;
+use bytes::Bytes;
+use clap::Parser;
+use eyre::Result;
+use futures::{stream, StreamExt};
+use rand::RngCore;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+#[clap(name = "throughput benchmark")]
+struct Opt {
+    /// Number of files to upload and then read back.
+    #[clap(long, default_value_t = 200)]
+    files: usize,
+
+    /// Size of each file, in bytes. Files smaller than 3072 bytes are stored as a single chunk.
+    #[clap(long, default_value_t = 1024)]
+    size: usize,
+
+    /// Number of files being uploaded or read at once.
+    #[clap(long, default_value_t = 32)]
+    concurrency: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opt = Opt::parse();
+
+    let client = Client::new(SecretKey::random())?;
+    let mut client_events_rx = client.events_channel();
+    loop {
+        if let Ok(ClientEvent::ConnectedToNetwork) = client_events_rx.recv().await {
+            break;
+        }
+    }
+    // Give the client some time to learn about the rest of the network
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    let files = Files::new(client);
+
+    let contents: Vec<_> = (0..opt.files)
+        .map(|_| {
+            let mut bytes = vec![0; opt.size];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            Bytes::from(bytes)
+        })
+        .collect();
+
+    let start = Instant::now();
+    let uploads: Vec<_> = stream::iter(contents)
+        .map(|bytes| files.upload(bytes))
+        .buffer_unordered(opt.concurrency)
+        .collect()
+        .await;
+    report("Uploaded", start.elapsed(), &uploads);
+    let addresses: Vec<_> = uploads.into_iter().flatten().collect();
+
+    let start = Instant::now();
+    let reads: Vec<_> = stream::iter(addresses)
+        .map(|address| files.read_bytes(address))
+        .buffer_unordered(opt.concurrency)
+        .collect()
+        .await;
+    report("Read", start.elapsed(), &reads);
+
+    Ok(())
+}
+
+fn report<T, E>(action: &str, elapsed: Duration, results: &[Result<T, E>]) {
+    let succeeded = results.iter().filter(|res| res.is_ok()).count();
+    println!(
+        "{action} {succeeded}/{} files in {elapsed:?}: {:.1} files/s",
+        results.len(),
+        succeeded as f64 / elapsed.as_secs_f64()
+    );
+}
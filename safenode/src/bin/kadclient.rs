@@ -9,18 +9,35 @@
 use safenode::{
     client::{Client, ClientEvent, Error as ClientError, Files, WalletClient},
     log::init_node_logging,
-    protocol::{address::ChunkAddress, wallet::LocalWallet},
+    protocol::{
+        address::{dbc_name, ChunkAddress},
+        wallet::{
+            address_from_hex, address_to_hex, dbc_from_hex, dbc_id_src_from_hex, dbc_id_src_to_hex,
+            dbc_to_hex, main_key_from_mnemonic, read_dbc_file, read_key_backup, write_dbc_file,
+            DepositWallet, LedgerFormat, LocalWallet, Wallet,
+        },
+    },
 };
 
 use bytes::Bytes;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use dirs_next::home_dir;
-use eyre::Result;
-use std::{fs, path::PathBuf};
+use eyre::{eyre, Result};
+use sn_dbc::Token;
+use std::{fs, net::SocketAddr, path::PathBuf};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
 use tracing::info;
 use walkdir::WalkDir;
 use xor_name::XorName;
 
+// Environment variable holding the passphrase the wallet files are encrypted with.
+const WALLET_PASSPHRASE_VAR: &str = "SAFE_WALLET_PASSPHRASE";
+// The request to send to the RPC address of a faucet, followed by the address to send tokens to.
+const FAUCET_REQUEST: &str = "faucet";
+
 #[derive(Parser, Debug)]
 #[clap(name = "safeclient cli")]
 struct Opt {
@@ -44,6 +61,78 @@ struct Opt {
 
     #[clap(long)]
     query_register: Vec<String>,
+
+    #[clap(subcommand)]
+    cmd: Option<SubCmd>,
+}
+
+#[derive(Subcommand, Debug)]
+enum SubCmd {
+    /// Manage the tokens of the wallet.
+    #[clap(subcommand)]
+    Wallet(WalletCmd),
+}
+
+#[derive(Subcommand, Debug)]
+enum WalletCmd {
+    /// Prints the address of the wallet, to which others can send tokens.
+    Address,
+    /// Prints the balance of the wallet.
+    Balance,
+    /// Generates a dbc id to receive tokens at, to be given to the sender.
+    Receive,
+    /// Prints the record of tokens received and sent, or exports it to a file.
+    History {
+        /// The format of the history, csv or json.
+        #[clap(long, default_value = "csv")]
+        format: LedgerFormat,
+        /// The file to export the history to, instead of printing it.
+        #[clap(long)]
+        file: Option<PathBuf>,
+    },
+    /// Sends tokens, and writes the dbc created for the recipient to a file, to be handed to them.
+    Send {
+        /// The amount of tokens to send, e.g. 1.5.
+        amount: String,
+        /// The hex-encoded address of the recipient, or a dbc id they generated with `receive`.
+        to: String,
+        /// The file to write the hex-encoded dbc to, which must not exist yet.
+        /// Defaults to a file named after the address of the dbc, in the current dir.
+        #[clap(long)]
+        dbc_file: Option<PathBuf>,
+    },
+    /// Verifies the dbc in the file against the network, and deposits it to the wallet.
+    Deposit {
+        /// The file holding the hex-encoded dbc, as written by `send`.
+        dbc_file: PathBuf,
+    },
+    /// Asks the faucet of a local testnet for test tokens, and deposits them to the wallet.
+    Faucet {
+        /// The RPC address of the node running the faucet.
+        #[clap(default_value = "127.0.0.1:12001")]
+        addr: SocketAddr,
+    },
+    /// Prints the mnemonic phrase of the wallet key, or writes a backup of it to a file,
+    /// encrypted with the wallet passphrase, to restore the wallet with `import-key`.
+    ExportKey {
+        /// The file to write the encrypted backup to, instead of printing the mnemonic.
+        #[clap(long)]
+        backup_file: Option<PathBuf>,
+    },
+    /// Restores a wallet from its key, where there is no wallet yet,
+    /// given either its mnemonic phrase or a backup file written by `export-key`.
+    ImportKey {
+        /// The mnemonic phrase of the key.
+        #[clap(
+            long,
+            conflicts_with = "backup_file",
+            required_unless_present = "backup_file"
+        )]
+        mnemonic: Option<String>,
+        /// The backup file of the key, decrypted with the wallet passphrase.
+        #[clap(long)]
+        backup_file: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
@@ -54,12 +143,86 @@ async fn main() -> Result<()> {
     info!("Instantiating a SAFE client...");
 
     let client_dir = opt.client_dir.unwrap_or(get_client_dir().await?);
-    let wallet = LocalWallet::load_from(&client_dir).await?;
+    let passphrase = std::env::var(WALLET_PASSPHRASE_VAR).ok();
+
+    // The imported key's wallet is restored where there is none yet, so before loading one.
+    if let Some(SubCmd::Wallet(WalletCmd::ImportKey {
+        mnemonic,
+        backup_file,
+    })) = &opt.cmd
+    {
+        let key = match (mnemonic, backup_file) {
+            (Some(phrase), _) => main_key_from_mnemonic(phrase)?,
+            (None, Some(path)) => read_key_backup(path, &required_passphrase(&passphrase)?).await?,
+            (None, None) => return Err(eyre!("Either a mnemonic or a backup file is required")),
+        };
+        let wallet = LocalWallet::restore_to(&client_dir, key, passphrase.as_deref()).await?;
+        println!(
+            "Restored the wallet of address {}",
+            address_to_hex(&wallet.address())
+        );
+        return Ok(());
+    }
+
+    // An encrypted wallet is unlocked with the passphrase from the environment,
+    // which also encrypts a wallet that isn't yet.
+    let wallet = match &passphrase {
+        Some(passphrase) => LocalWallet::unlock_from(&client_dir, passphrase).await?,
+        None => LocalWallet::load_from(&client_dir).await?,
+    };
+
+    // These wallet commands don't need to connect to the network.
+    if let Some(SubCmd::Wallet(cmd)) = &opt.cmd {
+        match cmd {
+            WalletCmd::Address => {
+                println!("{}", address_to_hex(&wallet.address()));
+                return Ok(());
+            }
+            WalletCmd::Balance => {
+                println!("{}", wallet.balance());
+                return Ok(());
+            }
+            WalletCmd::Receive => {
+                let dbc_id_src = wallet.new_dbc_address();
+                println!("{}", dbc_id_src_to_hex(&dbc_id_src)?);
+                return Ok(());
+            }
+            WalletCmd::History { format, file } => {
+                let history = wallet.export_ledger(*format)?;
+                match file {
+                    Some(path) => {
+                        fs::write(path, history)?;
+                        println!(
+                            "Exported {} wallet history entries to {path:?}",
+                            wallet.ledger().len()
+                        );
+                    }
+                    None => print!("{history}"),
+                }
+                return Ok(());
+            }
+            WalletCmd::ExportKey { backup_file } => {
+                match backup_file {
+                    Some(path) => {
+                        wallet
+                            .export_backup(path, &required_passphrase(&passphrase)?)
+                            .await?;
+                        println!("Wrote a backup of the wallet key to {path:?}");
+                    }
+                    None => println!("{}", wallet.export_mnemonic()?),
+                }
+                return Ok(());
+            }
+            // Handled above, before loading the wallet.
+            WalletCmd::ImportKey { .. } => return Ok(()),
+            WalletCmd::Send { .. } | WalletCmd::Deposit { .. } | WalletCmd::Faucet { .. } => {}
+        }
+    }
 
     let secret_key = bls::SecretKey::random();
     let client = Client::new(secret_key)?;
     let file_api = Files::new(client.clone());
-    let _wallet_client = WalletClient::new(client.clone(), wallet);
+    let mut wallet_client = WalletClient::new(client.clone(), wallet);
 
     let mut client_events_rx = client.events_channel();
     if let Ok(event) = client_events_rx.recv().await {
@@ -70,6 +233,73 @@ async fn main() -> Result<()> {
         }
     }
 
+    if let Some(SubCmd::Wallet(cmd)) = opt.cmd {
+        match cmd {
+            WalletCmd::Send {
+                amount,
+                to,
+                dbc_file,
+            } => {
+                let amount: Token = amount
+                    .parse()
+                    .map_err(|err| eyre!("Invalid amount {amount}: {err}"))?;
+                // The recipient is either an address, or a dbc id they generated.
+                let dbc_id_src = match address_from_hex(&to) {
+                    Ok(address) => address.random_dbc_id_src(&mut rand::thread_rng()),
+                    Err(_) => dbc_id_src_from_hex(&to)?,
+                };
+                // The dbc file is checked before sending, as to not send tokens it can't hold.
+                let dbc_file = dbc_file.unwrap_or_else(|| {
+                    PathBuf::from(format!("dbc-{:x}", dbc_name(&dbc_id_src.dbc_id())))
+                });
+                if dbc_file.exists() {
+                    return Err(eyre!("The dbc file {dbc_file:?} already exists"));
+                }
+                let (dbc, position) = wallet_client.send_to(amount, dbc_id_src).await?;
+                wallet_client.wallet().store().await?;
+                if let Err(err) = write_dbc_file(&dbc_file, &dbc).await {
+                    // The tokens were sent, so the dbc must not be lost.
+                    println!("Failed to write the dbc to {dbc_file:?}, here it is:");
+                    println!("{}", dbc_to_hex(&dbc)?);
+                    return Err(err.into());
+                }
+                println!("Sent {amount}, the dbc for the recipient was written to {dbc_file:?}");
+                if let Some(position) = position {
+                    println!(
+                        "The spends are at position {} in the spend queue, to be recorded in about {}s",
+                        position.position,
+                        position.eta.as_secs()
+                    );
+                }
+            }
+            WalletCmd::Deposit { dbc_file } => {
+                let dbc = read_dbc_file(&dbc_file).await?;
+                wallet_client.verify_and_deposit(vec![dbc]).await?;
+                println!(
+                    "Deposited the dbc, the balance is now {}",
+                    wallet_client.wallet().balance()
+                );
+            }
+            WalletCmd::Faucet { addr } => {
+                let address = address_to_hex(&wallet_client.wallet().address());
+                let dbc = dbc_from_hex(&faucet_request(addr, &address).await?)?;
+                wallet_client.verify_and_deposit(vec![dbc]).await?;
+                println!(
+                    "Deposited tokens from the faucet, the balance is now {}",
+                    wallet_client.wallet().balance()
+                );
+            }
+            // Handled above, without connecting to the network.
+            WalletCmd::Address
+            | WalletCmd::Balance
+            | WalletCmd::Receive
+            | WalletCmd::History { .. }
+            | WalletCmd::ExportKey { .. }
+            | WalletCmd::ImportKey { .. } => {}
+        }
+        return Ok(());
+    }
+
     let mut chunks_to_fetch = Vec::new();
 
     if let Some(files_path) = opt.upload_chunks {
@@ -170,6 +400,28 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+// Ask the faucet at the RPC address to send test tokens to the address, returning the hex-encoded dbc.
+async fn faucet_request(addr: SocketAddr, address: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(format!("{FAUCET_REQUEST} {address}\n").as_bytes())
+        .await?;
+    let mut response = String::new();
+    let _ = BufReader::new(&mut stream).read_line(&mut response).await?;
+    let response = response.trim();
+    match response.strip_prefix("error:") {
+        Some(error) => Err(eyre!("The faucet failed to send tokens: {}", error.trim())),
+        None => Ok(response.to_string()),
+    }
+}
+
+// The wallet passphrase, which backups of the wallet key are encrypted with.
+fn required_passphrase(passphrase: &Option<String>) -> Result<String> {
+    passphrase
+        .clone()
+        .ok_or_else(|| eyre!("Key backups are encrypted with the passphrase in {WALLET_PASSPHRASE_VAR}, which is not set"))
+}
+
 async fn get_client_dir() -> Result<PathBuf> {
     let mut home_dirs = home_dir().expect("A homedir to exist.");
     home_dirs.push(".safe");
@@ -6,21 +6,48 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+#[cfg(feature = "local-testnet")]
+use safenode::{
+    client::{Client, Faucet},
+    protocol::wallet::{address_from_hex, dbc_to_hex},
+};
 use safenode::{
     log::init_node_logging,
-    network::Network,
-    node::{Node, NodeEvent},
+    network::TransportMode,
+    node::{Node, NodeEvent, RunningNode},
 };
 
 use clap::Parser;
 use eyre::{eyre, Result};
 use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
 use std::{
+    collections::BTreeMap,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     path::PathBuf,
-    thread, time,
+    sync::Arc,
+    time::Duration,
 };
-use tracing::info;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Mutex},
+};
+use tracing::{info, warn};
+
+// The request to send to the RPC address to shut the node down.
+const RPC_SHUTDOWN_REQUEST: &str = "shutdown";
+// The request to send to the RPC address for test tokens, followed by the hex-encoded address to send them to.
+const RPC_FAUCET_REQUEST: &str = "faucet";
+// The request to send to the RPC address for the node's stats.
+const RPC_STATS_REQUEST: &str = "stats";
+// Time allowed for sending a request over an RPC connection, and for reading the response.
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+// Maximum length of a request sent over an RPC connection.
+const MAX_RPC_REQUEST_LEN: u64 = 1024;
+// Number of RPC requests read, waiting to be handled.
+const RPC_REQUESTS_BUFFER: usize = 64;
+// How often the node's stats are logged.
+const STATS_LOG_INTERVAL: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -28,25 +55,234 @@ async fn main() -> Result<()> {
     let _log_appender_guard = init_node_logging(&opt.log_dir)?;
 
     let socket_addr = SocketAddr::new(opt.ip, opt.port);
+    let peers = opt
+        .peers
+        .into_iter()
+        .map(parse_peer_addr)
+        .collect::<Result<Vec<_>>>()?;
 
     info!("Starting a node...");
-    let node_events_channel = Node::run(socket_addr).await?;
+    let running_node = Node::run(socket_addr, opt.transport, peers).await?;
 
-    let mut node_events_rx = node_events_channel.subscribe();
-    if let Ok(event) = node_events_rx.recv().await {
-        match event {
-            NodeEvent::ConnectedToNetwork => {
-                info!("Connected to the Network");
+    #[cfg(feature = "local-testnet")]
+    let faucet = match &opt.faucet_dir {
+        Some(faucet_dir) => {
+            let client = Client::new(bls::SecretKey::random())?;
+            info!("Running a faucet with its wallet in {faucet_dir:?}");
+            Some(Faucet::load(client, faucet_dir).await?)
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "local-testnet"))]
+    let faucet = None;
+
+    let mut node_events_rx = running_node.node_events_channel().subscribe();
+    let _handle = tokio::spawn(async move {
+        while let Ok(event) = node_events_rx.recv().await {
+            match event {
+                NodeEvent::ConnectedToNetwork => info!("Connected to the Network"),
+                NodeEvent::ShuttingDown => info!("Shutting down, no longer accepting writes"),
+                NodeEvent::DataHandedOver {
+                    handed_over,
+                    failed,
+                } => info!("Handed {handed_over} items over to other nodes, {failed} failed"),
+                NodeEvent::StoresFlushed => info!("Stores flushed"),
+                NodeEvent::ConnectionsClosed => info!("Connections closed"),
             }
         }
+    });
+
+    // Keep the node running till asked to shut down.
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result?;
+            info!("Received SIGINT");
+        }
+        result = terminate_signal() => {
+            result?;
+            info!("Received SIGTERM");
+        }
+        _ = log_stats(&running_node) => {}
+        result = rpc_requests(opt.rpc, &running_node, faucet) => {
+            result?;
+            info!("Received a shutdown request over RPC");
+        }
     }
 
-    // Keep the node running.
+    running_node.shutdown().await?;
+    info!("Node stopped");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn terminate_signal() -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let _ = signal(SignalKind::terminate())?.recv().await;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn terminate_signal() -> Result<()> {
+    std::future::pending().await
+}
+
+// Periodically log the node's stats. Never returns.
+async fn log_stats(running_node: &RunningNode) {
+    let mut interval = tokio::time::interval(STATS_LOG_INTERVAL);
     loop {
-        thread::sleep(time::Duration::from_millis(100));
+        let _ = interval.tick().await;
+        match node_stats(running_node, false).await {
+            Ok(stats) => info!("Node stats: {}", stats.replace('\n', "; ")),
+            Err(err) => warn!("Failed to get the node stats: {err}"),
+        }
     }
 }
 
+// The number of connected peers per agent and protocol version, one version per line,
+// followed by the agent and protocol versions of each peer if `per_peer` is set.
+async fn node_stats(running_node: &RunningNode, per_peer: bool) -> Result<String> {
+    let peers_agent = running_node.peers_agent().await?;
+    let mut versions: BTreeMap<_, usize> = BTreeMap::new();
+    for agent in peers_agent.values() {
+        *versions
+            .entry((&agent.agent_version, &agent.protocol_version))
+            .or_default() += 1;
+    }
+
+    let mut lines = vec![format!("{} identified peers", peers_agent.len())];
+    lines.extend(
+        versions.into_iter().map(|((agent, protocol), count)| {
+            format!("{count} peers running {agent} ({protocol})")
+        }),
+    );
+    if per_peer {
+        lines.extend(peers_agent.iter().map(|(peer_id, agent)| {
+            format!(
+                "{peer_id}: {} ({})",
+                agent.agent_version, agent.protocol_version
+            )
+        }));
+    }
+    Ok(lines.join("\n"))
+}
+
+// Wait for a connection to the RPC address sending the shutdown request as a line, if given an address.
+// Each connection's request is read in its own task, so that slow or failed connections are logged
+// and dropped without holding up the others, or affecting the node.
+// When running a faucet, faucet requests are answered with the hex-encoded dbc sent, each in its own
+// task, while the faucet sends to one address at a time.
+// Stats requests are answered with the node's stats, followed by an empty line.
+async fn rpc_requests(
+    addr: Option<SocketAddr>,
+    running_node: &RunningNode,
+    faucet: Option<Faucet>,
+) -> Result<()> {
+    let addr = match addr {
+        Some(addr) => addr,
+        None => std::future::pending().await,
+    };
+    let listener = TcpListener::bind(addr).await?;
+    info!("Listening for RPC requests on {}", listener.local_addr()?);
+    let faucet = faucet.map(|faucet| Arc::new(Mutex::new(faucet)));
+    let (requests_sender, mut requests) = mpsc::channel(RPC_REQUESTS_BUFFER);
+
+    loop {
+        let (request, mut stream, peer_addr) = tokio::select! {
+            accepted = listener.accept() => {
+                match accepted {
+                    Ok((mut stream, peer_addr)) => {
+                        let requests_sender = requests_sender.clone();
+                        let _handle = tokio::spawn(async move {
+                            match read_rpc_request(&mut stream).await {
+                                Ok(request) => {
+                                    let _ = requests_sender.send((request, stream, peer_addr)).await;
+                                }
+                                Err(err) => {
+                                    warn!("Failed to read the RPC request from {peer_addr}: {err}")
+                                }
+                            }
+                        });
+                    }
+                    Err(err) => warn!("Failed to accept an RPC connection: {err}"),
+                }
+                continue;
+            }
+            // We hold a sender, so the channel is never closed.
+            Some(received) = requests.recv() => received,
+        };
+
+        if request == RPC_SHUTDOWN_REQUEST {
+            respond(&mut stream, peer_addr, "ok\n").await;
+            return Ok(());
+        }
+        if request == RPC_STATS_REQUEST {
+            let response = node_stats(running_node, true)
+                .await
+                .unwrap_or_else(|err| format!("error: {err}"));
+            respond(&mut stream, peer_addr, &format!("{response}\n\n")).await;
+            continue;
+        }
+        if let Some(address) = request.strip_prefix(RPC_FAUCET_REQUEST) {
+            let faucet = match &faucet {
+                Some(faucet) => faucet.clone(),
+                None => {
+                    respond(&mut stream, peer_addr, "error: not running a faucet\n").await;
+                    continue;
+                }
+            };
+            let address = address.trim().to_string();
+            let _handle = tokio::spawn(async move {
+                let response = faucet_send(&mut *faucet.lock().await, &address)
+                    .await
+                    .unwrap_or_else(|err| format!("error: {err}"));
+                respond(&mut stream, peer_addr, &format!("{response}\n")).await;
+            });
+            continue;
+        }
+        warn!("Unknown RPC request from {peer_addr}: {request:?}");
+        respond(&mut stream, peer_addr, "unknown request\n").await;
+    }
+}
+
+// Read the request line sent over the RPC connection, trimmed,
+// failing if it's not sent in time, or is too long.
+async fn read_rpc_request(stream: &mut TcpStream) -> Result<String> {
+    let mut request = String::new();
+    let mut reader = BufReader::new(stream).take(MAX_RPC_REQUEST_LEN);
+    let _ = tokio::time::timeout(RPC_TIMEOUT, reader.read_line(&mut request))
+        .await
+        .map_err(|_| eyre!("no request received within {RPC_TIMEOUT:?}"))??;
+    Ok(request.trim().to_string())
+}
+
+// Write the response to the RPC connection, logging any failure.
+async fn respond(stream: &mut TcpStream, peer_addr: SocketAddr, response: &str) {
+    match tokio::time::timeout(RPC_TIMEOUT, stream.write_all(response.as_bytes())).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => warn!("Failed to respond to the RPC request from {peer_addr}: {err}"),
+        Err(_) => warn!("Timed out responding to the RPC request from {peer_addr}"),
+    }
+}
+
+// Send test tokens from the faucet to the hex-encoded address, returning the hex-encoded dbc.
+#[cfg(feature = "local-testnet")]
+async fn faucet_send(faucet: &mut Faucet, address: &str) -> Result<String> {
+    let address = address_from_hex(address)?;
+    let dbc = faucet.send(address).await?;
+    info!("Faucet sent {:?} to {address:?}", dbc.id());
+    Ok(dbc_to_hex(&dbc)?)
+}
+
+// Only nodes of local testnets run a faucet, handing out the tokens of the testnet genesis dbc.
+#[cfg(not(feature = "local-testnet"))]
+enum Faucet {}
+
+#[cfg(not(feature = "local-testnet"))]
+async fn faucet_send(faucet: &mut Faucet, _address: &str) -> Result<String> {
+    match *faucet {}
+}
+
 #[derive(Parser, Debug)]
 #[clap(name = "safenode cli")]
 struct Opt {
@@ -62,18 +298,40 @@ struct Opt {
     /// Defaults to 0.0.0.0, which will bind to all network interfaces.
     #[clap(long, default_value_t = IpAddr::V4(Ipv4Addr::UNSPECIFIED))]
     ip: IpAddr,
+
+    /// Transport(s) to listen on: quic, tcp or both, on the same port.
+    /// Peers can be dialled over either, whichever is chosen.
+    #[clap(long, default_value = "both")]
+    transport: TransportMode,
+
+    /// Peer(s) to connect to when starting, to join networks outside the local one.
+    /// The multiaddr must end with the peer ID, and can be either a QUIC or a TCP one,
+    /// e.g. /ip4/1.2.3.4/udp/12000/quic-v1/p2p/<peer-id> or /ip4/1.2.3.4/tcp/12000/p2p/<peer-id>
+    #[clap(long = "peer")]
+    peers: Vec<Multiaddr>,
+
+    /// Address to listen on for RPC requests, e.g. 127.0.0.1:12001.
+    /// Sending the line `shutdown` to it gracefully shuts the node down, as SIGINT and SIGTERM do,
+    /// and the line `stats` returns the node's stats, e.g. the versions its peers are running.
+    #[clap(long)]
+    rpc: Option<SocketAddr>,
+
+    /// Runs a faucet for a local testnet, with its wallet in this dir, handing out test tokens
+    /// from the genesis dbc. Requests are sent to the RPC address, as the line `faucet <address>`.
+    /// Only available when built with the `local-testnet` feature.
+    #[cfg(feature = "local-testnet")]
+    #[clap(long)]
+    faucet_dir: Option<PathBuf>,
 }
 
-// Todo: Implement node bootstrapping to connect to peers from outside the local network
-#[allow(dead_code)]
-async fn bootstrap_node(network_api: &mut Network, addr: Multiaddr) -> Result<()> {
-    let peer_id = match addr.iter().last() {
-        Some(Protocol::P2p(hash)) => PeerId::from_multihash(hash).expect("Valid hash."),
-        _ => return Err(eyre!("Expect peer multiaddr to contain peer ID.")),
-    };
-    network_api
-        .dial(peer_id, addr)
-        .await
-        .expect("Dial to succeed");
-    Ok(())
+// Extract the peer ID from the peer's multiaddr, returning it along with the address to dial.
+fn parse_peer_addr(mut addr: Multiaddr) -> Result<(PeerId, Multiaddr)> {
+    match addr.pop() {
+        Some(Protocol::P2p(hash)) => {
+            let peer_id = PeerId::from_multihash(hash)
+                .map_err(|_| eyre!("Invalid peer ID in multiaddr: {addr}"))?;
+            Ok((peer_id, addr))
+        }
+        _ => Err(eyre!("Expect peer multiaddr to contain peer ID: {addr}")),
+    }
 }
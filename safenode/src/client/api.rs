@@ -12,19 +12,24 @@ use super::{
 };
 
 use crate::{
-    network::{NetworkEvent, SwarmDriver},
+    network::{close_group_majority, NetworkEvent, PeerMisbehaviour, SwarmDriver},
+    network_transfers::Error as TransferError,
+    node::NodeId,
     protocol::{
-        address::ChunkAddress,
+        address::{dbc_address, ChunkAddress, DbcAddress},
         chunk::Chunk,
         error::Error as ProtocolError,
-        messages::{Cmd, CmdResponse, Query, QueryResponse, Request, Response},
+        fees::{FeeCiphers, SpendQueuePosition},
+        messages::{Cmd, CmdResponse, Query, QueryResponse, Request, Response, SpendQuery},
     },
 };
 
 use bls::{PublicKey, SecretKey, Signature};
 use futures::future::select_all;
+use itertools::Itertools;
 use libp2p::PeerId;
-use std::time::Duration;
+use sn_dbc::{DbcTransaction, SignedSpend};
+use std::{collections::BTreeMap, time::Duration};
 use tokio::task::spawn;
 use xor_name::XorName;
 
@@ -148,14 +153,28 @@ impl Client {
     pub(super) async fn get_chunk(&self, address: ChunkAddress) -> Result<Chunk> {
         info!("Get chunk: {address:?}");
         let request = Request::Query(Query::GetChunk(address));
-        let responses = self.send_to_closest(request).await?;
+        let responses = self.send_to_closest_with_peers(request).await?;
 
-        // We will return the first chunk we get.
-        for resp in responses.iter().flatten() {
-            if let Response::Query(QueryResponse::GetChunk(Ok(chunk))) = resp {
-                return Ok(chunk.clone());
+        // We will return the first chunk we get which matches the address.
+        for (peer, resp) in &responses {
+            if let Ok(Response::Query(QueryResponse::GetChunk(Ok(chunk)))) = resp {
+                if chunk.address() == &address {
+                    return Ok(chunk.clone());
+                }
+                warn!(
+                    "Peer {peer:?} sent chunk {:?} for {address:?}",
+                    chunk.address()
+                );
+                if let Err(err) = self
+                    .network
+                    .report_peer(*peer, PeerMisbehaviour::WrongData)
+                    .await
+                {
+                    warn!("Error while reporting peer {peer:?}: {err:?}");
+                }
             };
         }
+        let responses: Vec<_> = responses.into_iter().map(|(_, resp)| resp).collect();
 
         // If no chunk was found, we will return the first error sent to us.
         for resp in responses.iter().flatten() {
@@ -174,7 +193,198 @@ impl Client {
         Err(Error::Protocol(ProtocolError::UnexpectedResponses))
     }
 
+    /// Send a `SignedSpend` to its close group, along with the fee ciphers of its nodes,
+    /// succeeding once a majority of them has accepted it.
+    /// Returns where the spend sits in their spend queues, i.e. when a majority will have recorded it.
+    pub(super) async fn spend_dbc(
+        &self,
+        signed_spend: SignedSpend,
+        source_tx: DbcTransaction,
+        fee_ciphers: BTreeMap<NodeId, FeeCiphers>,
+    ) -> Result<SpendQueuePosition> {
+        let address = dbc_address(signed_spend.dbc_id());
+        info!("Spend dbc: {address:?}");
+        let request = Request::Cmd(Cmd::SpendDbc {
+            signed_spend: Box::new(signed_spend),
+            source_tx: Box::new(source_tx),
+            fee_ciphers,
+        });
+        let responses = self.send_to_closest(request).await?;
+
+        let mut accepted: Vec<SpendQueuePosition> = responses
+            .iter()
+            .flat_map(|resp| {
+                if let Ok(Response::Cmd(CmdResponse::Spend(Ok(position)))) = resp {
+                    Some(*position)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if accepted.len() >= close_group_majority() {
+            // The spend is recorded once a majority of the close group has recorded it,
+            // so we return the position at the node which gets there last within that majority.
+            accepted.sort_by_key(|position| position.eta);
+            return Ok(accepted[close_group_majority() - 1]);
+        }
+
+        // If not enough accepted it, we will return the first error sent to us.
+        for resp in responses.iter().flatten() {
+            if let Response::Cmd(CmdResponse::Spend(result)) = resp {
+                let _ = result.clone()?;
+            };
+        }
+
+        // If there were no success or fail to the expected cmd,
+        // we check if there were any send errors.
+        for resp in responses {
+            let _ = resp?;
+        }
+
+        // If there were no spend errors, then we had unexpected responses.
+        Err(Error::Protocol(ProtocolError::UnexpectedResponses))
+    }
+
+    /// Retrieve a `Spend` from the closest peers, as recorded by a majority of them.
+    /// The spend is only reported as not found, or the dbc as double spent,
+    /// if a majority of them reported so.
+    pub(super) async fn get_spend(&self, query: SpendQuery) -> Result<SignedSpend> {
+        let address = query.dst();
+        info!("Get spend: {address:?}");
+        let request = Request::Query(Query::Spend(query));
+        let responses = self.send_to_closest(request).await?;
+
+        // Get all Ok results of the expected response type `GetDbcSpend`.
+        let spends: Vec<_> = responses
+            .iter()
+            .flatten()
+            .flat_map(|resp| {
+                if let Response::Query(QueryResponse::GetDbcSpend(Ok(signed_spend))) = resp {
+                    Some(signed_spend.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        // As to not have a single rogue node deliver a bogus spend,
+        // we look for a majority of the same responses, and ignore any other responses.
+        if let Some(spend) = spends
+            .into_iter()
+            .map(|spend| (spend, ()))
+            .into_group_map()
+            .into_iter()
+            .filter(|(_, v)| v.len() >= close_group_majority())
+            .max_by_key(|(_, v)| v.len())
+            .map(|(spend, _)| spend)
+        {
+            return Ok(spend);
+        }
+
+        // The spend is not recognised by enough peers in its close group.
+        info!("The spend could not be verified as valid: {address:?}");
+
+        // If no spend was agreed on, we will return the first error sent to us,
+        // but for the spend not being found or the dbc being double spent, unless a majority
+        // of the peers reported so, as a few peers reporting either does not make it so.
+        let mut not_found = 0;
+        let mut double_spent = 0;
+        for resp in responses.iter().flatten() {
+            match resp {
+                Response::Query(QueryResponse::GetDbcSpend(Err(ProtocolError::Transfers(
+                    TransferError::SpendNotFound(_),
+                )))) => not_found += 1,
+                Response::Query(QueryResponse::GetDbcSpend(Err(ProtocolError::Transfers(
+                    TransferError::DoubleSpendDetected(_),
+                )))) => double_spent += 1,
+                Response::Query(QueryResponse::GetDbcSpend(result)) => {
+                    let _ = result.clone()?;
+                }
+                _ => {}
+            }
+        }
+        if not_found >= close_group_majority() {
+            return Err(Error::Protocol(ProtocolError::Transfers(
+                TransferError::SpendNotFound(address),
+            )));
+        }
+        if double_spent >= close_group_majority() {
+            return Err(Error::Protocol(ProtocolError::Transfers(
+                TransferError::DoubleSpendDetected(address),
+            )));
+        }
+
+        // If there were no success or fail to the expected query,
+        // we check if there were any send errors.
+        for resp in responses {
+            let _ = resp?;
+        }
+
+        // If there was none of the above, then we had unexpected responses.
+        Err(Error::Protocol(ProtocolError::UnexpectedResponses))
+    }
+
+    /// Retrieve the conflicting spends of a double spent Dbc from the closest peers,
+    /// as proof of why the Dbc is unspendable.
+    pub async fn get_double_spend_proof(
+        &self,
+        address: DbcAddress,
+    ) -> Result<(SignedSpend, SignedSpend)> {
+        info!("Get double spend proof: {address:?}");
+        let request = Request::Query(Query::Spend(SpendQuery::GetDoubleSpendProof(address)));
+        let responses = self.send_to_closest_with_peers(request).await?;
+
+        // We will return the first proof we get which checks out.
+        for (peer, resp) in &responses {
+            if let Ok(Response::Query(QueryResponse::GetDoubleSpendProof(Ok((a_spend, b_spend))))) =
+                resp
+            {
+                if is_double_spend_proof(address, a_spend, b_spend) {
+                    return Ok((a_spend.clone(), b_spend.clone()));
+                }
+                warn!("Peer {peer:?} sent an invalid double spend proof for {address:?}");
+                if let Err(err) = self
+                    .network
+                    .report_peer(*peer, PeerMisbehaviour::WrongData)
+                    .await
+                {
+                    warn!("Error while reporting peer {peer:?}: {err:?}");
+                }
+            };
+        }
+        let responses: Vec<_> = responses.into_iter().map(|(_, resp)| resp).collect();
+
+        // If no proof was found, we will return the first error sent to us.
+        for resp in responses.iter().flatten() {
+            if let Response::Query(QueryResponse::GetDoubleSpendProof(result)) = resp {
+                let _ = result.clone()?;
+            };
+        }
+
+        // If there were no success or fail to the expected query,
+        // we check if there were any send errors.
+        for resp in responses {
+            let _ = resp?;
+        }
+
+        // If there was none of the above, then we had unexpected responses.
+        Err(Error::Protocol(ProtocolError::UnexpectedResponses))
+    }
+
     pub(crate) async fn send_to_closest(&self, request: Request) -> Result<Vec<Result<Response>>> {
+        Ok(self
+            .send_to_closest_with_peers(request)
+            .await?
+            .into_iter()
+            .map(|(_, resp)| resp)
+            .collect())
+    }
+
+    // As `send_to_closest`, pairing each response with the peer which sent it.
+    async fn send_to_closest_with_peers(
+        &self,
+        request: Request,
+    ) -> Result<Vec<(PeerId, Result<Response>)>> {
         info!("Sending {:?} to the closest peers.", request.dst());
         let closest_peers = self
             .network
@@ -194,31 +404,35 @@ impl Client {
         nodes: Vec<PeerId>,
         req: &Request,
         get_all_responses: bool,
-    ) -> Vec<Result<Response>> {
+    ) -> Vec<(PeerId, Result<Response>)> {
         let mut list_of_futures = Vec::new();
         for node in nodes {
-            let future = Box::pin(tokio::time::timeout(
-                Duration::from_secs(10),
-                self.network.send_request(req.clone(), node),
-            ));
+            let future = Box::pin(async move {
+                let res = tokio::time::timeout(
+                    Duration::from_secs(10),
+                    self.network.send_request(req.clone(), node),
+                )
+                .await;
+                (node, res)
+            });
             list_of_futures.push(future);
         }
 
         let mut responses = Vec::new();
         while !list_of_futures.is_empty() {
             match select_all(list_of_futures).await {
-                (Ok(res), _, remaining_futures) => {
+                ((node, Ok(res)), _, remaining_futures) => {
                     let res = res.map_err(Error::Network);
                     info!("Got response for the req: {req:?}, res: {res:?}");
                     // return the first successful response
                     if !get_all_responses && res.is_ok() {
-                        return vec![res];
+                        return vec![(node, res)];
                     }
-                    responses.push(res);
+                    responses.push((node, res));
                     list_of_futures = remaining_futures;
                 }
-                (Err(timeout_err), _, remaining_futures) => {
-                    responses.push(Err(Error::ResponseTimeout(timeout_err)));
+                ((node, Err(timeout_err)), _, remaining_futures) => {
+                    responses.push((node, Err(Error::ResponseTimeout(timeout_err))));
                     list_of_futures = remaining_futures;
                 }
             }
@@ -227,3 +441,17 @@ impl Client {
         responses
     }
 }
+
+// Two spends prove a double spend of the Dbc at the address if they're both
+// signed by its owner, but differ in their content.
+fn is_double_spend_proof(
+    address: DbcAddress,
+    a_spend: &SignedSpend,
+    b_spend: &SignedSpend,
+) -> bool {
+    a_spend.dbc_id() == b_spend.dbc_id()
+        && dbc_address(a_spend.dbc_id()) == address
+        && a_spend.spend.hash() != b_spend.spend.hash()
+        && a_spend.verify(a_spend.dst_tx_hash()).is_ok()
+        && b_spend.verify(b_spend.dst_tx_hash()).is_ok()
+}
@@ -40,4 +40,10 @@ pub enum Error {
         Entries hashes of branches are: {0:?}"
     )]
     ContentBranchDetected(BTreeSet<(EntryHash, Entry)>),
+
+    #[error("Entries to write atop of are not in the newest Register of the chain: {0:?}")]
+    EntriesNotInNewestRegister(BTreeSet<EntryHash>),
+
+    #[error("RegisterMap key is too big: {size} bytes serialised, max: {max}")]
+    MapKeyTooBig { size: usize, max: usize },
 }
@@ -0,0 +1,131 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{Client, WalletClient};
+
+use crate::protocol::{
+    address::dbc_address,
+    dbc_genesis::{create_genesis_dbc, genesis_src_tx, testnet_genesis_key},
+    messages::SpendQuery,
+    wallet::{read_dbc_file, write_dbc_file, Error, LocalWallet, Result},
+};
+
+use sn_dbc::{Dbc, PublicAddress, Token};
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// The amount sent per faucet request, in nanos: 100 tokens.
+const FAUCET_AMOUNT: u64 = 100 * 1_000_000_000;
+/// Filename for storing the genesis dbc, as only one can ever be spent.
+const GENESIS_DBC_FILENAME: &str = "genesis_dbc";
+/// Number of times to check whether the network has recorded the genesis spend.
+const GENESIS_SPEND_CHECKS: usize = 30;
+/// Time between the checks of whether the genesis spend has been recorded.
+const GENESIS_SPEND_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A faucet hands out test tokens on a local testnet.
+/// It holds the Genesis DBC in a wallet with the genesis key, and registers
+/// the spend of the genesis input with the network before the first send.
+pub struct Faucet {
+    client: Client,
+    wallet_client: WalletClient<LocalWallet>,
+    root_dir: PathBuf,
+    genesis_recorded: bool,
+}
+
+impl Faucet {
+    /// Loads the faucet wallet from the dir, creating it if there is none yet.
+    pub async fn load(client: Client, root_dir: &Path) -> Result<Self> {
+        tokio::fs::create_dir_all(root_dir).await?;
+        let wallet = match LocalWallet::restore_to(root_dir, testnet_genesis_key()?, None).await {
+            Err(Error::WalletAlreadyExists(_)) => LocalWallet::load_from(root_dir).await?,
+            result => result?,
+        };
+        Ok(Self {
+            client: client.clone(),
+            wallet_client: WalletClient::new(client, wallet),
+            root_dir: root_dir.to_path_buf(),
+            genesis_recorded: false,
+        })
+    }
+
+    /// Sends test tokens to the address, returning the dbc created for it, to be handed over.
+    pub async fn send(&mut self, to: PublicAddress) -> Result<Dbc> {
+        self.register_genesis().await?;
+        let (dbc, _) = self
+            .wallet_client
+            .send(Token::from_nano(FAUCET_AMOUNT), to)
+            .await?;
+        self.wallet_client.wallet().store().await?;
+        Ok(dbc)
+    }
+
+    // Create the genesis dbc if not done yet, and register the spend of its input with the network,
+    // waiting for it to be recorded, as the genesis dbc can't be spent till then.
+    async fn register_genesis(&mut self) -> Result<()> {
+        if self.genesis_recorded {
+            return Ok(());
+        }
+
+        let genesis_dbc = self.load_or_create_genesis_dbc().await?;
+        // The genesis dbc holds a single spend, the one of the genesis input.
+        let genesis_spend = genesis_dbc
+            .signed_spends
+            .iter()
+            .next()
+            .cloned()
+            .ok_or_else(|| Error::CouldNotSendTokens("The genesis dbc has no spend".to_string()))?;
+        let address = dbc_address(genesis_spend.dbc_id());
+
+        // Queued spends are accepted ones, so it's only sent if not found queued either.
+        let query = SpendQuery::GetPendingDbcSpend(address);
+        if self.client.get_spend(query).await.is_err() {
+            info!("Registering the genesis spend {address:?}");
+            let _ = self
+                .client
+                .spend_dbc(genesis_spend, genesis_src_tx(), BTreeMap::new())
+                .await?;
+        }
+
+        for _ in 0..GENESIS_SPEND_CHECKS {
+            let query = SpendQuery::GetDbcSpend(address);
+            if self.client.get_spend(query).await.is_ok() {
+                self.genesis_recorded = true;
+                break;
+            }
+            tokio::time::sleep(GENESIS_SPEND_CHECK_INTERVAL).await;
+        }
+        if !self.genesis_recorded {
+            return Err(Error::GenesisSpendNotRecorded(address));
+        }
+
+        // The genesis dbc is the very first deposit to the faucet wallet.
+        if self.wallet_client.wallet().ledger().is_empty() {
+            self.wallet_client
+                .verify_and_deposit(vec![genesis_dbc])
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn load_or_create_genesis_dbc(&self) -> Result<Dbc> {
+        let path = self.root_dir.join(GENESIS_DBC_FILENAME);
+        if path.is_file() {
+            return read_dbc_file(&path).await;
+        }
+        let genesis_dbc = create_genesis_dbc(&testnet_genesis_key()?)?;
+        // Stored before its spend is registered, as no other genesis dbc could be spent after that.
+        write_dbc_file(&path, &genesis_dbc).await?;
+        Ok(genesis_dbc)
+    }
+}
@@ -10,15 +10,19 @@ mod api;
 mod chunks;
 mod error;
 mod event;
+#[cfg(feature = "local-testnet")]
+mod faucet;
 mod file_apis;
 mod register;
 mod wallet;
 
+#[cfg(feature = "local-testnet")]
+pub use self::faucet::Faucet;
 pub use self::{
     error::Error,
     event::{ClientEvent, ClientEventsReceiver},
     file_apis::Files,
-    register::{Register, RegisterOffline},
+    register::{ChunkedRegister, Register, RegisterMap, RegisterOffline},
     wallet::WalletClient,
 };
 
@@ -0,0 +1,132 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Once a Register is full, a last "continued-at" entry is written onto it,
+//! pointing to the Register which continues it. The address of each Register
+//! of the chain is derived from the address of the first one and its position.
+//! Entries are stored as a `ChainEntry`, so user data is never mistaken for a "continued-at" one.
+//! Entries of Registers written before they were chained hold the user data as is,
+//! and are read as such.
+
+use super::super::error::Result;
+
+use crate::protocol::{address::RegisterAddress, register::Entry};
+
+use bincode::{serialize, DefaultOptions, Options};
+use serde::{Deserialize, Serialize};
+use xor_name::XorName;
+
+/// The bytes a `ChainEntry::Data` entry takes besides the data:
+/// the variant index and the length of the data, as serialised by bincode.
+/// The largest entry a user can write is thus 1012 bytes, i.e. `MAX_REG_ENTRY_SIZE` minus these.
+pub(super) const DATA_ENTRY_OVERHEAD: usize = 12;
+
+/// An entry of a Register of the chain, as stored on the network.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub(super) enum ChainEntry {
+    /// An entry written by the user.
+    Data(Entry),
+    /// The last entry of a full Register, pointing to the Register continuing it.
+    ContinuedAt(RegisterAddress),
+}
+
+impl ChainEntry {
+    /// Serialise the entry to be stored in a Register.
+    pub(super) fn encode(&self) -> Result<Entry> {
+        Ok(serialize(self)?)
+    }
+
+    /// Deserialise an entry stored in a Register.
+    /// Entries which are not exactly a serialised `ChainEntry` were written before Registers
+    /// were chained, i.e. hold the user data as is, so they are read as a `ChainEntry::Data`.
+    pub(super) fn decode(entry: &[u8]) -> Self {
+        DefaultOptions::new()
+            .with_fixint_encoding()
+            .reject_trailing_bytes()
+            .deserialize(entry)
+            .unwrap_or_else(|_| Self::Data(entry.to_vec()))
+    }
+}
+
+/// Return the address of the Register at position `generation` of the chain starting at `root`.
+pub(super) fn link_address(root: &RegisterAddress, generation: usize) -> RegisterAddress {
+    if generation == 0 {
+        return *root;
+    }
+    let mut bytes = root.name().0.to_vec();
+    bytes.extend_from_slice(&(generation as u64).to_be_bytes());
+    RegisterAddress::new(XorName::from_content(&bytes), root.tag())
+}
+
+/// Return the address of the next Register if the entry is a "continued-at" one.
+pub(super) fn continued_at(entry: &[u8]) -> Option<RegisterAddress> {
+    match ChainEntry::decode(entry) {
+        ChainEntry::ContinuedAt(address) => Some(address),
+        ChainEntry::Data(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_chain_link_addresses_are_derived_from_root() {
+        let root = RegisterAddress::new(XorName::random(&mut rand::thread_rng()), 43_000);
+
+        assert_eq!(link_address(&root, 0), root);
+        let first = link_address(&root, 1);
+        assert_eq!(first, link_address(&root, 1));
+        assert_ne!(first, root);
+        assert_ne!(first, link_address(&root, 2));
+        assert_eq!(first.tag(), root.tag());
+    }
+
+    #[test]
+    fn register_chain_continued_at_entry_roundtrip() -> eyre::Result<()> {
+        let next = RegisterAddress::new(XorName::random(&mut rand::thread_rng()), 43_000);
+
+        let entry = ChainEntry::ContinuedAt(next).encode()?;
+        assert_eq!(continued_at(&entry), Some(next));
+        assert_eq!(continued_at(b"some user entry"), None);
+
+        // User data is never taken for a "continued-at" entry, whatever its bytes.
+        let data = ChainEntry::Data(entry.clone()).encode()?;
+        assert_eq!(continued_at(&data), None);
+        assert_eq!(ChainEntry::decode(&data), ChainEntry::Data(entry));
+
+        Ok(())
+    }
+
+    #[test]
+    fn register_chain_legacy_entries_are_read_as_data() -> eyre::Result<()> {
+        let legacy = b"written before Registers were chained".to_vec();
+        assert_eq!(
+            ChainEntry::decode(&legacy),
+            ChainEntry::Data(legacy.clone())
+        );
+
+        // Nor is an entry holding more than a serialised `ChainEntry` taken for one.
+        let next = RegisterAddress::new(XorName::random(&mut rand::thread_rng()), 43_000);
+        let mut entry = ChainEntry::ContinuedAt(next).encode()?;
+        entry.push(0);
+        assert_eq!(ChainEntry::decode(&entry), ChainEntry::Data(entry.clone()));
+        assert_eq!(continued_at(&entry), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn register_chain_data_entry_overhead() -> eyre::Result<()> {
+        for size in [0, 1, 100] {
+            let entry = ChainEntry::Data(vec![0; size]).encode()?;
+            assert_eq!(entry.len(), size + DATA_ENTRY_OVERHEAD);
+        }
+        Ok(())
+    }
+}
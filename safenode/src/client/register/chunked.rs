@@ -0,0 +1,282 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    super::{error::Result, Client, Files},
+    chain::DATA_ENTRY_OVERHEAD,
+    Register,
+};
+
+use crate::protocol::{
+    address::ChunkAddress,
+    register::{Entry, EntryHash, Policy, MAX_REG_ENTRY_SIZE},
+};
+
+use bincode::{deserialize, serialize};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use xor_name::XorName;
+
+/// Where the values which don't fit in a Register entry are stored.
+#[async_trait::async_trait]
+pub(super) trait ValueStore: Sync {
+    /// Store the value, returning the address to point to it at.
+    async fn store(&self, value: Bytes) -> Result<ChunkAddress>;
+    /// Fetch the value stored at the address.
+    async fn fetch(&self, address: ChunkAddress) -> Result<Bytes>;
+}
+
+#[async_trait::async_trait]
+impl ValueStore for Files {
+    async fn store(&self, value: Bytes) -> Result<ChunkAddress> {
+        self.upload(value).await
+    }
+
+    async fn fetch(&self, address: ChunkAddress) -> Result<Bytes> {
+        self.read_bytes(address).await
+    }
+}
+
+/// A value stored in a Register entry, either inlined in the entry,
+/// or stored in chunks when it doesn't fit in it, with the entry pointing to them.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub(super) enum ChunkedEntry {
+    Inline(Vec<u8>),
+    Chunk(ChunkAddress),
+}
+
+impl ChunkedEntry {
+    /// Inline the value if it fits in an entry once serialised with `overhead` bytes
+    /// and stored as a data entry of the Register chain, otherwise upload it and point to it.
+    pub(super) async fn pack(
+        store: &impl ValueStore,
+        value: Vec<u8>,
+        overhead: usize,
+    ) -> Result<Self> {
+        let inline = Self::Inline(value);
+        let size = serialize(&inline)?.len() + overhead + DATA_ENTRY_OVERHEAD;
+        match inline {
+            Self::Inline(value) if size > MAX_REG_ENTRY_SIZE => {
+                let address = store.store(Bytes::from(value)).await?;
+                Ok(Self::Chunk(address))
+            }
+            _ => Ok(inline),
+        }
+    }
+
+    /// Return the value, fetching it from the network if it's stored in chunks.
+    pub(super) async fn unpack(self, store: &impl ValueStore) -> Result<Bytes> {
+        match self {
+            Self::Inline(value) => Ok(Bytes::from(value)),
+            Self::Chunk(address) => store.fetch(address).await,
+        }
+    }
+}
+
+/// A Register whose entries can be of any size. Values which don't fit in
+/// a Register entry are uploaded as chunks, storing a pointer to them in the entry
+/// instead, and pointers are transparently resolved back to the values when reading.
+/// Note all entries of the Register are expected to be written through this type.
+pub struct ChunkedRegister {
+    register: Register,
+    files: Files,
+}
+
+impl ChunkedRegister {
+    /// Create a new Register.
+    pub async fn create(client: Client, name: XorName, tag: u64) -> Result<Self> {
+        let register = Register::create(client, name, tag).await?;
+        Ok(Self::from(register))
+    }
+
+    /// Create a new Register, which only its owner can write to.
+    pub async fn create_owner_only(client: Client, name: XorName, tag: u64) -> Result<Self> {
+        let register = Register::create_owner_only(client, name, tag).await?;
+        Ok(Self::from(register))
+    }
+
+    /// Retrieve a Register from the network.
+    pub async fn retrieve(client: Client, name: XorName, tag: u64) -> Result<Self> {
+        let register = Register::retrieve(client, name, tag).await?;
+        Ok(Self::from(register))
+    }
+
+    /// Return the underlying Register, whose entries are the serialised values or pointers.
+    pub fn into_inner(self) -> Register {
+        self.register
+    }
+
+    /// Return the Policy of the Register.
+    pub fn policy(&self) -> &Policy {
+        self.register.policy()
+    }
+
+    /// Return the XorName of the Register.
+    pub fn name(&self) -> &XorName {
+        self.register.name()
+    }
+
+    /// Return the tag value of the Register.
+    pub fn tag(&self) -> u64 {
+        self.register.tag()
+    }
+
+    /// Return the number of items held in the register
+    pub fn size(&self) -> u64 {
+        self.register.size()
+    }
+
+    /// Return a value corresponding to the provided 'hash', if present.
+    pub async fn get(&self, hash: EntryHash) -> Result<Entry> {
+        let entry = self.register.get(hash)?;
+        self.resolve(&entry).await
+    }
+
+    /// Read the last entry, or entries when there are branches, if the register is not empty.
+    pub async fn read(&self) -> Result<BTreeSet<(EntryHash, Entry)>> {
+        let mut entries = BTreeSet::new();
+        for (hash, entry) in self.register.read() {
+            let _ = entries.insert((hash, self.resolve(&entry).await?));
+        }
+        Ok(entries)
+    }
+
+    /// Write a new value onto the Register atop latest value.
+    /// It returns an error if it finds branches in the content/entries; if it is
+    /// required to merge/resolve the branches, invoke the `write_merging_branches` API.
+    pub async fn write(&mut self, entry: &[u8]) -> Result<()> {
+        let entry = self.pack(entry).await?;
+        self.register.write(&entry).await
+    }
+
+    /// Write a new value onto the Register atop latest value.
+    /// If there are branches of content/entries, it automatically merges them
+    /// all leaving the new value as a single latest value of the Register.
+    pub async fn write_merging_branches(&mut self, entry: &[u8]) -> Result<()> {
+        let entry = self.pack(entry).await?;
+        self.register.write_merging_branches(&entry).await
+    }
+
+    /// Write a new value onto the Register atop the set of braches/entries
+    /// referenced by the provided list of their corresponding entry hash.
+    pub async fn write_atop(&mut self, entry: &[u8], children: BTreeSet<EntryHash>) -> Result<()> {
+        let entry = self.pack(entry).await?;
+        self.register.write_atop(&entry, children).await
+    }
+
+    /// Sync this Register with the replicas on the network.
+    pub async fn sync(&mut self) -> Result<()> {
+        self.register.sync().await
+    }
+
+    // ********* Private helpers  *********
+
+    async fn pack(&self, entry: &[u8]) -> Result<Vec<u8>> {
+        let value = ChunkedEntry::pack(&self.files, entry.to_vec(), 0).await?;
+        Ok(serialize(&value)?)
+    }
+
+    async fn resolve(&self, entry: &[u8]) -> Result<Entry> {
+        let value: ChunkedEntry = deserialize(entry)?;
+        Ok(value.unpack(&self.files).await?.to_vec())
+    }
+}
+
+impl From<Register> for ChunkedRegister {
+    fn from(register: Register) -> Self {
+        let files = Files::new(register.offline_reg.client().clone());
+        Self { register, files }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    /// Keeps the values in memory, in place of uploading them as chunks.
+    #[derive(Default)]
+    struct MemoryStore(Mutex<BTreeMap<ChunkAddress, Bytes>>);
+
+    #[async_trait::async_trait]
+    impl ValueStore for MemoryStore {
+        async fn store(&self, value: Bytes) -> Result<ChunkAddress> {
+            let address = ChunkAddress::new(XorName::from_content(&value));
+            let _ = self
+                .0
+                .lock()
+                .expect("Store lock to not be poisoned")
+                .insert(address, value);
+            Ok(address)
+        }
+
+        async fn fetch(&self, address: ChunkAddress) -> Result<Bytes> {
+            let value = self
+                .0
+                .lock()
+                .expect("Store lock to not be poisoned")
+                .get(&address)
+                .cloned()
+                .expect("Value to have been stored");
+            Ok(value)
+        }
+    }
+
+    // The bytes an `Inline` entry takes besides its value once stored: the variant index
+    // and the length, plus those of the data entry of the Register chain it's wrapped in.
+    fn inline_overhead() -> eyre::Result<usize> {
+        Ok(serialize(&ChunkedEntry::Inline(vec![]))?.len() + DATA_ENTRY_OVERHEAD)
+    }
+
+    #[tokio::test]
+    async fn chunked_entry_at_the_limit_is_inlined() -> eyre::Result<()> {
+        let store = MemoryStore::default();
+        let overhead = 16;
+        let value = vec![1; MAX_REG_ENTRY_SIZE - inline_overhead()? - overhead];
+
+        let entry = ChunkedEntry::pack(&store, value.clone(), overhead).await?;
+        assert_eq!(entry, ChunkedEntry::Inline(value));
+        assert_eq!(
+            serialize(&entry)?.len() + overhead + DATA_ENTRY_OVERHEAD,
+            MAX_REG_ENTRY_SIZE
+        );
+        assert!(store.0.lock().expect("Store lock").is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chunked_entry_one_byte_over_the_limit_is_a_pointer() -> eyre::Result<()> {
+        let store = MemoryStore::default();
+        let overhead = 16;
+        let value = vec![1; MAX_REG_ENTRY_SIZE - inline_overhead()? - overhead + 1];
+
+        let entry = ChunkedEntry::pack(&store, value.clone(), overhead).await?;
+        let address = ChunkAddress::new(XorName::from_content(&value));
+        assert_eq!(entry, ChunkedEntry::Chunk(address));
+        assert!(serialize(&entry)?.len() + overhead + DATA_ENTRY_OVERHEAD <= MAX_REG_ENTRY_SIZE);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chunked_entry_pack_and_unpack_round_trip() -> eyre::Result<()> {
+        let store = MemoryStore::default();
+        for size in [0, 10, MAX_REG_ENTRY_SIZE, 5 * MAX_REG_ENTRY_SIZE] {
+            let value: Vec<u8> = (0..size).map(|i| i as u8).collect();
+            let entry = ChunkedEntry::pack(&store, value.clone(), 0).await?;
+            // Entries are stored serialised in the Register.
+            let entry: ChunkedEntry = deserialize(&serialize(&entry)?)?;
+            assert_eq!(entry.unpack(&store).await?, Bytes::from(value));
+        }
+
+        Ok(())
+    }
+}
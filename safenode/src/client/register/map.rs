@@ -0,0 +1,351 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    super::{
+        error::{Error, Result},
+        Client, Files,
+    },
+    chain::{ChainEntry, DATA_ENTRY_OVERHEAD},
+    chunked::ChunkedEntry,
+    Register,
+};
+
+use crate::protocol::{
+    address::ChunkAddress,
+    register::{EntryHash, Register as RegisterReplica, MAX_REG_ENTRY_SIZE},
+};
+
+use bincode::{deserialize, serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+};
+use xor_name::XorName;
+
+/// An operation made on a `RegisterMap`, which is serialised and stored as a Register entry.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum MapOp {
+    /// Set the value of a key.
+    Put { key: Vec<u8>, value: ChunkedEntry },
+    /// Remove a key from the map.
+    Delete { key: Vec<u8> },
+}
+
+/// A typed key-value map stored on the network as a chain of Registers.
+///
+/// Each insert/remove is written as an entry on the Register, the current value of
+/// a key is resolved by walking the CRDT history. Concurrent writes to the same key
+/// are resolved deterministically, the one with the greatest entry hash wins.
+/// Values too big to fit in a Register entry are stored in chunks, and once a Register
+/// is full the map continues in the Register chained to it. That Register is only created
+/// when it's not found on the network, any other error retrieving it is returned by the write.
+pub struct RegisterMap<K, V> {
+    client: Client,
+    register: Register,
+    _types: PhantomData<(K, V)>,
+}
+
+impl<K, V> RegisterMap<K, V>
+where
+    K: Serialize + DeserializeOwned + Ord,
+    V: Serialize + DeserializeOwned,
+{
+    /// Create a new empty map on the network.
+    pub async fn create(client: Client, name: XorName, tag: u64) -> Result<Self> {
+        let register = Register::create(client.clone(), name, tag).await?;
+        Ok(Self {
+            client,
+            register,
+            _types: PhantomData,
+        })
+    }
+
+    /// Retrieve a map from the network, following the chain of Registers holding it.
+    pub async fn retrieve(client: Client, name: XorName, tag: u64) -> Result<Self> {
+        let register = Register::retrieve(client.clone(), name, tag).await?;
+        Ok(Self {
+            client,
+            register,
+            _types: PhantomData,
+        })
+    }
+
+    /// Return the XorName of the map.
+    pub fn name(&self) -> &XorName {
+        self.register.name()
+    }
+
+    /// Return the tag value of the map.
+    pub fn tag(&self) -> u64 {
+        self.register.tag()
+    }
+
+    /// Return the current value of the provided key, if present.
+    pub async fn get(&self, key: &K) -> Result<Option<V>> {
+        let key = serialize(key)?;
+        match self.resolve()?.remove(&key) {
+            Some(value) => Ok(Some(self.fetch_value(value).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return all the current key-value pairs of the map.
+    pub async fn entries(&self) -> Result<BTreeMap<K, V>> {
+        let mut entries = BTreeMap::new();
+        for (key, value) in self.resolve()? {
+            let _ = entries.insert(deserialize(&key)?, self.fetch_value(value).await?);
+        }
+        Ok(entries)
+    }
+
+    /// Set the value of the provided key.
+    /// Values which don't fit in a Register entry are uploaded as chunks.
+    /// Keys whose serialised size exceeds `max_key_size` are rejected with `Error::MapKeyTooBig`.
+    pub async fn insert(&mut self, key: &K, value: &V) -> Result<()> {
+        let key = serialize_key(key)?;
+        // Besides the value, a put op takes as many bytes as a delete op of the same key.
+        let overhead = serialize(&MapOp::Delete { key: key.clone() })?.len();
+        let value = ChunkedEntry::pack(&self.files(), serialize(value)?, overhead).await?;
+
+        self.write_op(&MapOp::Put { key, value }).await
+    }
+
+    /// Remove the provided key from the map.
+    pub async fn remove(&mut self, key: &K) -> Result<()> {
+        let op = MapOp::Delete {
+            key: serialize_key(key)?,
+        };
+        self.write_op(&op).await
+    }
+
+    /// Sync the map with the replicas on the network,
+    /// following any new Register other clients may have chained to it.
+    pub async fn sync(&mut self) -> Result<()> {
+        self.register.sync().await
+    }
+
+    // ********* Private helpers  *********
+
+    // Resolve the current (serialised) value of every key, applying the links in chain order.
+    fn resolve(&self) -> Result<BTreeMap<Vec<u8>, ChunkedEntry>> {
+        let mut map = BTreeMap::new();
+        for replica in self.register.replicas() {
+            for (key, value) in resolve_link(replica)? {
+                let _ = match value {
+                    Some(value) => map.insert(key, value),
+                    None => map.remove(&key),
+                };
+            }
+        }
+        Ok(map)
+    }
+
+    fn files(&self) -> Files {
+        Files::new(self.client.clone())
+    }
+
+    async fn fetch_value(&self, value: ChunkedEntry) -> Result<V> {
+        let bytes = value.unpack(&self.files()).await?;
+        Ok(deserialize(&bytes)?)
+    }
+
+    async fn write_op(&mut self, op: &MapOp) -> Result<()> {
+        self.register.write_merging_branches(&serialize(op)?).await
+    }
+}
+
+/// Return the max size of a serialised key, so that a put op of it fits in a Register entry
+/// whatever the value, as values which don't fit are replaced with a pointer to chunks.
+fn max_key_size() -> Result<usize> {
+    let op = MapOp::Put {
+        key: vec![],
+        value: ChunkedEntry::Chunk(ChunkAddress::new(XorName::default())),
+    };
+    Ok(MAX_REG_ENTRY_SIZE.saturating_sub(serialize(&op)?.len() + DATA_ENTRY_OVERHEAD))
+}
+
+/// Serialise the key, checking it fits in a Register entry along with any value.
+fn serialize_key<K: Serialize>(key: &K) -> Result<Vec<u8>> {
+    let key = serialize(key)?;
+    let max = max_key_size()?;
+    if key.len() > max {
+        return Err(Error::MapKeyTooBig {
+            size: key.len(),
+            max,
+        });
+    }
+    Ok(key)
+}
+
+/// Return all the ops stored in the Register, keyed by their entry hash.
+fn all_ops(register: &RegisterReplica) -> Result<BTreeMap<EntryHash, MapOp>> {
+    let mut ops = BTreeMap::new();
+    let mut visited = BTreeSet::new();
+    let mut pending: Vec<EntryHash> = register.read().into_iter().map(|(hash, _)| hash).collect();
+    while let Some(hash) = pending.pop() {
+        if !visited.insert(hash) {
+            continue;
+        }
+        pending.extend(register.children(hash)?);
+        if let ChainEntry::Data(entry) = ChainEntry::decode(register.get(hash)?) {
+            let _ = ops.insert(hash, deserialize(&entry)?);
+        }
+    }
+    Ok(ops)
+}
+
+/// Resolve the latest value of every key written on the Register, `None` meaning the key was deleted.
+fn resolve_link(register: &RegisterReplica) -> Result<BTreeMap<Vec<u8>, Option<ChunkedEntry>>> {
+    let ops = all_ops(register)?;
+
+    // The set of entries each entry was (directly or transitively) written atop of.
+    let mut ancestors: BTreeMap<EntryHash, BTreeSet<EntryHash>> = BTreeMap::new();
+    for hash in ops.keys() {
+        let mut found = BTreeSet::new();
+        let mut pending: Vec<EntryHash> = register.children(*hash)?.into_iter().collect();
+        while let Some(child) = pending.pop() {
+            if found.insert(child) {
+                pending.extend(register.children(child)?);
+            }
+        }
+        let _ = ancestors.insert(*hash, found);
+    }
+
+    let mut ops_per_key: BTreeMap<Vec<u8>, Vec<(EntryHash, Option<ChunkedEntry>)>> =
+        BTreeMap::new();
+    for (hash, op) in ops {
+        let (key, value) = match op {
+            MapOp::Put { key, value } => (key, Some(value)),
+            MapOp::Delete { key } => (key, None),
+        };
+        ops_per_key.entry(key).or_default().push((hash, value));
+    }
+
+    let mut resolved = BTreeMap::new();
+    for (key, key_ops) in ops_per_key {
+        // The latest ops of a key are the ones no other op of the same key was written atop of,
+        // when there are concurrent ones the greatest entry hash wins.
+        let latest = key_ops
+            .iter()
+            .filter(|(hash, _)| {
+                !key_ops
+                    .iter()
+                    .any(|(other, _)| ancestors[other].contains(hash))
+            })
+            .max_by_key(|(hash, _)| *hash);
+        if let Some((_, value)) = latest {
+            let _ = resolved.insert(key, value.clone());
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::protocol::register::{Policy, User};
+
+    use bls::SecretKey;
+
+    fn new_replica() -> RegisterReplica {
+        let owner = User::Key(SecretKey::random().public_key());
+        let policy = Policy {
+            owner,
+            permissions: BTreeMap::default(),
+        };
+        RegisterReplica::new(
+            owner,
+            XorName::random(&mut rand::thread_rng()),
+            43_000,
+            policy,
+        )
+    }
+
+    fn put(key: &str, value: &str) -> MapOp {
+        MapOp::Put {
+            key: key.as_bytes().to_vec(),
+            value: ChunkedEntry::Inline(value.as_bytes().to_vec()),
+        }
+    }
+
+    fn write_atop(
+        replica: &mut RegisterReplica,
+        op: &MapOp,
+        children: BTreeSet<EntryHash>,
+    ) -> eyre::Result<EntryHash> {
+        let entry = ChainEntry::Data(serialize(op)?).encode()?;
+        let (hash, _) = replica.write(entry, children)?;
+        Ok(hash)
+    }
+
+    #[test]
+    fn register_map_latest_op_per_key_wins() -> eyre::Result<()> {
+        let mut replica = new_replica();
+
+        let h1 = write_atop(&mut replica, &put("a", "1"), BTreeSet::new())?;
+        let h2 = write_atop(&mut replica, &put("b", "1"), [h1].into())?;
+        let h3 = write_atop(&mut replica, &put("a", "2"), [h2].into())?;
+        let delete = MapOp::Delete { key: b"b".to_vec() };
+        let _ = write_atop(&mut replica, &delete, [h3].into())?;
+
+        let resolved = resolve_link(&replica)?;
+        assert_eq!(
+            resolved.get(b"a".as_slice()),
+            Some(&Some(ChunkedEntry::Inline(b"2".to_vec())))
+        );
+        assert_eq!(resolved.get(b"b".as_slice()), Some(&None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn register_map_concurrent_puts_resolve_deterministically() -> eyre::Result<()> {
+        let mut replica = new_replica();
+
+        let root = write_atop(&mut replica, &put("a", "0"), BTreeSet::new())?;
+        let h1 = write_atop(&mut replica, &put("a", "1"), [root].into())?;
+        let h2 = write_atop(&mut replica, &put("a", "2"), [root].into())?;
+
+        let expected = if h1 > h2 { "1" } else { "2" };
+        let resolved = resolve_link(&replica)?;
+        assert_eq!(
+            resolved.get(b"a".as_slice()),
+            Some(&Some(ChunkedEntry::Inline(expected.as_bytes().to_vec())))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn register_map_keys_are_checked_against_max_size() -> eyre::Result<()> {
+        let max = max_key_size()?;
+        // A serialised `Vec<u8>` is its bytes prefixed with an 8 byte length.
+        let fitting = vec![0u8; max - 8];
+        let key = serialize_key(&fitting)?;
+        let op = MapOp::Put {
+            key,
+            value: ChunkedEntry::Chunk(ChunkAddress::new(XorName::default())),
+        };
+        assert_eq!(
+            serialize(&op)?.len() + DATA_ENTRY_OVERHEAD,
+            MAX_REG_ENTRY_SIZE
+        );
+
+        let too_big = vec![0u8; max - 7];
+        assert!(matches!(
+            serialize_key(&too_big),
+            Err(Error::MapKeyTooBig { size, max: reported }) if size == max + 1 && reported == max
+        ));
+
+        Ok(())
+    }
+}
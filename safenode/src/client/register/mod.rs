@@ -6,13 +6,23 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod chain;
+mod chunked;
+mod map;
 mod offline_replica;
 
-pub use offline_replica::RegisterOffline;
+pub use self::{chunked::ChunkedRegister, map::RegisterMap, offline_replica::RegisterOffline};
 
-use super::{error::Result, Client};
+use super::{
+    error::{Error, Result},
+    Client,
+};
 
-use crate::protocol::register::{Entry, EntryHash, Policy};
+use crate::protocol::{
+    address::RegisterAddress,
+    error::Error as ProtocolError,
+    register::{Entry, EntryHash, Policy, Register as RegisterReplica, MAX_REG_NUM_ENTRIES},
+};
 
 use std::collections::BTreeSet;
 use xor_name::XorName;
@@ -20,8 +30,17 @@ use xor_name::XorName;
 /// Operations made to a Register instance are applied not only locally,
 /// but also sent them to the replicas on the network. The user can
 /// switch offline-mode by invoking the `offline` API.
+///
+/// Once a Register is full, it's automatically continued in a new Register whose
+/// address is derived from this one's. Reads follow the whole chain of Registers,
+/// while writes are always made onto the newest one.
+/// Entries are stored along with a few bytes of chain framing, so a value
+/// written can be at most 1012 bytes, rather than the 1024 of a Register entry.
 pub struct Register {
+    // The newest Register of the chain, which writes are made onto.
     pub(super) offline_reg: RegisterOffline,
+    // The full Registers preceding the newest one in the chain, oldest first.
+    prev_links: Vec<RegisterOffline>,
 }
 
 impl Register {
@@ -29,20 +48,46 @@ impl Register {
     pub async fn create(client: Client, name: XorName, tag: u64) -> Result<Self> {
         let mut offline_reg = RegisterOffline::create(client, name, tag)?;
         offline_reg.push().await?;
-        Ok(Self { offline_reg })
+        Ok(Self {
+            offline_reg,
+            prev_links: vec![],
+        })
     }
 
-    /// Retrieve a Register from the network.
+    /// Create a new Register, which only its owner can write to.
+    /// The Registers continuing it once it's full are owner-only as well.
+    pub async fn create_owner_only(client: Client, name: XorName, tag: u64) -> Result<Self> {
+        let mut offline_reg = RegisterOffline::create_owner_only(client, name, tag)?;
+        offline_reg.push().await?;
+        Ok(Self {
+            offline_reg,
+            prev_links: vec![],
+        })
+    }
+
+    /// Retrieve a Register from the network, following the chain of Registers continuing it.
     pub async fn retrieve(client: Client, name: XorName, tag: u64) -> Result<Self> {
         let offline_reg = RegisterOffline::retrieve(client, name, tag).await?;
-        Ok(Self { offline_reg })
+        let mut reg = Self {
+            offline_reg,
+            prev_links: vec![],
+        };
+        reg.follow_chain().await?;
+        Ok(reg)
     }
 
     /// Switch to 'offline' mode where each op is made only locally.
+    /// Note the offline Register is the newest one of the chain.
     pub fn offline(self) -> RegisterOffline {
         RegisterOffline::from(self)
     }
 
+    /// Opt-in to store values of any size in the Register, uploading those
+    /// which don't fit in an entry as chunks and storing a pointer to them instead.
+    pub fn chunked(self) -> ChunkedRegister {
+        ChunkedRegister::from(self)
+    }
+
     /// Return the Policy of the Register.
     pub fn policy(&self) -> &Policy {
         self.offline_reg.policy()
@@ -50,33 +95,44 @@ impl Register {
 
     /// Return the XorName of the Register.
     pub fn name(&self) -> &XorName {
-        self.offline_reg.name()
+        self.root().name()
     }
 
     /// Return the tag value of the Register.
     pub fn tag(&self) -> u64 {
-        self.offline_reg.tag()
+        self.root().tag()
     }
 
-    /// Return the number of items held in the register
+    /// Return the number of items held in the register, across the whole chain.
     pub fn size(&self) -> u64 {
-        self.offline_reg.size()
+        self.links().map(|link| link.size()).sum()
     }
 
     /// Return a value corresponding to the provided 'hash', if present.
-    pub fn get(&self, hash: EntryHash) -> Result<&Entry> {
-        self.offline_reg.get(hash)
+    pub fn get(&self, hash: EntryHash) -> Result<Entry> {
+        self.offline_reg.get(hash).or_else(|err| {
+            self.prev_links
+                .iter()
+                .find_map(|link| link.get(hash).ok())
+                .ok_or(err)
+        })
     }
 
     /// Read the last entry, or entries when there are branches, if the register is not empty.
     pub fn read(&self) -> BTreeSet<(EntryHash, Entry)> {
-        self.offline_reg.read()
+        // The newest Register may still be empty if it was just chained by another client.
+        std::iter::once(&self.offline_reg)
+            .chain(self.prev_links.iter().rev())
+            .map(|link| link.read())
+            .find(|entries| !entries.is_empty())
+            .unwrap_or_default()
     }
 
     /// Write a new value onto the Register atop latest value.
     /// It returns an error if it finds branches in the content/entries; if it is
     /// required to merge/resolve the branches, invoke the `write_merging_branches` API.
     pub async fn write(&mut self, entry: &[u8]) -> Result<()> {
+        self.roll_over_if_full().await?;
         self.offline_reg.write(entry)?;
         self.offline_reg.push().await
     }
@@ -87,6 +143,7 @@ impl Register {
     /// Note you can use `write` API instead if you need to handle
     /// content/entries branches in a diffeerent way.
     pub async fn write_merging_branches(&mut self, entry: &[u8]) -> Result<()> {
+        self.roll_over_if_full().await?;
         self.offline_reg.write_merging_branches(entry)?;
         self.offline_reg.push().await
     }
@@ -95,13 +152,121 @@ impl Register {
     /// referenced by the provided list of their corresponding entry hash.
     /// Note you can use `write_merging_branches` API instead if you
     /// want to write atop all exiting branches/entries.
+    /// Only entries of the newest Register of the chain can be referenced, besides the
+    /// ones it was continued from, which it's implicitly written atop of.
+    /// An error is returned if any other entry is referenced.
     pub async fn write_atop(&mut self, entry: &[u8], children: BTreeSet<EntryHash>) -> Result<()> {
+        self.roll_over_if_full().await?;
+        let continued_from = self.continued_from();
+        let (children, not_in_newest): (BTreeSet<_>, BTreeSet<_>) = children
+            .into_iter()
+            .filter(|hash| !continued_from.contains(hash))
+            .partition(|hash| self.offline_reg.replica().get(*hash).is_ok());
+        if !not_in_newest.is_empty() {
+            return Err(Error::EntriesNotInNewestRegister(not_in_newest));
+        }
         self.offline_reg.write_atop(entry, children)?;
         self.offline_reg.push().await
     }
 
-    /// Sync this Register with the replicas on the network.
+    /// Sync this Register with the replicas on the network,
+    /// following any new Register other clients may have chained to it.
     pub async fn sync(&mut self) -> Result<()> {
-        self.offline_reg.sync().await
+        self.offline_reg.sync().await?;
+        self.follow_chain().await
+    }
+
+    // ********* Private helpers  *********
+
+    // Return the local replicas of all the Registers of the chain, oldest first.
+    pub(super) fn replicas(&self) -> impl Iterator<Item = &RegisterReplica> {
+        self.links().map(|link| link.replica())
+    }
+
+    fn links(&self) -> impl Iterator<Item = &RegisterOffline> {
+        self.prev_links
+            .iter()
+            .chain(std::iter::once(&self.offline_reg))
+    }
+
+    fn root(&self) -> &RegisterOffline {
+        self.prev_links.first().unwrap_or(&self.offline_reg)
+    }
+
+    // Return the address of the Register continuing the newest one, if it's already been chained.
+    fn continued_at(&self) -> Option<RegisterAddress> {
+        self.offline_reg
+            .replica()
+            .read()
+            .iter()
+            .find_map(|(_, entry)| chain::continued_at(entry))
+    }
+
+    // Return the entries the newest Register was continued from, i.e. the ones the
+    // "continued-at" entry of the Register preceding it was written atop of.
+    fn continued_from(&self) -> BTreeSet<EntryHash> {
+        let prev = match self.prev_links.last() {
+            Some(prev) => prev.replica(),
+            None => return BTreeSet::new(),
+        };
+        prev.read()
+            .into_iter()
+            .filter(|(_, entry)| chain::continued_at(entry).is_some())
+            .flat_map(|(hash, _)| prev.children(hash).unwrap_or_default())
+            .collect()
+    }
+
+    // Retrieve the Registers chained after the newest one, if any.
+    async fn follow_chain(&mut self) -> Result<()> {
+        while let Some(address) = self.continued_at() {
+            debug!("Register at {:?} continues at {address:?}", self.name());
+            let client = self.offline_reg.client().clone();
+            let next = RegisterOffline::retrieve(client, *address.name(), address.tag()).await?;
+            self.push_link(next);
+        }
+        Ok(())
+    }
+
+    // Chain a fresh Register if the newest one has room only for the "continued-at" entry.
+    async fn roll_over_if_full(&mut self) -> Result<()> {
+        if self.offline_reg.size() + 1 < MAX_REG_NUM_ENTRIES as u64 {
+            return Ok(());
+        }
+
+        // Another client may have already chained it.
+        self.offline_reg.sync().await?;
+        if self.continued_at().is_some() {
+            return self.follow_chain().await;
+        }
+
+        let root = RegisterAddress::new(*self.name(), self.tag());
+        let address = chain::link_address(&root, self.prev_links.len() + 1);
+        debug!("Register at {root:?} is full, continuing at {address:?}");
+
+        let client = self.offline_reg.client().clone();
+        let next =
+            match RegisterOffline::retrieve(client.clone(), *address.name(), address.tag()).await {
+                Ok(next) => next,
+                Err(Error::Protocol(ProtocolError::RegisterNotFound(_))) => {
+                    // The next Register has the same permissions as this one.
+                    let permissions = self.policy().permissions.clone();
+                    let mut next =
+                        RegisterOffline::new(client, *address.name(), address.tag(), permissions)?;
+                    next.push().await?;
+                    next
+                }
+                Err(err) => return Err(err),
+            };
+
+        self.offline_reg.write_continued_at(&address)?;
+        self.offline_reg.push().await?;
+        self.push_link(next);
+
+        Ok(())
+    }
+
+    fn push_link(&mut self, next: RegisterOffline) {
+        let full = std::mem::replace(&mut self.offline_reg, next);
+        self.prev_links.push(full);
     }
 }
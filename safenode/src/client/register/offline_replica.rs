@@ -8,6 +8,7 @@
 
 use super::{
     super::error::{Error, Result},
+    chain::ChainEntry,
     Client, Register,
 };
 
@@ -23,7 +24,7 @@ use crate::protocol::{
 };
 
 use bincode::serialize;
-use std::collections::{BTreeSet, LinkedList};
+use std::collections::{BTreeMap, BTreeSet, LinkedList};
 use xor_name::XorName;
 
 /// Ops made to an offline Register instance are applied locally only,
@@ -33,12 +34,23 @@ pub struct RegisterOffline {
     client: Client,
     register: RegisterReplica,
     ops: LinkedList<RegisterCmd>, // Cached operations.
+    // The full Registers preceding this one in its chain, oldest first,
+    // kept to restore the chain when switching back to 'online' mode.
+    prev_links: Vec<RegisterOffline>,
 }
 
 impl RegisterOffline {
     /// Create a new Register offline.
     pub fn create(client: Client, name: XorName, tag: u64) -> Result<Self> {
-        Self::new(client, name, tag)
+        let permissions = [(User::Anyone, Permissions::new(true))]
+            .into_iter()
+            .collect();
+        Self::new(client, name, tag, permissions)
+    }
+
+    /// Create a new Register offline, which only its owner can write to.
+    pub fn create_owner_only(client: Client, name: XorName, tag: u64) -> Result<Self> {
+        Self::new(client, name, tag, BTreeMap::new())
     }
 
     /// Retrieve a Register from the network to work on it offline.
@@ -49,6 +61,7 @@ impl RegisterOffline {
             client,
             register,
             ops: LinkedList::new(),
+            prev_links: vec![],
         })
     }
 
@@ -58,13 +71,18 @@ impl RegisterOffline {
             client: replica.offline_reg.client,
             register: replica.offline_reg.register,
             ops: LinkedList::new(),
+            prev_links: replica.prev_links,
         }
     }
 
     /// Switch to 'online' mode where each op made locally is immediatelly pushed to the network.
     pub async fn online(mut self) -> Result<Register> {
         self.push().await?;
-        Ok(Register { offline_reg: self })
+        let prev_links = std::mem::take(&mut self.prev_links);
+        Ok(Register {
+            offline_reg: self,
+            prev_links,
+        })
     }
 
     /// Return the Policy of the Register.
@@ -88,26 +106,59 @@ impl RegisterOffline {
     }
 
     /// Return a value corresponding to the provided 'hash', if present.
-    pub fn get(&self, hash: EntryHash) -> Result<&Entry> {
-        let entry = self.register.get(hash)?;
-        Ok(entry)
+    pub fn get(&self, hash: EntryHash) -> Result<Entry> {
+        match ChainEntry::decode(self.register.get(hash)?) {
+            ChainEntry::Data(entry) => Ok(entry),
+            ChainEntry::ContinuedAt(_) => Err(ProtocolError::NoSuchEntry(hash).into()),
+        }
     }
 
     /// Read the last entry, or entries when there are branches, if the register is not empty.
+    /// The "continued-at" entry of a full Register is replaced with the ones it was written atop of.
     pub fn read(&self) -> BTreeSet<(EntryHash, Entry)> {
-        self.register.read()
+        let mut entries = BTreeSet::new();
+        for (hash, entry) in self.register.read() {
+            let hashes = match ChainEntry::decode(&entry) {
+                ChainEntry::Data(entry) => {
+                    let _ = entries.insert((hash, entry));
+                    continue;
+                }
+                ChainEntry::ContinuedAt(_) => self.register.children(hash).unwrap_or_default(),
+            };
+            for hash in hashes {
+                if let Ok(entry) = self.get(hash) {
+                    let _ = entries.insert((hash, entry));
+                }
+            }
+        }
+        entries
+    }
+
+    /// Return the client used to sync the Register with the network.
+    pub(super) fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Return the local replica of the Register.
+    pub(super) fn replica(&self) -> &RegisterReplica {
+        &self.register
     }
 
     /// Write a new value onto the Register atop latest value.
     /// It returns an error if it finds branches in the content/entries; if it is
     /// required to merge/resolve the branches, invoke the `write_merging_branches` API.
     pub fn write(&mut self, entry: &[u8]) -> Result<()> {
-        let children = self.register.read();
+        let children: BTreeSet<EntryHash> = self
+            .register
+            .read()
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect();
         if children.len() > 1 {
-            return Err(Error::ContentBranchDetected(children));
+            return Err(Error::ContentBranchDetected(self.read()));
         }
 
-        self.write_atop(entry, children.into_iter().map(|(hash, _)| hash).collect())
+        self.write_atop(entry, children)
     }
 
     /// Write a new value onto the Register atop latest value.
@@ -130,13 +181,36 @@ impl RegisterOffline {
     /// referenced by the provided list of their corresponding entry hash.
     /// Note you can use `write_merging_branches` API instead if you
     /// want to write atop all exiting branches/entries.
+    /// The value is stored along with a few bytes of chain framing,
+    /// so it can be at most 1012 bytes, rather than the 1024 of a Register entry.
     pub fn write_atop(&mut self, entry: &[u8], children: BTreeSet<EntryHash>) -> Result<()> {
+        self.write_chain_entry(&ChainEntry::Data(entry.to_vec()), children)
+    }
+
+    /// Write the last entry of this Register once it's full,
+    /// pointing to the Register continuing it, atop all branches/entries.
+    pub(super) fn write_continued_at(&mut self, next: &RegisterAddress) -> Result<()> {
+        let children = self
+            .register
+            .read()
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect();
+        self.write_chain_entry(&ChainEntry::ContinuedAt(*next), children)
+    }
+
+    // Sign and cache the op writing the entry atop the children, to be pushed to the network.
+    fn write_chain_entry(
+        &mut self,
+        entry: &ChainEntry,
+        children: BTreeSet<EntryHash>,
+    ) -> Result<()> {
         // we need to check permissions first
         let public_key = self.client.signer_pk();
         self.register
             .check_permissions(Action::Write, Some(User::Key(public_key)))?;
 
-        let (_hash, edit) = self.register.write(entry.into(), children)?;
+        let (_hash, edit) = self.register.write(entry.encode()?, children)?;
         let op = EditRegister {
             address: *self.register.address(),
             edit,
@@ -193,15 +267,15 @@ impl RegisterOffline {
     // ********* Private helpers  *********
 
     // Create a new RegisterOffline instance with the given name and tag.
-    fn new(client: Client, name: XorName, tag: u64) -> Result<Self> {
+    pub(super) fn new(
+        client: Client,
+        name: XorName,
+        tag: u64,
+        permissions: BTreeMap<User, Permissions>,
+    ) -> Result<Self> {
         let public_key = client.signer_pk();
         let owner = User::Key(public_key);
-        let policy = Policy {
-            owner,
-            permissions: [(User::Anyone, Permissions::new(true))]
-                .into_iter()
-                .collect(),
-        };
+        let policy = Policy { owner, permissions };
 
         let op = CreateRegister {
             name,
@@ -219,6 +293,7 @@ impl RegisterOffline {
             client,
             register,
             ops: LinkedList::from([create_cmd]),
+            prev_links: vec![],
         };
 
         Ok(reg)
@@ -300,7 +375,7 @@ impl RegisterOffline {
 
         // If no register was gotten, we will return the first error sent to us.
         for resp in responses.iter().flatten() {
-            if let Response::Query(QueryResponse::GetChunk(result)) = resp {
+            if let Response::Query(QueryResponse::GetRegister(result)) = resp {
                 let _ = result.clone()?;
             };
         }
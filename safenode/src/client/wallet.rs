@@ -6,14 +6,25 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use sn_dbc::{Dbc, DbcIdSource, DerivedKey, PublicAddress, Token};
+use sn_dbc::{Dbc, DbcIdSource, DerivedKey, PublicAddress, SignedSpend, Token};
 
-use crate::protocol::{
-    transfers::{create_online_transfer, Outputs as TransferDetails},
-    wallet::{Result, SendClient, SendWallet},
+use crate::{
+    network_transfers::Error as TransferError,
+    protocol::{
+        address::dbc_address,
+        error::Error as ProtocolError,
+        fees::SpendQueuePosition,
+        messages::SpendQuery,
+        transfers::{create_online_transfer, Outputs as TransferDetails},
+        wallet::{
+            Error, ReconcileWallet, Reconciliation, Result, SendClient, SendWallet, SpendStatus,
+        },
+    },
 };
 
-use super::Client;
+use super::{Client, Error as ClientError};
+
+use std::collections::{BTreeMap, BTreeSet};
 
 /// A wallet client can be used to send and
 /// receive tokens to/from other wallets.
@@ -28,10 +39,104 @@ impl<W: SendWallet> WalletClient<W> {
         Self { client, wallet }
     }
 
+    /// The wallet used by the client.
+    pub fn wallet(&self) -> &W {
+        &self.wallet
+    }
+
     /// Send tokens to another wallet.
-    pub async fn send(&mut self, amount: Token, to: PublicAddress) -> Result<()> {
-        let _dbcs = self.wallet.send(vec![(amount, to)], &self.client).await?;
-        Ok(())
+    /// Returns the dbc created for the recipient, to be handed to them, and when the spends
+    /// will have been recorded by the network, if they were sent to it.
+    pub async fn send(
+        &mut self,
+        amount: Token,
+        to: PublicAddress,
+    ) -> Result<(Dbc, Option<SpendQueuePosition>)> {
+        let dbc_id_src = to.random_dbc_id_src(&mut rand::thread_rng());
+        self.send_to(amount, dbc_id_src).await
+    }
+
+    /// Send tokens to the dbc id the recipient generated to receive them.
+    /// Returns the dbc created for the recipient, to be handed to them, and when the spends
+    /// will have been recorded by the network, i.e. the position of the last one to be recorded.
+    pub async fn send_to(
+        &mut self,
+        amount: Token,
+        to: DbcIdSource,
+    ) -> Result<(Dbc, Option<SpendQueuePosition>)> {
+        let dbc_id = to.dbc_id();
+        let (created_dbcs, positions) = self.wallet.send(vec![(amount, to)], &self.client).await?;
+        let dbc = created_dbcs
+            .into_iter()
+            .map(|created| created.dbc)
+            .find(|dbc| dbc.id() == dbc_id)
+            .ok_or_else(|| {
+                Error::CouldNotSendTokens(format!("no dbc was created for dbc id {dbc_id:?}"))
+            })?;
+        let last_recorded = positions.into_values().max_by_key(|position| position.eta);
+        Ok((dbc, last_recorded))
+    }
+
+    /// Deposit the received dbcs, once they've all been verified against the network,
+    /// i.e. once the spends of the inputs of the tx which created each of them have been
+    /// found by a majority of their close groups, recorded or still queued, and that tx
+    /// checks out with them, and no spend of the dbcs themselves has been found either,
+    /// i.e. they are still unspent. The wallet is then persisted.
+    /// None of the dbcs are deposited if any of them fails verification.
+    pub async fn verify_and_deposit(&mut self, dbcs: Vec<Dbc>) -> Result<()> {
+        for dbc in &dbcs {
+            verify(&self.client, dbc).await?;
+        }
+        self.wallet.deposit_and_store(dbcs).await
+    }
+}
+
+impl<W: ReconcileWallet> WalletClient<W> {
+    /// Reconcile the wallet with the network, after a crash in the middle of a send,
+    /// or a spend the network rejected: the available dbcs found spent are moved to the
+    /// spent ones, the spent dbcs whose spend was never accepted are made available again,
+    /// and the double spent ones are reported. The reconciled wallet is then persisted.
+    /// The dbcs whose status could not be found out are left untouched.
+    pub async fn reconcile(&mut self) -> Result<Reconciliation> {
+        let mut statuses = BTreeMap::new();
+        for dbc_id in self.wallet.dbc_ids() {
+            // Queued spends are accepted ones, so they're looked up as well.
+            // A dbc is only unspent if a majority of its close group did not find its spend,
+            // and only double spent if a majority of it reported so, and the conflicting
+            // spends it holds prove it. Otherwise its status is left as is.
+            let query = SpendQuery::GetPendingDbcSpend(dbc_address(&dbc_id));
+            let status = match self.client.get_spend(query).await {
+                Ok(_) => SpendStatus::Spent,
+                Err(ClientError::Protocol(ProtocolError::Transfers(
+                    TransferError::SpendNotFound(_),
+                ))) => SpendStatus::Unspent,
+                Err(ClientError::Protocol(ProtocolError::Transfers(
+                    TransferError::DoubleSpendDetected(address),
+                ))) => {
+                    match self.client.get_double_spend_proof(address).await {
+                        Ok(_) => SpendStatus::DoubleSpent,
+                        Err(err) => {
+                            warn!("Could not get the proof of dbc {dbc_id:?} being double spent: {err}");
+                            continue;
+                        }
+                    }
+                }
+                Err(err) => {
+                    warn!("Could not get the spend status of dbc {dbc_id:?}: {err}");
+                    continue;
+                }
+            };
+            let _ = statuses.insert(dbc_id, status);
+        }
+
+        let reconciliation = self.wallet.reconcile(statuses).await?;
+        if !reconciliation.double_spent.is_empty() {
+            warn!(
+                "Double spent dbcs found in the wallet: {:?}",
+                reconciliation.double_spent
+            );
+        }
+        Ok(reconciliation)
     }
 }
 
@@ -43,10 +148,200 @@ impl SendClient for Client {
         to: Vec<(Token, DbcIdSource)>,
         change_to: PublicAddress,
     ) -> Result<TransferDetails> {
-        let transfer = create_online_transfer(dbcs, to, change_to, self).await?;
+        let src_txs: BTreeMap<_, _> = dbcs
+            .iter()
+            .map(|(dbc, _)| (dbc.id(), dbc.src_tx.clone()))
+            .collect();
+
+        let mut transfer = create_online_transfer(dbcs, to, change_to, self).await?;
 
         // Upload the spends to the network:
+        // all the created dbcs hold the same spends, i.e. those of the inputs of the tx.
+        // Should any of them not be accepted, the wallet can be reconciled with the network.
+        let signed_spends: BTreeSet<_> = transfer
+            .created_dbcs
+            .iter()
+            .map(|created| &created.dbc)
+            .chain(transfer.change_dbc.iter())
+            .flat_map(|dbc| &dbc.signed_spends)
+            .collect();
+        for signed_spend in signed_spends {
+            let dbc_id = signed_spend.dbc_id();
+            let source_tx = src_txs.get(dbc_id).cloned().ok_or_else(|| {
+                Error::CouldNotSendTokens(format!("no source tx for input dbc {dbc_id:?}"))
+            })?;
+            let fee_ciphers = transfer
+                .fee_ciphers
+                .get(dbc_id)
+                .cloned()
+                .unwrap_or_default();
+            let position = self
+                .spend_dbc(signed_spend.clone(), source_tx, fee_ciphers)
+                .await
+                .map_err(|err| {
+                    Error::CouldNotSendTokens(format!(
+                        "the spend of input dbc {dbc_id:?} was not accepted: {err}"
+                    ))
+                })?;
+            let _ = transfer.spend_queue_positions.insert(*dbc_id, position);
+        }
 
         Ok(transfer)
     }
 }
+
+// Verify that the dbc was created in a tx whose inputs were all spent on the network,
+// and that the dbc itself has not been spent yet.
+// Spends still queued are accepted ones, so they're looked up as well, as to verify a dbc
+// right after it was sent, the spends of its parents only being recorded later on.
+async fn verify<C: SpendsClient>(client: &C, dbc: &Dbc) -> Result<()> {
+    let dbc_id = dbc.id();
+    let src_tx = &dbc.src_tx;
+    if !src_tx
+        .outputs
+        .iter()
+        .any(|output| output.dbc_id() == &dbc_id)
+    {
+        return Err(Error::CouldNotVerifyTransfer(format!(
+            "dbc {dbc_id:?} is not an output of its source tx"
+        )));
+    }
+
+    // Fetch the parent spends, i.e. the spends of each input of the source tx.
+    let src_tx_hash = src_tx.hash();
+    let mut parent_blinded_amounts = Vec::with_capacity(src_tx.inputs.len());
+    for input in &src_tx.inputs {
+        let address = dbc_address(&input.dbc_id());
+        let query = SpendQuery::GetPendingDbcSpend(address);
+        let parent_spend = client.get_spend(query).await.map_err(|err| {
+            Error::CouldNotVerifyTransfer(format!(
+                "parent spend {address:?} of dbc {dbc_id:?} not found: {err}"
+            ))
+        })?;
+        // The parent must have been spent in the tx which created the dbc.
+        if parent_spend.dst_tx_hash() != src_tx_hash {
+            return Err(Error::CouldNotVerifyTransfer(format!(
+                "parent spend {address:?} of dbc {dbc_id:?} was spent in another tx"
+            )));
+        }
+        parent_blinded_amounts.push(parent_spend.spend.blinded_amount);
+    }
+
+    src_tx.verify(&parent_blinded_amounts).map_err(|err| {
+        Error::CouldNotVerifyTransfer(format!("invalid source tx of dbc {dbc_id:?}: {err}"))
+    })?;
+
+    // The dbc must not have been spent already, e.g. by a sender handing out a copy of it.
+    let address = dbc_address(&dbc_id);
+    match client
+        .get_spend(SpendQuery::GetPendingDbcSpend(address))
+        .await
+    {
+        Err(ClientError::Protocol(ProtocolError::Transfers(TransferError::SpendNotFound(_)))) => {
+            Ok(())
+        }
+        Ok(_) => Err(Error::CouldNotVerifyTransfer(format!(
+            "dbc {dbc_id:?} has already been spent"
+        ))),
+        Err(err) => Err(Error::CouldNotVerifyTransfer(format!(
+            "could not check that dbc {dbc_id:?} is unspent: {err}"
+        ))),
+    }
+}
+
+// Looks up the spends of dbcs on the network, as to verify the dbcs received before depositing them.
+// For tests the implementation can be without network connection.
+#[async_trait::async_trait]
+trait SpendsClient: Send + Sync {
+    // Retrieve a spend, as agreed on by a majority of the close group of the dbc.
+    async fn get_spend(&self, query: SpendQuery) -> std::result::Result<SignedSpend, ClientError>;
+}
+
+#[async_trait::async_trait]
+impl SpendsClient for Client {
+    async fn get_spend(&self, query: SpendQuery) -> std::result::Result<SignedSpend, ClientError> {
+        Client::get_spend(self, query).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::protocol::{dbc_genesis::create_genesis_dbc, transfers::create_offline_transfer};
+
+    use eyre::{eyre, Result};
+    use sn_dbc::MainKey;
+
+    #[tokio::test]
+    async fn dbc_is_verified_right_after_it_was_sent() -> Result<()> {
+        let sender_key = MainKey::random();
+        let genesis = create_genesis_dbc(&sender_key)?;
+        let derived_key = genesis.derived_key(&sender_key)?;
+
+        let dbc_id_src = MainKey::random().random_dbc_id_src(&mut rand::thread_rng());
+        let transfer = create_offline_transfer(
+            vec![(genesis, derived_key)],
+            vec![(Token::from_nano(100), dbc_id_src)],
+            sender_key.public_address(),
+        )?;
+        let dbc = transfer
+            .created_dbcs
+            .first()
+            .map(|created| created.dbc.clone())
+            .ok_or_else(|| eyre!("No dbc was created for the recipient"))?;
+
+        // The spend of the parent was accepted by the network, but is still queued.
+        let client = MockSpendsClient {
+            recorded: BTreeSet::new(),
+            queued: dbc.signed_spends.clone(),
+        };
+        verify(&client, &dbc).await?;
+
+        // Once recorded it still is.
+        let client = MockSpendsClient {
+            recorded: dbc.signed_spends.clone(),
+            queued: BTreeSet::new(),
+        };
+        verify(&client, &dbc).await?;
+
+        // But not if the spend of the parent was not accepted.
+        let client = MockSpendsClient {
+            recorded: BTreeSet::new(),
+            queued: BTreeSet::new(),
+        };
+        assert!(matches!(
+            verify(&client, &dbc).await,
+            Err(Error::CouldNotVerifyTransfer(_))
+        ));
+
+        Ok(())
+    }
+
+    // Serves the spends as a node would, the queued ones only when pending spends are queried.
+    struct MockSpendsClient {
+        recorded: BTreeSet<SignedSpend>,
+        queued: BTreeSet<SignedSpend>,
+    }
+
+    #[async_trait::async_trait]
+    impl SpendsClient for MockSpendsClient {
+        async fn get_spend(
+            &self,
+            query: SpendQuery,
+        ) -> std::result::Result<SignedSpend, ClientError> {
+            let address = query.dst();
+            let mut spends: Vec<_> = self.recorded.iter().collect();
+            if let SpendQuery::GetPendingDbcSpend(_) = query {
+                spends.extend(&self.queued);
+            }
+            spends
+                .into_iter()
+                .find(|spend| dbc_address(spend.dbc_id()) == address)
+                .cloned()
+                .ok_or(ClientError::Protocol(ProtocolError::Transfers(
+                    TransferError::SpendNotFound(address),
+                )))
+        }
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Faults injected into nodes built with the `chaos` feature, i.e. random restarts, connection
+//! drops and response delays, to exercise replication and client retries on a local network.
+//! They're configured through the following environment variables:
+//!
+//! - `SAFENODE_CHAOS_INTERVAL_MS`: minimum time between two rolls of the dice for restarts and
+//!   connection drops, defaults to 1000.
+//! - `SAFENODE_CHAOS_RESTART_PROBABILITY`: probability of restarting at each roll, defaults to 0.002.
+//! - `SAFENODE_CHAOS_DROP_PROBABILITY`: probability of dropping the connection with a random peer
+//!   at each roll, defaults to 0.01.
+//! - `SAFENODE_CHAOS_MAX_RESPONSE_DELAY_MS`: maximum delay added to each response, defaults to 500.
+
+use super::{Network, SwarmDriver};
+
+use libp2p::PeerId;
+use rand::{seq::IteratorRandom, Rng};
+use std::{
+    env,
+    process::{self, Command, Stdio},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+const INTERVAL_VAR: &str = "SAFENODE_CHAOS_INTERVAL_MS";
+const RESTART_PROBABILITY_VAR: &str = "SAFENODE_CHAOS_RESTART_PROBABILITY";
+const DROP_PROBABILITY_VAR: &str = "SAFENODE_CHAOS_DROP_PROBABILITY";
+const MAX_RESPONSE_DELAY_VAR: &str = "SAFENODE_CHAOS_MAX_RESPONSE_DELAY_MS";
+
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const DEFAULT_RESTART_PROBABILITY: f64 = 0.002;
+const DEFAULT_DROP_PROBABILITY: f64 = 0.01;
+const DEFAULT_MAX_RESPONSE_DELAY_MS: u64 = 500;
+
+/// How often and how badly a node misbehaves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ChaosConfig {
+    interval: Duration,
+    restart_probability: f64,
+    drop_probability: f64,
+    max_response_delay: Duration,
+}
+
+impl ChaosConfig {
+    /// Reads the config from the environment, using the defaults for the variables not set.
+    pub(crate) fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            interval: Duration::from_millis(parse_var(&var, INTERVAL_VAR, DEFAULT_INTERVAL_MS)),
+            restart_probability: parse_var(
+                &var,
+                RESTART_PROBABILITY_VAR,
+                DEFAULT_RESTART_PROBABILITY,
+            ),
+            drop_probability: parse_var(&var, DROP_PROBABILITY_VAR, DEFAULT_DROP_PROBABILITY),
+            max_response_delay: Duration::from_millis(parse_var(
+                &var,
+                MAX_RESPONSE_DELAY_VAR,
+                DEFAULT_MAX_RESPONSE_DELAY_MS,
+            )),
+        }
+    }
+}
+
+fn parse_var<T: FromStr + Copy>(var: impl Fn(&str) -> Option<String>, name: &str, default: T) -> T {
+    match var(name).map(|value| value.parse()) {
+        Some(Ok(value)) => value,
+        Some(Err(_)) => {
+            warn!("Invalid value for {name}, using the default one");
+            default
+        }
+        None => default,
+    }
+}
+
+/// Chaos config of a node, along with when it last rolled the dice.
+#[derive(Debug)]
+pub(crate) struct Chaos {
+    config: ChaosConfig,
+    last_roll: Instant,
+}
+
+impl Chaos {
+    pub(crate) fn new(config: ChaosConfig) -> Self {
+        info!("Chaos enabled: {config:?}");
+        Self {
+            config,
+            last_roll: Instant::now(),
+        }
+    }
+}
+
+impl SwarmDriver {
+    /// Restart the node or drop the connection with a random peer, at random,
+    /// once every chaos interval at most.
+    pub(super) fn cause_chaos(&mut self) {
+        let config = match &mut self.chaos {
+            Some(chaos) if chaos.last_roll.elapsed() >= chaos.config.interval => {
+                chaos.last_roll = Instant::now();
+                chaos.config
+            }
+            _ => return,
+        };
+
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(config.restart_probability.clamp(0.0, 1.0)) {
+            restart(self.swarm.local_peer_id());
+        }
+        if rng.gen_bool(config.drop_probability.clamp(0.0, 1.0)) {
+            if let Some(peer) = self.swarm.connected_peers().choose(&mut rng).cloned() {
+                warn!("Dropping the connection with {peer:?} at random!");
+                let _ = self.swarm.disconnect_peer_id(peer);
+            }
+        }
+    }
+}
+
+impl Network {
+    /// Wait for a random delay before sending a response.
+    pub(super) async fn delay_response(&self) {
+        if let Some(config) = self.chaos {
+            let max = config.max_response_delay.as_millis() as u64;
+            let delay = Duration::from_millis(rand::thread_rng().gen_range(0..=max));
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Restarts the whole program.
+///
+/// This provides a way to test the network layer's ability to recover from
+/// unexpected shutdowns. The current process is deliberately killed right after
+/// the new one is spawned, i.e. without the graceful shutdown handing data over to
+/// other nodes, as a crash would. If the new process can't be spawned, the node keeps running.
+fn restart(peer_id: &PeerId) {
+    warn!("Restarting {peer_id:?} at random!");
+
+    let ten_millis = std::time::Duration::from_millis(10);
+    std::thread::sleep(ten_millis);
+
+    // Get the current executable's path
+    let executable = match env::current_exe() {
+        Ok(executable) => executable,
+        Err(err) => {
+            error!("Failed to get the current executable path, not restarting: {err}");
+            return;
+        }
+    };
+
+    // Spawn a new process to restart the binary with the same arguments and environment
+    let spawned = Command::new(&executable)
+        .args(env::args().skip(1))
+        .envs(env::vars())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(err) = spawned {
+        error!("Failed to spawn {executable:?}, not restarting: {err}");
+        return;
+    }
+
+    info!("Spawned executable: {executable:?}");
+    // Hard kill the current process now that we've spawned a new one, skipping the graceful shutdown.
+    process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chaos_config_falls_back_to_defaults() {
+        let config = ChaosConfig::from_vars(|name| match name {
+            RESTART_PROBABILITY_VAR => Some("0.5".to_string()),
+            DROP_PROBABILITY_VAR => Some("not a number".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            config,
+            ChaosConfig {
+                interval: Duration::from_millis(DEFAULT_INTERVAL_MS),
+                restart_probability: 0.5,
+                drop_probability: DEFAULT_DROP_PROBABILITY,
+                max_response_delay: Duration::from_millis(DEFAULT_MAX_RESPONSE_DELAY_MS),
+            }
+        );
+    }
+}
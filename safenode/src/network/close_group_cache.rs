@@ -0,0 +1,390 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Resolving the close group of a target without an iterative Kademlia query where possible,
+//! either from the results of a previous query for the same or a nearby target, or from our
+//! own k-buckets when the target is close enough to us for them to be complete.
+
+use super::{SwarmDriver, CLOSE_GROUP_SIZE};
+
+use libp2p::{kad::KBucketKey, PeerId};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+// Cached groups are dropped after this long, as a backstop for the churn we didn't witness.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+// Maximum number of targets whose groups are cached.
+const MAX_CACHED_GROUPS: usize = 1024;
+
+// The range of the k-bucket a key falls in, relative to our own key.
+type BucketRange = Option<u32>;
+
+#[derive(Debug)]
+struct CachedGroup {
+    target: KBucketKey<Vec<u8>>,
+    peers: HashSet<PeerId>,
+    refreshed: Instant,
+}
+
+impl CachedGroup {
+    fn is_expired(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.refreshed) > CACHE_TTL
+    }
+
+    /// The members of the group which are the closest peers to the target, ordered by their
+    /// distance to it, as far as it can be told from the group found for a nearby target.
+    ///
+    /// The group holds the closest peers to its own target `t`, i.e. any other peer is further
+    /// from `t` than its furthest member `f`. A member `m` is then closer to the target `x` than
+    /// any other peer if the highest bit `d(m, f)` has set is above the highest bit `d(t, x)` has
+    /// set, as `d(m, x)` and `d(f, x)` first differ at that bit, `f` being the further one.
+    fn closest_to(&self, target: &KBucketKey<Vec<u8>>) -> Vec<PeerId> {
+        let offset = self.target.distance(target).ilog2();
+        let mut members: Vec<(PeerId, KBucketKey<PeerId>)> = self
+            .peers
+            .iter()
+            .map(|peer| (*peer, KBucketKey::from(*peer)))
+            .collect();
+        let furthest = match members
+            .iter()
+            .max_by_key(|(_, key)| self.target.distance(key))
+        {
+            Some((_, key)) => key.clone(),
+            None => return vec![],
+        };
+
+        members.sort_by_cached_key(|(_, key)| target.distance(key));
+        // Members closer to the target than one known to be among its closest are too.
+        let known = members
+            .iter()
+            .rposition(|(_, key)| offset.is_none() || key.distance(&furthest).ilog2() > offset)
+            .map_or(0, |position| position + 1);
+        members
+            .into_iter()
+            .take(known)
+            .map(|(peer, _)| peer)
+            .collect()
+    }
+}
+
+/// Peers found by the `get_closest_peers` queries, keyed by the range of the k-bucket their
+/// target falls in, so that a group can be looked up for targets near the one it was found for.
+/// Names are hashed into the Kademlia keyspace, so it's that of their keys which is compared.
+#[derive(Debug)]
+pub(crate) struct CloseGroupCache {
+    our_key: KBucketKey<PeerId>,
+    groups: HashMap<BucketRange, Vec<CachedGroup>>,
+}
+
+impl CloseGroupCache {
+    pub(crate) fn new(our_id: PeerId) -> Self {
+        Self {
+            our_key: KBucketKey::from(our_id),
+            groups: HashMap::new(),
+        }
+    }
+
+    /// The cached peers closest to the target, if there are enough of them and they're recent enough.
+    /// They're taken from the group of the nearest cached target in the same bucket range.
+    pub(crate) fn get(&mut self, target: &[u8], now: Instant) -> Option<HashSet<PeerId>> {
+        let target = KBucketKey::new(target.to_vec());
+        let range = self.bucket_range(&target);
+        let groups = self.groups.get_mut(&range)?;
+        groups.retain(|group| !group.is_expired(now));
+
+        groups.sort_by_cached_key(|group| group.target.distance(&target));
+        let closest = groups
+            .iter()
+            .map(|group| group.closest_to(&target))
+            .find(|closest| closest.len() >= CLOSE_GROUP_SIZE);
+
+        if groups.is_empty() {
+            let _ = self.groups.remove(&range);
+        }
+        closest.map(|closest| closest.into_iter().collect())
+    }
+
+    /// Cache the peers a query found close to the target.
+    /// When full, the expired groups are dropped, or else the oldest one.
+    pub(crate) fn insert(&mut self, target: &[u8], peers: HashSet<PeerId>, now: Instant) {
+        let target = KBucketKey::new(target.to_vec());
+        let range = self.bucket_range(&target);
+        let group = CachedGroup {
+            target,
+            peers,
+            refreshed: now,
+        };
+
+        if let Some(cached) = self.groups.get_mut(&range).and_then(|groups| {
+            groups
+                .iter_mut()
+                .find(|cached| cached.target == group.target)
+        }) {
+            *cached = group;
+            return;
+        }
+
+        if self.len() >= MAX_CACHED_GROUPS {
+            self.retain(|group| !group.is_expired(now));
+            if self.len() >= MAX_CACHED_GROUPS {
+                let oldest = self
+                    .groups
+                    .values()
+                    .flatten()
+                    .min_by_key(|group| group.refreshed)
+                    .map(|group| group.target.clone());
+                if let Some(oldest) = oldest {
+                    self.retain(|group| group.target != oldest);
+                }
+            }
+        }
+        self.groups.entry(range).or_default().push(group);
+    }
+
+    /// Add a peer which joined our routing table to the cached groups it's closer to the target of
+    /// than their furthest member, which it then replaces.
+    pub(crate) fn add_peer(&mut self, peer: PeerId) {
+        let peer_key = KBucketKey::from(peer);
+        for group in self.groups.values_mut().flatten() {
+            if group.peers.contains(&peer) {
+                continue;
+            }
+            let furthest = group
+                .peers
+                .iter()
+                .max_by_key(|member| group.target.distance(&KBucketKey::from(**member)))
+                .cloned();
+            if let Some(furthest) = furthest {
+                let furthest_key = KBucketKey::from(furthest);
+                if group.target.distance(&peer_key) < group.target.distance(&furthest_key) {
+                    let _ = group.peers.remove(&furthest);
+                    let _ = group.peers.insert(peer);
+                }
+            }
+        }
+    }
+
+    /// Remove a peer we're no longer connected to, dropping the groups it leaves too small.
+    pub(crate) fn remove_peer(&mut self, peer: &PeerId) {
+        self.retain(|group| {
+            let _ = group.peers.remove(peer);
+            group.peers.len() >= CLOSE_GROUP_SIZE
+        });
+    }
+
+    fn bucket_range(&self, key: &KBucketKey<Vec<u8>>) -> BucketRange {
+        self.our_key.distance(key).ilog2()
+    }
+
+    fn len(&self) -> usize {
+        self.groups.values().map(Vec::len).sum()
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&mut CachedGroup) -> bool) {
+        self.groups.retain(|_, groups| {
+            groups.retain_mut(&mut keep);
+            !groups.is_empty()
+        });
+    }
+}
+
+impl SwarmDriver {
+    /// The closest peers to the target, taken from our own k-buckets, if the target is within
+    /// the range of our own close group. Our k-buckets hold all the peers that close to us, so
+    /// they also hold the closest peers to such a target, without having to query the network.
+    pub(super) fn local_closest_peers(&mut self, target: &[u8]) -> Option<HashSet<PeerId>> {
+        let our_key = KBucketKey::new(self.swarm.local_peer_id().to_bytes());
+        let target_key = KBucketKey::new(target.to_vec());
+
+        let mut known_peers: Vec<(PeerId, KBucketKey<Vec<u8>>)> = self
+            .swarm
+            .behaviour_mut()
+            .kademlia
+            .kbuckets()
+            .flat_map(|kbucket| {
+                kbucket
+                    .iter()
+                    .map(|entry| *entry.node.key.preimage())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|peer| !self.reputations.is_banned(peer))
+            .map(|peer| (peer, KBucketKey::new(peer.to_bytes())))
+            .collect();
+        if known_peers.len() < CLOSE_GROUP_SIZE {
+            return None;
+        }
+
+        // The range of our close group is the distance to its furthest member
+        known_peers.sort_by_cached_key(|(_, key)| our_key.distance(key));
+        let (_, furthest) = &known_peers[CLOSE_GROUP_SIZE - 1];
+        if our_key.distance(&target_key) > our_key.distance(furthest) {
+            return None;
+        }
+
+        known_peers.sort_by_cached_key(|(_, key)| target_key.distance(key));
+        Some(
+            known_peers
+                .into_iter()
+                .take(CLOSE_GROUP_SIZE)
+                .map(|(peer, _)| peer)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use xor_name::XorName;
+
+    fn random_target() -> Vec<u8> {
+        XorName::random(&mut rand::thread_rng()).0.to_vec()
+    }
+
+    // A peer closer to the target than all the peers of the group.
+    fn peer_closer_than(target: &[u8], peers: &[PeerId]) -> PeerId {
+        let target_key = KBucketKey::new(target.to_vec());
+        let closest = peers
+            .iter()
+            .map(|peer| target_key.distance(&KBucketKey::new(peer.to_bytes())))
+            .min()
+            .expect("The group to have peers");
+        loop {
+            let peer = PeerId::random();
+            if target_key.distance(&KBucketKey::new(peer.to_bytes())) < closest {
+                return peer;
+            }
+        }
+    }
+
+    // The closest peers to the target among the given ones, closest first.
+    fn closest_peers(target: &[u8], peers: &[PeerId], count: usize) -> Vec<PeerId> {
+        let target_key = KBucketKey::new(target.to_vec());
+        let mut peers = peers.to_vec();
+        peers.sort_by_cached_key(|peer| target_key.distance(&KBucketKey::from(*peer)));
+        peers.truncate(count);
+        peers
+    }
+
+    #[test]
+    fn close_group_cache_answers_the_cached_target() {
+        let mut cache = CloseGroupCache::new(PeerId::random());
+        let target = random_target();
+        let peers: HashSet<_> = (0..CLOSE_GROUP_SIZE).map(|_| PeerId::random()).collect();
+        let now = Instant::now();
+
+        assert_eq!(cache.get(&target, now), None);
+        cache.insert(&target, peers.clone(), now);
+        assert_eq!(cache.get(&target, now), Some(peers));
+
+        // Names are hashed, so one sharing a long prefix with the target isn't near it.
+        let mut other_target = target.clone();
+        other_target[xor_name::XOR_NAME_LEN - 1] ^= 1;
+        assert_eq!(cache.get(&other_target, now), None);
+
+        // Entries expire
+        assert_eq!(
+            cache.get(&target, now + CACHE_TTL + Duration::from_secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn close_group_cache_nearby_targets_share_an_entry() {
+        let our_id = PeerId::random();
+        let mut cache = CloseGroupCache::new(our_id);
+        let network: Vec<_> = (0..4 * CLOSE_GROUP_SIZE)
+            .map(|_| PeerId::random())
+            .collect();
+        let target = random_target();
+        let target_key = KBucketKey::new(target.clone());
+        let now = Instant::now();
+
+        // A query returns the closest peers of the network to the target.
+        let group = closest_peers(&target, &network, 2 * CLOSE_GROUP_SIZE);
+        cache.insert(&target, group.iter().cloned().collect(), now);
+
+        // A target whose key shares its first 12 bits with the cached one's, in the same bucket range.
+        let near_target = loop {
+            let near_target = random_target();
+            let near_key = KBucketKey::new(near_target.clone());
+            if target_key.distance(&near_key).ilog2() < Some(244)
+                && cache.bucket_range(&near_key) == cache.bucket_range(&target_key)
+            {
+                break near_target;
+            }
+        };
+        let cached = cache.get(&near_target, now);
+        assert!(matches!(&cached, Some(cached) if cached.len() >= CLOSE_GROUP_SIZE));
+        let cached = cached.unwrap_or_default();
+        let expected: HashSet<_> = closest_peers(&near_target, &network, cached.len())
+            .into_iter()
+            .collect();
+        assert_eq!(cached, expected);
+        assert_eq!(cache.len(), 1);
+
+        // While the group of a far target can't be told from it.
+        let far_target = loop {
+            let far_target = random_target();
+            let far_key = KBucketKey::new(far_target.clone());
+            if target_key.distance(&far_key).ilog2() == Some(255) {
+                break far_target;
+            }
+        };
+        assert_eq!(cache.get(&far_target, now), None);
+    }
+
+    #[test]
+    fn close_group_cache_is_bounded() {
+        let mut cache = CloseGroupCache::new(PeerId::random());
+        let peers: HashSet<_> = (0..CLOSE_GROUP_SIZE).map(|_| PeerId::random()).collect();
+        let now = Instant::now();
+        let first = random_target();
+        cache.insert(&first, peers.clone(), now);
+        for i in 1..=MAX_CACHED_GROUPS {
+            cache.insert(
+                &random_target(),
+                peers.clone(),
+                now + Duration::from_millis(i as u64),
+            );
+        }
+        assert_eq!(cache.len(), MAX_CACHED_GROUPS);
+        // The oldest group made room for the newest one.
+        assert_eq!(cache.get(&first, now), None);
+    }
+
+    #[test]
+    fn close_group_cache_follows_routing_updates_and_disconnections() {
+        let mut cache = CloseGroupCache::new(PeerId::random());
+        let target = random_target();
+        let peers: Vec<_> = (0..CLOSE_GROUP_SIZE).map(|_| PeerId::random()).collect();
+        let now = Instant::now();
+        cache.insert(&target, peers.iter().cloned().collect(), now);
+
+        // A closer peer replaces the furthest member of the group.
+        let new_peer = peer_closer_than(&target, &peers);
+        cache.add_peer(new_peer);
+        let group = cache.get(&target, now).unwrap_or_default();
+        assert!(group.contains(&new_peer));
+        assert_eq!(group.len(), CLOSE_GROUP_SIZE);
+
+        // Still enough peers after one disconnects, the group being of CLOSE_GROUP_SIZE + 1
+        let extra = peer_closer_than(&target, &peers);
+        cache.insert(&target, peers.iter().cloned().chain([extra]).collect(), now);
+        cache.remove_peer(&peers[0]);
+        let group = cache.get(&target, now);
+        assert!(matches!(group, Some(group) if !group.contains(&peers[0])));
+
+        // Not anymore after another one does
+        cache.remove_peer(&peers[1]);
+        assert_eq!(cache.get(&target, now), None);
+    }
+}
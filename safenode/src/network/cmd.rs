@@ -11,11 +11,14 @@ use crate::{
     protocol::messages::{Request, Response},
 };
 
-use super::{error::Error, SwarmDriver};
+use super::{error::Error, ClosestPeers, PeerAgent, PeerMisbehaviour, SwarmDriver};
 use libp2p::{multiaddr::Protocol, request_response::ResponseChannel, Multiaddr, PeerId};
-use std::collections::{hash_map, HashSet};
+use std::{
+    collections::{hash_map, HashMap},
+    time::Instant,
+};
 use tokio::sync::oneshot;
-use tracing::warn;
+use tracing::{info, trace, warn};
 use xor_name::XorName;
 
 /// Commands to send to the Swarm
@@ -32,7 +35,7 @@ pub enum SwarmCmd {
     },
     GetClosestPeers {
         xor_name: XorName,
-        sender: oneshot::Sender<(PeerId, HashSet<PeerId>)>,
+        sender: oneshot::Sender<ClosestPeers>,
     },
     SendRequest {
         req: Request,
@@ -43,6 +46,19 @@ pub enum SwarmCmd {
         resp: Response,
         channel: ResponseChannel<Response>,
     },
+    GetPeersAgent {
+        sender: oneshot::Sender<HashMap<PeerId, PeerAgent>>,
+    },
+    ReportPeer {
+        peer: PeerId,
+        misbehaviour: PeerMisbehaviour,
+    },
+    GetPeerScores {
+        sender: oneshot::Sender<HashMap<PeerId, i32>>,
+    },
+    Shutdown {
+        sender: oneshot::Sender<()>,
+    },
 }
 
 impl SwarmDriver {
@@ -82,6 +98,26 @@ impl SwarmDriver {
             }
             SwarmCmd::GetClosestPeers { xor_name, sender } => {
                 let key = xor_name.0.to_vec();
+                let cached = self.close_group_cache.get(&key, Instant::now());
+                let known = match cached {
+                    Some(peers) => Some(peers),
+                    None if self.local_close_group => self.local_closest_peers(&key),
+                    None => None,
+                };
+                if let Some(peers) = known {
+                    trace!("Resolved the close group of {xor_name} without querying the network");
+                    let our_id = *self.swarm.local_peer_id();
+                    let low_score = peers
+                        .iter()
+                        .filter(|peer| self.reputations.is_low(peer))
+                        .cloned()
+                        .collect();
+                    sender
+                        .send((our_id, peers, low_score))
+                        .map_err(|_| Error::InternalMsgChannelDropped)?;
+                    return Ok(());
+                }
+
                 let query_id = self.swarm.behaviour_mut().kademlia.get_closest_peers(key);
                 let _ = self
                     .pending_get_closest_peers
@@ -92,7 +128,7 @@ impl SwarmDriver {
                     .swarm
                     .behaviour_mut()
                     .request_response
-                    .send_request(&peer, req);
+                    .send_request(&peer, Ok(req));
                 let _ = self.pending_requests.insert(request_id, sender);
             }
             SwarmCmd::SendResponse { resp, channel } => {
@@ -102,6 +138,23 @@ impl SwarmDriver {
                     .send_response(channel, resp)
                     .map_err(Error::OutgoingResponseDropped)?;
             }
+            SwarmCmd::GetPeersAgent { sender } => {
+                let _ = sender.send(self.peers_agent.clone());
+            }
+            SwarmCmd::ReportPeer { peer, misbehaviour } => {
+                self.report_peer(peer, misbehaviour);
+            }
+            SwarmCmd::GetPeerScores { sender } => {
+                let _ = sender.send(self.reputations.scores());
+            }
+            SwarmCmd::Shutdown { sender } => {
+                info!("Shutting down, closing the connections to all peers");
+                let peers: Vec<_> = self.swarm.connected_peers().cloned().collect();
+                for peer in peers {
+                    let _ = self.swarm.disconnect_peer_id(peer);
+                }
+                self.shutdown_sender = Some(sender);
+            }
         }
         Ok(())
     }
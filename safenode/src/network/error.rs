@@ -6,17 +6,17 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::protocol::messages::Response;
+use crate::protocol::messages::{Response, UnsupportedMsg};
 
 use super::{cmd::SwarmCmd, NetworkEvent};
 
 use libp2p::{
-    kad,
+    kad, noise,
     request_response::{OutboundFailure, RequestId},
     swarm::DialError,
     TransportError,
 };
-use std::io;
+use std::{io, time::Duration};
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 
@@ -41,6 +41,9 @@ pub enum Error {
     #[error("Transport Error")]
     TransportError(#[from] TransportError<std::io::Error>),
 
+    #[error("Noise handshake configuration error: {0}")]
+    Noise(#[from] noise::Error),
+
     #[error("Dial Error")]
     DialError(#[from] DialError),
 
@@ -62,6 +65,12 @@ pub enum Error {
     #[error("The oneshot::sender has been dropped")]
     SenderDropped(#[from] oneshot::error::RecvError),
 
+    #[error("Peer could not decode our request: {0:?}")]
+    UnsupportedMsg(UnsupportedMsg),
+
+    #[error("Peer is busy, the request can be retried after {0:?}")]
+    PeerBusy(Duration),
+
     #[error("Could not get CLOSE_GROUP_SIZE number of peers.")]
     NotEnoughPeers,
 }
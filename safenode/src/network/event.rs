@@ -8,12 +8,14 @@
 
 use super::{
     error::{Error, Result},
-    msg::MsgCodec,
-    SwarmDriver,
+    msg::{MsgCodec, MsgRequest},
+    PeerAgent, SwarmDriver, IDENTIFY_PROTOCOL_STR,
 };
 
 use crate::protocol::messages::{Request, Response};
 use libp2p::{
+    allow_block_list::{self, BlockedPeers},
+    identify,
     kad::{store::MemoryStore, Kademlia, KademliaEvent, QueryResult, K_VALUE},
     mdns,
     multiaddr::Protocol,
@@ -21,8 +23,8 @@ use libp2p::{
     swarm::{NetworkBehaviour, SwarmEvent},
     PeerId,
 };
-use std::collections::HashSet;
-use tracing::{info, warn};
+use std::{collections::HashSet, time::Instant};
+use tracing::{debug, info, warn};
 
 #[derive(NetworkBehaviour)]
 #[behaviour(out_event = "NodeEvent")]
@@ -30,17 +32,20 @@ pub(super) struct NodeBehaviour {
     pub(super) request_response: request_response::Behaviour<MsgCodec>,
     pub(super) kademlia: Kademlia<MemoryStore>,
     pub(super) mdns: mdns::tokio::Behaviour,
+    pub(super) identify: identify::Behaviour,
+    pub(super) blocked_peers: allow_block_list::Behaviour<BlockedPeers>,
 }
 
 #[derive(Debug)]
 pub(super) enum NodeEvent {
-    RequestResponse(request_response::Event<Request, Response>),
+    RequestResponse(request_response::Event<MsgRequest, Response>),
     Kademlia(KademliaEvent),
     Mdns(Box<mdns::Event>),
+    Identify(Box<identify::Event>),
 }
 
-impl From<request_response::Event<Request, Response>> for NodeEvent {
-    fn from(event: request_response::Event<Request, Response>) -> Self {
+impl From<request_response::Event<MsgRequest, Response>> for NodeEvent {
+    fn from(event: request_response::Event<MsgRequest, Response>) -> Self {
         NodeEvent::RequestResponse(event)
     }
 }
@@ -57,11 +62,26 @@ impl From<mdns::Event> for NodeEvent {
     }
 }
 
+impl From<identify::Event> for NodeEvent {
+    fn from(event: identify::Event) -> Self {
+        NodeEvent::Identify(Box::new(event))
+    }
+}
+
+// The block list behaviour never emits events.
+impl From<void::Void> for NodeEvent {
+    fn from(event: void::Void) -> Self {
+        void::unreachable(event)
+    }
+}
+
 #[derive(Debug)]
 /// Events forwarded by the underlying Network; to be used by the upper layers
 pub enum NetworkEvent {
     /// Incoming `Request` from a peer
     RequestReceived {
+        /// The peer which sent the request
+        peer: PeerId,
         /// Request
         req: Request,
         /// The channel to send the `Response` through
@@ -104,13 +124,27 @@ impl SwarmDriver {
                     //       following creterias:
                     //   1, `stats.num_pending()` is 0
                     //   2, `stats.duration()` is longer than a defined period
-                    let new_peers: HashSet<PeerId> =
-                        closest_peers.peers.clone().into_iter().collect();
+                    let new_peers: HashSet<PeerId> = closest_peers
+                        .peers
+                        .iter()
+                        .filter(|peer| !self.reputations.is_banned(peer))
+                        .cloned()
+                        .collect();
                     current_closest.extend(new_peers);
                     if current_closest.len() >= usize::from(K_VALUE) || step.last {
                         let our_id = *self.swarm.local_peer_id();
+                        let low_score = current_closest
+                            .iter()
+                            .filter(|peer| self.reputations.is_low(peer))
+                            .cloned()
+                            .collect();
+                        self.close_group_cache.insert(
+                            &closest_peers.key,
+                            current_closest.clone(),
+                            Instant::now(),
+                        );
                         sender
-                            .send((our_id, current_closest))
+                            .send((our_id, current_closest, low_score))
                             .map_err(|_| Error::InternalMsgChannelDropped)?;
                     } else {
                         let _ = self
@@ -118,8 +152,11 @@ impl SwarmDriver {
                             .insert(*id, (sender, current_closest));
                     }
                 }
-                KademliaEvent::RoutingUpdated { is_new_peer, .. } => {
+                KademliaEvent::RoutingUpdated {
+                    peer, is_new_peer, ..
+                } => {
                     if *is_new_peer {
+                        self.close_group_cache.add_peer(*peer);
                         self.event_sender.send(NetworkEvent::PeerAdded).await?;
                     }
                 }
@@ -133,6 +170,9 @@ impl SwarmDriver {
             SwarmEvent::Behaviour(NodeEvent::Mdns(mdns_event)) => match *mdns_event {
                 mdns::Event::Discovered(list) => {
                     for (peer_id, multiaddr) in list {
+                        if self.reputations.is_banned(&peer_id) {
+                            continue;
+                        }
                         info!("Node discovered: {multiaddr:?}");
                         let _routing_update = self
                             .swarm
@@ -146,6 +186,45 @@ impl SwarmDriver {
                     info!("mdns peer {peer:?} expired");
                 }
             },
+            SwarmEvent::Behaviour(NodeEvent::Identify(identify_event)) => match *identify_event {
+                identify::Event::Received { peer_id, info } => {
+                    info!(
+                        "Identified peer {peer_id:?} as {:?}, protocol {:?}",
+                        info.agent_version, info.protocol_version
+                    );
+                    debug!("Peer {peer_id:?} observes us at {:?}", info.observed_addr);
+                    if self.reputations.is_banned(&peer_id) {
+                        debug!("Not adding the addresses of banned peer {peer_id:?}");
+                    } else if info.protocol_version != IDENTIFY_PROTOCOL_STR {
+                        warn!(
+                            "Peer {peer_id:?} runs an incompatible protocol version {:?}, ours is {IDENTIFY_PROTOCOL_STR:?}",
+                            info.protocol_version
+                        );
+                    } else {
+                        // Only add the addresses the peer is actually listening on,
+                        // the observed ones may not be reachable from other peers.
+                        for addr in info.listen_addrs {
+                            let _routing_update = self
+                                .swarm
+                                .behaviour_mut()
+                                .kademlia
+                                .add_address(&peer_id, addr);
+                        }
+                    }
+
+                    let _ = self.peers_agent.insert(
+                        peer_id,
+                        PeerAgent {
+                            agent_version: info.agent_version,
+                            protocol_version: info.protocol_version,
+                        },
+                    );
+                }
+                identify::Event::Error { peer_id, error } => {
+                    warn!("Failed to identify peer {peer_id:?}: {error}");
+                }
+                identify::Event::Sent { .. } | identify::Event::Pushed { .. } => {}
+            },
             SwarmEvent::NewListenAddr { address, .. } => {
                 let local_peer_id = *self.swarm.local_peer_id();
                 info!(
@@ -163,8 +242,23 @@ impl SwarmDriver {
                         let _ = sender.send(Ok(()));
                     }
                 }
+                if self.shutdown_sender.is_some() {
+                    debug!("Shutting down, closing the connection with {peer_id:?}");
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                }
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established,
+                ..
+            } => {
+                if num_established == 0 {
+                    let _ = self.peers_agent.remove(&peer_id);
+                    let _ = self.oversize_msgs.remove(&peer_id);
+                    self.reputations.disconnected(&peer_id);
+                    self.close_group_cache.remove_peer(&peer_id);
+                }
             }
-            SwarmEvent::ConnectionClosed { .. } => {}
             SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
                 if let Some(peer_id) = peer_id {
                     if let Some(sender) = self.pending_dial.remove(&peer_id) {
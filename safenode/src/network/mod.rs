@@ -6,40 +6,52 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+#[cfg(feature = "chaos")]
+mod chaos;
+mod close_group_cache;
 mod cmd;
 mod error;
 mod event;
 mod msg;
+mod rate_limit;
+mod reputation;
 
 use crate::protocol::messages::{Request, Response};
 
-pub use self::{error::Error, event::NetworkEvent};
+pub use self::{
+    error::Error,
+    event::NetworkEvent,
+    reputation::{PeerMisbehaviour, INITIAL_SCORE},
+};
 
 use self::{
+    close_group_cache::CloseGroupCache,
     cmd::SwarmCmd,
     error::Result,
     event::NodeBehaviour,
     msg::{MsgCodec, MsgProtocol},
+    rate_limit::{RateLimiter, BUCKETS_PRUNE_INTERVAL},
+    reputation::{Reputations, SCORE_DECAY_INTERVAL},
 };
 
-use futures::StreamExt;
+use futures::{future::Either, StreamExt};
 use libp2p::{
-    core::muxing::StreamMuxerBox,
-    identity,
+    allow_block_list,
+    core::{muxing::StreamMuxerBox, transport::OrTransport, upgrade},
+    identify, identity,
     kad::{record::store::MemoryStore, KBucketKey, Kademlia, KademliaConfig, QueryId},
     mdns,
     multiaddr::Protocol,
+    noise,
     request_response::{self, ProtocolSupport, RequestId, ResponseChannel},
     swarm::{Swarm, SwarmBuilder},
-    Multiaddr, PeerId, Transport,
+    tcp, yamux, Multiaddr, PeerId, Transport,
 };
-use rand::Rng;
 use std::{
     collections::{HashMap, HashSet},
-    env, iter,
     net::SocketAddr,
-    process::{self, Command, Stdio},
-    time::Duration,
+    str::FromStr,
+    time::{Duration, Instant},
 };
 use tokio::sync::{mpsc, oneshot};
 use tracing::warn;
@@ -50,14 +62,84 @@ use xor_name::XorName;
 /// an item in the network.
 pub(crate) const CLOSE_GROUP_SIZE: usize = 8;
 
+/// The protocol version exchanged with peers through the `identify` protocol,
+/// peers with a different version are considered incompatible.
+pub(crate) const IDENTIFY_PROTOCOL_STR: &str = concat!("safe/", env!("CARGO_PKG_VERSION"));
+
+// Agent versions exchanged with peers through the `identify` protocol.
+const IDENTIFY_NODE_AGENT_STR: &str = concat!("safenode/", env!("CARGO_PKG_VERSION"));
+const IDENTIFY_CLIENT_AGENT_STR: &str = concat!("safe-client/", env!("CARGO_PKG_VERSION"));
+
+// Number of times a request is sent again when the peer is too busy to handle it.
+const MAX_BUSY_RETRIES: usize = 3;
+// The longest we wait before sending a request again to a busy peer, whatever it asked for.
+const MAX_BUSY_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+// Timeout for the TCP connection upgrade, i.e. noise handshake and yamux negotiation.
+const TCP_UPGRADE_TIMEOUT: Duration = Duration::from_secs(20);
+
 /// Majority of a given group (i.e. > 1/2).
 #[inline]
 pub const fn close_group_majority() -> usize {
     CLOSE_GROUP_SIZE / 2 + 1
 }
 
-type PendingGetClosest =
-    HashMap<QueryId, (oneshot::Sender<(PeerId, HashSet<PeerId>)>, HashSet<PeerId>)>;
+/// Agent and protocol versions a connected peer identified itself with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerAgent {
+    /// Name and version of the software the peer runs, e.g. `safenode/0.1.0`.
+    pub agent_version: String,
+    /// Version of the protocol the peer speaks.
+    pub protocol_version: String,
+}
+
+/// The transport(s) a node listens on for incoming connections.
+/// Whichever it is, connections to peers can be dialled over either.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransportMode {
+    /// Listen over QUIC only.
+    Quic,
+    /// Listen over TCP only, e.g. for hosts behind middleboxes dropping UDP traffic.
+    Tcp,
+    /// Listen over both QUIC and TCP, on the same port.
+    #[default]
+    Both,
+}
+
+impl FromStr for TransportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "quic" => Ok(Self::Quic),
+            "tcp" => Ok(Self::Tcp),
+            "both" => Ok(Self::Both),
+            other => Err(format!(
+                "Unknown transport: {other}, expected quic, tcp or both"
+            )),
+        }
+    }
+}
+
+impl TransportMode {
+    // The addresses to listen on with this mode, for the provided socket address.
+    fn listen_addrs(&self, addr: SocketAddr) -> Vec<Multiaddr> {
+        let quic_addr = Multiaddr::from(addr.ip())
+            .with(Protocol::Udp(addr.port()))
+            .with(Protocol::QuicV1);
+        let tcp_addr = Multiaddr::from(addr.ip()).with(Protocol::Tcp(addr.port()));
+        match self {
+            Self::Quic => vec![quic_addr],
+            Self::Tcp => vec![tcp_addr],
+            Self::Both => vec![quic_addr, tcp_addr],
+        }
+    }
+}
+
+// Our `PeerId`, the closest peers found, and those among them with a low score.
+type ClosestPeers = (PeerId, HashSet<PeerId>, HashSet<PeerId>);
+
+type PendingGetClosest = HashMap<QueryId, (oneshot::Sender<ClosestPeers>, HashSet<PeerId>)>;
 
 /// `SwarmDriver` is responsible for managing the swarm of peers, handling
 /// swarm events, processing commands, and maintaining the state of pending
@@ -69,6 +151,19 @@ pub struct SwarmDriver {
     pending_dial: HashMap<PeerId, oneshot::Sender<Result<()>>>,
     pending_get_closest_peers: PendingGetClosest,
     pending_requests: HashMap<RequestId, oneshot::Sender<Result<Response>>>,
+    peers_agent: HashMap<PeerId, PeerAgent>,
+    // Number of oversize requests each connected peer has sent us, dropped on disconnection.
+    oversize_msgs: HashMap<PeerId, usize>,
+    reputations: Reputations,
+    rate_limiter: RateLimiter,
+    close_group_cache: CloseGroupCache,
+    // Whether close groups can be resolved from our own k-buckets, which only
+    // nodes hold enough of the peers close to them in.
+    local_close_group: bool,
+    // Set when shutting down, to be notified once all the connections are closed.
+    shutdown_sender: Option<oneshot::Sender<()>>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<chaos::Chaos>,
 }
 
 impl SwarmDriver {
@@ -85,27 +180,43 @@ impl SwarmDriver {
     /// # Errors
     ///
     /// Returns an error if there is a problem initializing the mDNS behavior.
-    pub fn new(addr: SocketAddr) -> Result<(Network, mpsc::Receiver<NetworkEvent>, SwarmDriver)> {
+    pub fn new(
+        addr: SocketAddr,
+        transport: TransportMode,
+    ) -> Result<(Network, mpsc::Receiver<NetworkEvent>, SwarmDriver)> {
         let mut cfg = KademliaConfig::default();
         let _ = cfg.set_query_timeout(Duration::from_secs(5 * 60));
         let _ = cfg.set_connection_idle_timeout(Duration::from_secs(10 * 60));
 
         let request_response = request_response::Behaviour::new(
             MsgCodec(),
-            iter::once((MsgProtocol(), ProtocolSupport::Full)),
+            MsgProtocol::SUPPORTED
+                .into_iter()
+                .map(|protocol| (protocol, ProtocolSupport::Full)),
             Default::default(),
         );
 
-        let (network, events_receiver, mut swarm_driver) = Self::with(cfg, request_response)?;
+        #[allow(unused_mut)]
+        let (mut network, events_receiver, mut swarm_driver) =
+            Self::with(cfg, request_response, IDENTIFY_NODE_AGENT_STR)?;
+        swarm_driver.local_close_group = true;
+
+        // Only nodes misbehave, clients being the ones exercised by it
+        #[cfg(feature = "chaos")]
+        {
+            let config = chaos::ChaosConfig::from_env();
+            swarm_driver.chaos = Some(chaos::Chaos::new(config));
+            network.chaos = Some(config);
+        }
 
-        // Listen on the provided address
-        let addr = Multiaddr::from(addr.ip())
-            .with(Protocol::Udp(addr.port()))
-            .with(Protocol::QuicV1);
-        let _listener_id = swarm_driver
-            .swarm
-            .listen_on(addr)
-            .expect("Failed to listen on the provided address");
+        // Listen on the provided address over the chosen transport(s). Over TCP,
+        // peers behind middleboxes dropping UDP traffic can still reach us.
+        for addr in transport.listen_addrs(addr) {
+            let _listener_id = swarm_driver
+                .swarm
+                .listen_on(addr)
+                .expect("Failed to listen on the provided address");
+        }
 
         Ok((network, events_receiver, swarm_driver))
     }
@@ -117,17 +228,20 @@ impl SwarmDriver {
         let cfg = KademliaConfig::default(); // default query timeout is 60 secs
         let request_response = request_response::Behaviour::new(
             MsgCodec(),
-            iter::once((MsgProtocol(), ProtocolSupport::Outbound)),
+            MsgProtocol::SUPPORTED
+                .into_iter()
+                .map(|protocol| (protocol, ProtocolSupport::Outbound)),
             Default::default(),
         );
 
-        Self::with(cfg, request_response)
+        Self::with(cfg, request_response, IDENTIFY_CLIENT_AGENT_STR)
     }
 
     // Private helper to create the network components with the provided config and req/res behaviour
     fn with(
         cfg: KademliaConfig,
         request_response: request_response::Behaviour<MsgCodec>,
+        agent_version: &str,
     ) -> Result<(Network, mpsc::Receiver<NetworkEvent>, SwarmDriver)> {
         // Create a random key for ourself.
         let keypair = identity::Keypair::generate_ed25519();
@@ -137,19 +251,39 @@ impl SwarmDriver {
 
         // QUIC configuration
         let quic_config = libp2p_quic::Config::new(&keypair);
-        let transport = libp2p_quic::tokio::Transport::new(quic_config);
-        let transport = transport
-            .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        let quic_transport = libp2p_quic::tokio::Transport::new(quic_config);
+
+        // TCP configuration, secured with noise and multiplexed with yamux
+        let tcp_transport = tcp::tokio::Transport::new(tcp::Config::default().nodelay(true))
+            .upgrade(upgrade::Version::V1)
+            .authenticate(noise::Config::new(&keypair)?)
+            .multiplex(yamux::Config::default())
+            .timeout(TCP_UPGRADE_TIMEOUT);
+
+        // The transport used for each connection is chosen according to the
+        // peer's multiaddr, i.e. `/udp/<port>/quic-v1` or `/tcp/<port>`.
+        let transport = OrTransport::new(quic_transport, tcp_transport)
+            .map(|output, _| match output {
+                Either::Left((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
+                Either::Right((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
+            })
             .boxed();
 
         // Create a Kademlia behaviour for client mode, i.e. set req/resp protocol
         // to outbound-only mode and don't listen on any address
         let kademlia = Kademlia::with_config(peer_id, MemoryStore::new(peer_id), cfg);
         let mdns = mdns::tokio::Behaviour::new(mdns::Config::default(), peer_id)?;
+        // Exchange protocol versions, agent strings and listen addresses with peers
+        let identify = identify::Behaviour::new(
+            identify::Config::new(IDENTIFY_PROTOCOL_STR.to_string(), keypair.public())
+                .with_agent_version(agent_version.to_string()),
+        );
         let behaviour = NodeBehaviour {
             request_response,
             kademlia,
             mdns,
+            identify,
+            blocked_peers: allow_block_list::Behaviour::default(),
         };
 
         let swarm = SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build();
@@ -163,12 +297,23 @@ impl SwarmDriver {
             pending_dial: Default::default(),
             pending_get_closest_peers: Default::default(),
             pending_requests: Default::default(),
+            peers_agent: Default::default(),
+            oversize_msgs: Default::default(),
+            reputations: Default::default(),
+            rate_limiter: Default::default(),
+            close_group_cache: CloseGroupCache::new(peer_id),
+            local_close_group: false,
+            shutdown_sender: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
         };
 
         Ok((
             Network {
                 swarm_cmd_sender,
                 peer_id,
+                #[cfg(feature = "chaos")]
+                chaos: None,
             },
             network_event_receiver,
             swarm_driver,
@@ -177,17 +322,27 @@ impl SwarmDriver {
 
     /// Asynchronously drives the swarm event loop, handling events from both
     /// the swarm and command receiver. This function will run indefinitely,
-    /// until the command channel is closed.
+    /// until the command channel is closed or a shutdown has closed all the connections.
     ///
     /// The `tokio::select` macro is used to concurrently process swarm events
     /// and command receiver messages, ensuring efficient handling of multiple
     /// asynchronous tasks.
     pub async fn run(mut self) {
+        let mut score_decay = tokio::time::interval(SCORE_DECAY_INTERVAL);
+        let mut buckets_prune = tokio::time::interval(BUCKETS_PRUNE_INTERVAL);
         loop {
+            if self.shutdown_sender.is_some() && self.swarm.connected_peers().next().is_none() {
+                info!("All connections closed, stopping the swarm driver");
+                if let Some(sender) = self.shutdown_sender.take() {
+                    let _ = sender.send(());
+                }
+                return;
+            }
+
             tokio::select! {
                 some_event = self.swarm.next() => {
-                    // TODO: currently disabled to provide a stable network.
-                    // restart_at_random(self.swarm.local_peer_id());
+                    #[cfg(feature = "chaos")]
+                    self.cause_chaos();
                     if let Err(err) = self.handle_swarm_events(some_event.expect("Swarm stream to be infinite!")).await {
                         warn!("Error while handling event: {err}");
                     }
@@ -200,54 +355,21 @@ impl SwarmDriver {
                     },
                     None =>  return,
                 },
+                _ = score_decay.tick() => self.decay_reputations(),
+                _ = buckets_prune.tick() => self.rate_limiter.prune(Instant::now()),
             }
         }
     }
 }
 
-/// Restarts the whole program.
-/// It does this at random, one in X times called.
-///
-/// This provides a way to test the network layer's ability to recover from
-/// unexpected shutdowns.
-#[allow(dead_code)]
-fn restart_at_random(peer_id: &PeerId) {
-    let mut rng = rand::thread_rng();
-    let random_num = rng.gen_range(0..500);
-
-    if random_num == 0 {
-        warn!("Restarting {peer_id:?} at random!");
-
-        let ten_millis = std::time::Duration::from_millis(10);
-        std::thread::sleep(ten_millis);
-
-        // Get the current executable's path
-        let executable = env::current_exe().expect("Failed to get current executable path");
-
-        info!("Spawned executable: {executable:?}");
-
-        // Spawn a new process to restart the binary with the same arguments and environment
-        let _ = Command::new(executable)
-            .args(env::args().skip(1))
-            .envs(env::vars())
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .expect("Failed to restart the app.");
-
-        debug!("New exec called.");
-        // exit the current process now that we've spawned a new one
-        process::exit(0);
-    }
-}
-
 #[derive(Clone)]
 /// API to interact with the underlying Swarm
 pub struct Network {
     pub(super) swarm_cmd_sender: mpsc::Sender<SwarmCmd>,
     #[allow(dead_code)]
     pub(super) peer_id: PeerId,
+    #[cfg(feature = "chaos")]
+    chaos: Option<chaos::ChaosConfig>,
 }
 
 impl Network {
@@ -289,18 +411,18 @@ impl Network {
         let (sender, receiver) = oneshot::channel();
         self.send_swarm_cmd(SwarmCmd::GetClosestPeers { xor_name, sender })
             .await?;
-        let (our_id, k_bucket_peers) = receiver.await?;
+        let (our_id, k_bucket_peers, low_score_peers) = receiver.await?;
 
-        // Count self in if among the CLOSE_GROUP_SIZE closest and sort the result
+        // Count self in if among the CLOSE_GROUP_SIZE closest and sort the result,
+        // peers with a low score coming after all the others.
         let mut closest_peers: Vec<_> = k_bucket_peers.into_iter().collect();
         if !client {
             closest_peers.push(our_id);
         }
         let target = KBucketKey::new(xor_name.0.to_vec());
-        closest_peers.sort_by(|a, b| {
-            let a = KBucketKey::new(a.to_bytes());
-            let b = KBucketKey::new(b.to_bytes());
-            target.distance(&a).cmp(&target.distance(&b))
+        closest_peers.sort_by_cached_key(|peer| {
+            let key = KBucketKey::new(peer.to_bytes());
+            (low_score_peers.contains(peer), target.distance(&key))
         });
         let closest_peers: Vec<PeerId> = closest_peers
             .iter()
@@ -321,12 +443,59 @@ impl Network {
         Ok(closest_peers)
     }
 
-    /// Send `Request` to the the given `PeerId`
-    pub async fn send_request(&self, req: Request, peer: PeerId) -> Result<Response> {
+    /// Returns the agent and protocol versions of the connected peers which identified themselves.
+    pub async fn peers_agent(&self) -> Result<HashMap<PeerId, PeerAgent>> {
         let (sender, receiver) = oneshot::channel();
-        self.send_swarm_cmd(SwarmCmd::SendRequest { req, peer, sender })
+        self.send_swarm_cmd(SwarmCmd::GetPeersAgent { sender })
             .await?;
-        receiver.await?
+        Ok(receiver.await?)
+    }
+
+    /// Lower the score of the peer for the misbehaviour, banning it once its score is too low.
+    pub async fn report_peer(&self, peer: PeerId, misbehaviour: PeerMisbehaviour) -> Result<()> {
+        self.send_swarm_cmd(SwarmCmd::ReportPeer { peer, misbehaviour })
+            .await
+    }
+
+    /// Returns the scores of the peers we've interacted with, peers not
+    /// included have the `INITIAL_SCORE`.
+    pub async fn peer_scores(&self) -> Result<HashMap<PeerId, i32>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_swarm_cmd(SwarmCmd::GetPeerScores { sender })
+            .await?;
+        Ok(receiver.await?)
+    }
+
+    /// Close the connections to all peers and stop the swarm driver, returning once it has stopped.
+    pub async fn shutdown(&self) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_swarm_cmd(SwarmCmd::Shutdown { sender }).await?;
+        Ok(receiver.await?)
+    }
+
+    /// Send `Request` to the the given `PeerId`. If the peer is too busy to handle it, e.g. as
+    /// we exceeded our request rate with it, it's sent again once the peer asked us to wait.
+    pub async fn send_request(&self, req: Request, peer: PeerId) -> Result<Response> {
+        let mut busy_retries = 0;
+        loop {
+            let (sender, receiver) = oneshot::channel();
+            self.send_swarm_cmd(SwarmCmd::SendRequest {
+                req: req.clone(),
+                peer,
+                sender,
+            })
+            .await?;
+            match receiver.await? {
+                Err(Error::PeerBusy(retry_after)) if busy_retries < MAX_BUSY_RETRIES => {
+                    busy_retries += 1;
+                    trace!(
+                        "Peer {peer:?} is busy, sending the request again after {retry_after:?}"
+                    );
+                    tokio::time::sleep(retry_after.min(MAX_BUSY_RETRY_AFTER)).await;
+                }
+                result => return result,
+            }
+        }
     }
 
     /// Send a `Response` through the channel opened by the requester.
@@ -335,6 +504,8 @@ impl Network {
         resp: Response,
         channel: ResponseChannel<Response>,
     ) -> Result<()> {
+        #[cfg(feature = "chaos")]
+        self.delay_response().await;
         self.send_swarm_cmd(SwarmCmd::SendResponse { resp, channel })
             .await
     }
@@ -349,7 +520,7 @@ impl Network {
 
 #[cfg(test)]
 mod tests {
-    use super::SwarmDriver;
+    use super::{SwarmDriver, TransportMode};
     use crate::log::init_node_logging;
     use eyre::{eyre, Result};
     use libp2p::{
@@ -378,6 +549,7 @@ mod tests {
                 "0.0.0.0:0"
                     .parse::<SocketAddr>()
                     .expect("0.0.0.0:0 should parse into a valid `SocketAddr`"),
+                TransportMode::default(),
             )?;
             let _handle = tokio::spawn(driver.run());
 
@@ -435,6 +607,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn transport_mode_listen_addrs() -> Result<()> {
+        let addr: SocketAddr = "127.0.0.1:12000".parse()?;
+
+        let tcp: TransportMode = "tcp".parse().map_err(|err: String| eyre!(err))?;
+        let addrs: Vec<_> = tcp
+            .listen_addrs(addr)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(addrs, vec!["/ip4/127.0.0.1/tcp/12000"]);
+
+        let addrs: Vec<_> = TransportMode::Both
+            .listen_addrs(addr)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            addrs,
+            vec![
+                "/ip4/127.0.0.1/udp/12000/quic-v1",
+                "/ip4/127.0.0.1/tcp/12000"
+            ]
+        );
+        assert!("udp".parse::<TransportMode>().is_err());
+
+        Ok(())
+    }
+
     /// Test utility
 
     fn assert_lists<I, J, K>(a: I, b: J)
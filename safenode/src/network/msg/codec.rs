@@ -6,99 +6,443 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::protocol::messages::{Request, Response};
+use super::{
+    limits::MsgKind,
+    v1::{self, NotInV1},
+};
+
+use crate::protocol::messages::{Request, Response, UnsupportedMsg};
 use async_trait::async_trait;
-use futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use libp2p::{
-    core::upgrade::{read_length_prefixed, write_length_prefixed},
+    core::upgrade::{read_length_prefixed, read_varint, write_length_prefixed},
     request_response::{self, ProtocolName},
 };
 use serde::{de::DeserializeOwned, Serialize};
 use std::io;
+use tracing::warn;
 
-#[derive(Debug, Clone)]
-pub(crate) struct MsgProtocol();
-#[derive(Clone)]
-pub(crate) struct MsgCodec();
+/// An inbound request, or why we rejected it.
+pub(crate) type MsgRequest = Result<Request, RejectedRequest>;
+
+/// Why an inbound request was rejected, with the details to report back to the peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RejectedRequest {
+    /// The request could not be decoded.
+    Undecodable(UnsupportedMsg),
+    /// The request is larger than the maximum size of its kind.
+    Oversize(UnsupportedMsg),
+}
+
+impl RejectedRequest {
+    pub(crate) fn into_unsupported(self) -> UnsupportedMsg {
+        match self {
+            RejectedRequest::Undecodable(details) | RejectedRequest::Oversize(details) => details,
+        }
+    }
+}
+
+// What went wrong reading a message.
+enum ReadError {
+    Io(io::Error),
+    Undecodable(String),
+    Oversize { kind: MsgKind, size: usize },
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        ReadError::Io(err)
+    }
+}
+
+impl From<ReadError> for io::Error {
+    fn from(err: ReadError) -> Self {
+        match err {
+            ReadError::Io(err) => err,
+            ReadError::Undecodable(reason) => io::Error::new(io::ErrorKind::InvalidData, reason),
+            ReadError::Oversize { kind, size } => {
+                warn!(
+                    "Rejected {kind:?} msg of {size} bytes, max is {}",
+                    kind.max_size()
+                );
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{kind:?} msg too large"),
+                )
+            }
+        }
+    }
+}
+
+/// The versions of the request-response protocol, each version
+/// being negotiated with peers under its own protocol name.
+/// Each version comes with its own message schema, messages being converted
+/// to and from the current one, so that they can be changed in a new version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MsgProtocol {
+    /// Length-prefixed msgpack encoded messages of the `v1` schema.
+    /// Messages which can't be expressed in it, as `Response::UnsupportedMsg`, are not sent.
+    V1,
+    /// Length-prefixed msgpack encoded `Request`/`Response`, each preceded by a tag of its kind,
+    /// so that its size can be checked before reading it, and rejected requests are answered
+    /// with `Response::UnsupportedMsg`.
+    V2,
+}
+
+impl MsgProtocol {
+    /// All the versions we support, newest first as it's the order they are proposed to peers.
+    pub(crate) const SUPPORTED: [MsgProtocol; 2] = [MsgProtocol::V2, MsgProtocol::V1];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MsgProtocol::V1 => "/safe/1",
+            MsgProtocol::V2 => "/safe/2",
+        }
+    }
+
+    // Details of a request we received over this version but could not decode.
+    fn unsupported(&self, reason: String) -> UnsupportedMsg {
+        UnsupportedMsg {
+            protocol: self.name().to_string(),
+            supported: Self::SUPPORTED
+                .iter()
+                .map(|protocol| protocol.name().to_string())
+                .collect(),
+            reason,
+        }
+    }
+}
 
 impl ProtocolName for MsgProtocol {
     fn protocol_name(&self) -> &[u8] {
-        "/safe/1".as_bytes()
+        self.name().as_bytes()
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct MsgCodec();
+
 #[async_trait]
 impl request_response::Codec for MsgCodec {
     type Protocol = MsgProtocol;
-    type Request = Request;
+    type Request = MsgRequest;
     type Response = Response;
 
-    async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
+    async fn read_request<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
     where
         T: AsyncRead + Unpin + Send,
     {
-        read_and_decode(io).await
+        match read_and_decode::<_, _, v1::Request>(protocol, io, MsgKind::of_request).await {
+            Ok(req) => Ok(Ok(req)),
+            // Let the peer know why we can't handle its request, instead of just dropping it.
+            // Over `V1` these are surfaced all the same, e.g. for requests of a newer schema,
+            // though the peer can't be answered.
+            Err(ReadError::Undecodable(reason)) => Ok(Err(RejectedRequest::Undecodable(
+                protocol.unsupported(reason),
+            ))),
+            Err(ReadError::Oversize { kind, size }) => Ok(Err(RejectedRequest::Oversize(
+                protocol.unsupported(format!(
+                    "{kind:?} msg of {size} bytes exceeds the maximum of {}",
+                    kind.max_size()
+                )),
+            ))),
+            Err(err) => Err(err.into()),
+        }
     }
 
     async fn read_response<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
     ) -> io::Result<Self::Response>
     where
         T: AsyncRead + Unpin + Send,
     {
-        read_and_decode(io).await
+        Ok(read_and_decode::<_, _, v1::Response>(protocol, io, MsgKind::of_response).await?)
     }
 
     async fn write_request<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         req: Self::Request,
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
     {
-        encode_and_write(io, req).await
+        match req {
+            Ok(req) => {
+                encode_and_write::<_, _, v1::Request>(protocol, io, MsgKind::of_request(&req), req)
+                    .await
+            }
+            Err(unsupported) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Cannot send an undecoded request: {unsupported:?}"),
+            )),
+        }
     }
 
     async fn write_response<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         res: Self::Response,
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
     {
-        encode_and_write(io, res).await
+        let kind = MsgKind::of_response(&res);
+        encode_and_write::<_, _, v1::Response>(protocol, io, kind, res).await
     }
 }
 
-// Encodes the Response/Response using rmp_serde
-async fn encode_and_write<IO, T>(io: &mut IO, data: T) -> io::Result<()>
+// Encodes the Request/Response using rmp_serde, as of the schema of the protocol version,
+// preceded by its kind as of `V2`.
+async fn encode_and_write<IO, T, V1>(
+    protocol: &MsgProtocol,
+    io: &mut IO,
+    kind: MsgKind,
+    data: T,
+) -> io::Result<()>
 where
     IO: AsyncWrite + Unpin,
-    T: Serialize,
+    T: Serialize + TryInto<V1, Error = NotInV1>,
+    V1: Serialize,
 {
-    let bytes = rmp_serde::to_vec(&data)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    let bytes = match protocol {
+        MsgProtocol::V1 => {
+            let data: V1 = data.try_into().map_err(|NotInV1| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Cannot send this {kind:?} msg over /safe/1"),
+                )
+            })?;
+            rmp_serde::to_vec(&data)
+        }
+        MsgProtocol::V2 => rmp_serde::to_vec(&data),
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    if bytes.len() > kind.max_size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{kind:?} msg of {} bytes exceeds the maximum of {}",
+                bytes.len(),
+                kind.max_size()
+            ),
+        ));
+    }
+    if *protocol == MsgProtocol::V2 {
+        io.write_all(&[kind.tag()]).await?;
+    }
     write_length_prefixed(io, bytes).await?;
     io.close().await?;
     Ok(())
 }
 
-// Decodes the Response/Response using rmp_serde
-async fn read_and_decode<IO, T>(io: &mut IO) -> io::Result<T>
+// Decodes the Request/Response using rmp_serde, as of the schema of the protocol version,
+// checking its size against the maximum of its kind.
+// As of `V2` the kind is read first, so oversize messages are rejected before reading them.
+async fn read_and_decode<IO, T, V1>(
+    protocol: &MsgProtocol,
+    io: &mut IO,
+    kind_of: fn(&T) -> MsgKind,
+) -> Result<T, ReadError>
 where
     IO: AsyncRead + Unpin,
     T: DeserializeOwned,
+    V1: DeserializeOwned + Into<T>,
 {
-    let vec = read_length_prefixed(io, 500_000_000).await?; // update transfer maximum
+    let (tagged_kind, vec) = match protocol {
+        MsgProtocol::V1 => (None, read_length_prefixed(io, MsgKind::MAX_SIZE).await?),
+        MsgProtocol::V2 => {
+            let mut tag = [0u8];
+            io.read_exact(&mut tag).await?;
+            let kind = MsgKind::from_tag(tag[0])
+                .ok_or_else(|| ReadError::Undecodable(format!("Unknown msg kind {}", tag[0])))?;
+            let size = read_varint(&mut *io).await?;
+            if size > kind.max_size() {
+                return Err(ReadError::Oversize { kind, size });
+            }
+            let mut vec = vec![0; size];
+            io.read_exact(&mut vec).await?;
+            (Some(kind), vec)
+        }
+    };
     if vec.is_empty() {
-        return Err(io::ErrorKind::UnexpectedEof.into());
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let data = match protocol {
+        MsgProtocol::V1 => rmp_serde::from_slice::<V1>(vec.as_slice()).map(Into::into),
+        MsgProtocol::V2 => rmp_serde::from_slice::<T>(vec.as_slice()),
+    }
+    .map_err(|e| ReadError::Undecodable(e.to_string()))?;
+
+    let kind = kind_of(&data);
+    if matches!(tagged_kind, Some(tagged) if tagged != kind) {
+        return Err(ReadError::Undecodable(format!(
+            "Msg tagged as {tagged_kind:?} is a {kind:?} one"
+        )));
+    }
+    if vec.len() > kind.max_size() {
+        return Err(ReadError::Oversize {
+            kind,
+            size: vec.len(),
+        });
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{
+        address::{ChunkAddress, DbcAddress},
+        chunk::Chunk,
+        fees::SpendQueuePosition,
+        messages::{Cmd, CmdResponse, Query, SpendQuery},
+    };
+    use bytes::Bytes;
+    use eyre::Result;
+    use futures::io::Cursor;
+    use libp2p::core::upgrade::write_varint;
+    use request_response::Codec;
+    use std::time::Duration;
+    use xor_name::XorName;
+
+    async fn length_prefixed(tag: Option<MsgKind>, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        let mut io = Cursor::new(tag.map(|kind| vec![kind.tag()]).unwrap_or_default());
+        io.set_position(io.get_ref().len() as u64);
+        write_length_prefixed(&mut io, bytes).await?;
+        Ok(io.into_inner())
+    }
+
+    #[tokio::test]
+    async fn msg_codec_roundtrips_requests_over_all_versions() -> Result<()> {
+        let req = Request::Query(Query::GetChunk(ChunkAddress::new(XorName::random(
+            &mut rand::thread_rng(),
+        ))));
+
+        for protocol in MsgProtocol::SUPPORTED {
+            let mut io = Cursor::new(vec![]);
+            MsgCodec()
+                .write_request(&protocol, &mut io, Ok(req.clone()))
+                .await?;
+            let mut io = Cursor::new(io.into_inner());
+            let read = MsgCodec().read_request(&protocol, &mut io).await?;
+            assert_eq!(read, Ok(req.clone()));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn msg_codec_reports_undecodable_requests() -> Result<()> {
+        let bytes = rmp_serde::to_vec(&"not a request")?;
+
+        let mut io = Cursor::new(length_prefixed(Some(MsgKind::Query), bytes).await?);
+        match MsgCodec().read_request(&MsgProtocol::V2, &mut io).await? {
+            Err(RejectedRequest::Undecodable(unsupported)) => {
+                assert_eq!(unsupported.protocol, "/safe/2");
+                assert_eq!(unsupported.supported, vec!["/safe/2", "/safe/1"]);
+            }
+            other => panic!("Unexpected read request: {other:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn msg_codec_v1_reports_msgs_of_a_newer_schema_as_unsupported() -> Result<()> {
+        // A query added as of `V2`, sent by a peer over `V1` nonetheless.
+        let req = Request::Query(Query::Spend(SpendQuery::GetPendingDbcSpend(
+            DbcAddress::new(XorName::random(&mut rand::thread_rng())),
+        )));
+        let bytes = rmp_serde::to_vec(&req)?;
+
+        let mut io = Cursor::new(length_prefixed(None, bytes).await?);
+        match MsgCodec().read_request(&MsgProtocol::V1, &mut io).await? {
+            Err(RejectedRequest::Undecodable(unsupported)) => {
+                assert_eq!(unsupported.protocol, "/safe/1");
+                assert_eq!(unsupported.supported, vec!["/safe/2", "/safe/1"]);
+            }
+            other => panic!("Unexpected read request: {other:?}"),
+        }
+
+        // Nor do we send it over `V1`.
+        let mut io = Cursor::new(vec![]);
+        let written = MsgCodec()
+            .write_request(&MsgProtocol::V1, &mut io, Ok(req))
+            .await;
+        assert!(matches!(written, Err(err) if err.kind() == io::ErrorKind::InvalidInput));
+        assert!(io.into_inner().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn msg_codec_v1_responses_are_converted() -> Result<()> {
+        let accepted = Response::Cmd(CmdResponse::Spend(Ok(SpendQueuePosition {
+            position: 3,
+            eta: Duration::from_secs(3),
+        })));
+        let mut io = Cursor::new(vec![]);
+        MsgCodec()
+            .write_response(&MsgProtocol::V1, &mut io, accepted)
+            .await?;
+
+        // A peer on `V1` doesn't learn about the position, nor do we from one.
+        let mut io = Cursor::new(io.into_inner());
+        let read = MsgCodec().read_response(&MsgProtocol::V1, &mut io).await?;
+        assert_eq!(
+            read,
+            Response::Cmd(CmdResponse::Spend(Ok(SpendQueuePosition {
+                position: 0,
+                eta: Duration::ZERO,
+            })))
+        );
+
+        let busy = Response::Busy {
+            retry_after: Duration::from_secs(1),
+        };
+        let mut io = Cursor::new(vec![]);
+        let written = MsgCodec()
+            .write_response(&MsgProtocol::V1, &mut io, busy)
+            .await;
+        assert!(matches!(written, Err(err) if err.kind() == io::ErrorKind::InvalidInput));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn msg_codec_rejects_oversize_requests_before_reading_them() -> Result<()> {
+        // Only the tag and the length are sent, the claimed body is never read
+        let mut io = Cursor::new(vec![MsgKind::Query.tag()]);
+        io.set_position(1);
+        write_varint(&mut io, MsgKind::Query.max_size() + 1).await?;
+
+        let mut io = Cursor::new(io.into_inner());
+        let read = MsgCodec().read_request(&MsgProtocol::V2, &mut io).await?;
+        assert!(matches!(read, Err(RejectedRequest::Oversize(_))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn msg_codec_rejects_mistagged_msgs() -> Result<()> {
+        let chunk = Chunk::new(Bytes::from(vec![0; 1024]));
+        let bytes = rmp_serde::to_vec(&Request::Cmd(Cmd::StoreChunk(chunk)))?;
+
+        let mut io = Cursor::new(length_prefixed(Some(MsgKind::Query), bytes).await?);
+        let read = MsgCodec().read_request(&MsgProtocol::V2, &mut io).await?;
+        assert!(matches!(read, Err(RejectedRequest::Undecodable(_))));
+
+        Ok(())
     }
-    rmp_serde::from_slice::<T>(vec.as_slice())
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
@@ -0,0 +1,187 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Maximum sizes of the encoded messages, per kind of message, so that a peer cannot
+//! make us allocate more memory than the largest legitimate message of its kind needs.
+
+use crate::protocol::{
+    messages::{Cmd, QueryResponse, Request, Response},
+    register::{MAX_REG_ENTRY_SIZE, MAX_REG_NUM_ENTRIES},
+};
+
+use self_encryption::MAX_CHUNK_SIZE;
+
+// Allowance for the fields around the payload of a message, i.e. addresses, keys, signatures, etc.
+const ENVELOPE_OVERHEAD: usize = 16 * 1024;
+// Allowance for the hash, parents, signature and author of each register entry.
+const REG_OP_OVERHEAD: usize = 512;
+// Spends carry their whole transaction, with any number of inputs and outputs.
+// So do the responses to spend cmds, which can hold the spends of a double spend attempt,
+// or the parent spends found invalid.
+const MAX_SPEND_MSG_SIZE: usize = 512 * 1024;
+
+/// The kind of a message, which is sent ahead of it so its size can be checked before reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MsgKind {
+    Query,
+    StoreChunk,
+    RegisterCmd,
+    SpendCmd,
+    Event,
+    CmdResponse,
+    ChunkResponse,
+    RegisterResponse,
+    SpendResponse,
+    UnsupportedResponse,
+    BusyResponse,
+    ReplicateCmd,
+}
+
+impl MsgKind {
+    const ALL: [MsgKind; 12] = [
+        MsgKind::Query,
+        MsgKind::StoreChunk,
+        MsgKind::RegisterCmd,
+        MsgKind::SpendCmd,
+        MsgKind::Event,
+        MsgKind::CmdResponse,
+        MsgKind::ChunkResponse,
+        MsgKind::RegisterResponse,
+        MsgKind::SpendResponse,
+        MsgKind::UnsupportedResponse,
+        MsgKind::BusyResponse,
+        MsgKind::ReplicateCmd,
+    ];
+
+    /// The largest of all the kinds' maximum sizes.
+    pub(crate) const MAX_SIZE: usize =
+        MAX_REG_NUM_ENTRIES as usize * (MAX_REG_ENTRY_SIZE + REG_OP_OVERHEAD) + ENVELOPE_OVERHEAD;
+
+    pub(crate) fn of_request(req: &Request) -> Self {
+        match req {
+            Request::Query(_) => MsgKind::Query,
+            Request::Cmd(Cmd::StoreChunk(_)) => MsgKind::StoreChunk,
+            Request::Cmd(Cmd::Register(_)) => MsgKind::RegisterCmd,
+            Request::Cmd(Cmd::SpendDbc { .. }) => MsgKind::SpendCmd,
+            Request::Cmd(Cmd::Replicate(_)) => MsgKind::ReplicateCmd,
+            Request::Event(_) => MsgKind::Event,
+        }
+    }
+
+    pub(crate) fn of_response(resp: &Response) -> Self {
+        match resp {
+            Response::Cmd(_) => MsgKind::CmdResponse,
+            Response::Query(QueryResponse::GetChunk(_)) => MsgKind::ChunkResponse,
+            Response::Query(
+                QueryResponse::GetFees(_)
+                | QueryResponse::GetDbcSpend(_)
+                | QueryResponse::GetDoubleSpendProof(_),
+            ) => MsgKind::SpendResponse,
+            Response::Query(_) => MsgKind::RegisterResponse,
+            Response::UnsupportedMsg(_) => MsgKind::UnsupportedResponse,
+            Response::Busy { .. } => MsgKind::BusyResponse,
+        }
+    }
+
+    /// The tag the kind is sent as.
+    pub(crate) fn tag(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The kind sent as `tag`, if it's one we know of.
+    pub(crate) fn from_tag(tag: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.tag() == tag)
+    }
+
+    /// The maximum size of an encoded message of this kind.
+    pub(crate) fn max_size(&self) -> usize {
+        match self {
+            MsgKind::Query | MsgKind::UnsupportedResponse | MsgKind::BusyResponse => {
+                ENVELOPE_OVERHEAD
+            }
+            MsgKind::StoreChunk | MsgKind::ChunkResponse => MAX_CHUNK_SIZE + ENVELOPE_OVERHEAD,
+            MsgKind::RegisterCmd => MAX_REG_ENTRY_SIZE + REG_OP_OVERHEAD + ENVELOPE_OVERHEAD,
+            MsgKind::SpendCmd | MsgKind::Event | MsgKind::CmdResponse | MsgKind::SpendResponse => {
+                MAX_SPEND_MSG_SIZE
+            }
+            // Replicated data can be a whole Register op log
+            MsgKind::RegisterResponse | MsgKind::ReplicateCmd => Self::MAX_SIZE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        network_transfers::Error as TransferError,
+        protocol::{
+            dbc_genesis::create_genesis_dbc, error::Error as ProtocolError, messages::CmdResponse,
+            transfers::create_offline_transfer,
+        },
+    };
+
+    use eyre::eyre;
+    use sn_dbc::{MainKey, SignedSpend, Token};
+
+    #[test]
+    fn msg_kind_tags_roundtrip() {
+        for kind in MsgKind::ALL {
+            assert_eq!(MsgKind::from_tag(kind.tag()), Some(kind));
+        }
+        assert_eq!(MsgKind::from_tag(u8::MAX), None);
+    }
+
+    #[test]
+    fn msg_kind_max_size_is_the_largest() {
+        for kind in MsgKind::ALL {
+            assert!(kind.max_size() <= MsgKind::MAX_SIZE, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn msg_kind_cmd_response_fits_a_double_spend_attempt() -> eyre::Result<()> {
+        let key = MainKey::random();
+        let genesis = create_genesis_dbc(&key)?;
+        let derived_key = genesis.derived_key(&key)?;
+
+        // The spend of the genesis dbc in a tx with as many outputs as recipients.
+        let spend = |recipients: usize| -> eyre::Result<SignedSpend> {
+            let recipients = (0..recipients)
+                .map(|_| {
+                    let dbc_id_src = key.random_dbc_id_src(&mut rand::thread_rng());
+                    (Token::from_nano(1), dbc_id_src)
+                })
+                .collect();
+            let outputs = create_offline_transfer(
+                vec![(genesis.clone(), derived_key.clone())],
+                recipients,
+                key.public_address(),
+            )?;
+            outputs
+                .created_dbcs
+                .first()
+                .and_then(|created| created.dbc.signed_spends.first().cloned())
+                .ok_or_else(|| eyre!("No spend of the genesis dbc was created"))
+        };
+
+        let response = Response::Cmd(CmdResponse::Spend(Err(ProtocolError::Transfers(
+            TransferError::DoubleSpendAttempt {
+                new: Box::new(spend(10)?),
+                existing: Box::new(spend(10)?),
+            },
+        ))));
+        let size = rmp_serde::to_vec(&response)?.len();
+
+        assert!(size > ENVELOPE_OVERHEAD, "{size}");
+        assert!(size <= MsgKind::of_response(&response).max_size(), "{size}");
+
+        Ok(())
+    }
+}
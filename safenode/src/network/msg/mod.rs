@@ -7,50 +7,102 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 mod codec;
-pub(crate) use codec::{MsgCodec, MsgProtocol};
+mod limits;
+mod v1;
 
-use crate::network::{error::Error, NetworkEvent, SwarmDriver};
-use crate::protocol::messages::{Request, Response};
-use libp2p::request_response::{self, Message};
-use tracing::{trace, warn};
+pub(crate) use codec::{MsgCodec, MsgProtocol, MsgRequest, RejectedRequest};
+
+use crate::network::{error::Error, NetworkEvent, PeerMisbehaviour, SwarmDriver};
+use crate::protocol::messages::Response;
+use libp2p::request_response::{self, Message, OutboundFailure};
+use std::time::Instant;
+use tracing::{debug, trace, warn};
 
 impl SwarmDriver {
     /// Forwards `Request` to the upper layers using `Sender<NetworkEvent>`. Sends `Response` to the peers
-    pub async fn handle_msg(
+    pub(crate) async fn handle_msg(
         &mut self,
-        event: request_response::Event<Request, Response>,
+        event: request_response::Event<MsgRequest, Response>,
     ) -> Result<(), Error> {
         match event {
-            request_response::Event::Message { message, .. } => match message {
-                Message::Request {
-                    request,
-                    channel,
-                    request_id,
-                    ..
-                } => {
-                    trace!("Received request with id: {request_id:?}, req: {request:?}");
-                    self.event_sender
-                        .send(NetworkEvent::RequestReceived {
-                            req: request,
-                            channel,
-                        })
-                        .await?
-                }
-                Message::Response {
-                    request_id,
-                    response,
-                } => {
-                    trace!("Got response for id: {request_id:?}, res: {response:?} ");
-                    self.pending_requests
-                        .remove(&request_id)
-                        .ok_or(Error::ReceivedResponseDropped(request_id))?
-                        .send(Ok(response))
-                        .map_err(|_| Error::InternalMsgChannelDropped)?;
+            request_response::Event::Message { peer, message } => {
+                match message {
+                    Message::Request {
+                        request: Ok(request),
+                        channel,
+                        request_id,
+                    } => {
+                        trace!("Received request with id: {request_id:?} from {peer:?}, req: {request:?}");
+                        if let Err(retry_after) =
+                            self.rate_limiter.try_acquire(peer, Instant::now())
+                        {
+                            debug!("Peer {peer:?} exceeded its request rate, retry after {retry_after:?}");
+                            self.swarm
+                                .behaviour_mut()
+                                .request_response
+                                .send_response(channel, Response::Busy { retry_after })
+                                .map_err(Error::OutgoingResponseDropped)?;
+                            return Ok(());
+                        }
+                        self.event_sender
+                            .send(NetworkEvent::RequestReceived {
+                                peer,
+                                req: request,
+                                channel,
+                            })
+                            .await?
+                    }
+                    Message::Request {
+                        request: Err(rejected),
+                        channel,
+                        request_id,
+                    } => {
+                        if let RejectedRequest::Oversize(_) = rejected {
+                            let count = self.oversize_msgs.entry(peer).or_default();
+                            *count += 1;
+                            warn!("Rejected oversize request with id: {request_id:?} from {peer:?}, {count} so far: {rejected:?}");
+                            self.report_peer(peer, PeerMisbehaviour::OversizeMsg);
+                        } else {
+                            warn!("Could not decode request with id: {request_id:?} from {peer:?}: {rejected:?}");
+                        }
+                        self.swarm
+                            .behaviour_mut()
+                            .request_response
+                            .send_response(
+                                channel,
+                                Response::UnsupportedMsg(rejected.into_unsupported()),
+                            )
+                            .map_err(Error::OutgoingResponseDropped)?;
+                    }
+                    Message::Response {
+                        request_id,
+                        response,
+                    } => {
+                        trace!("Got response for id: {request_id:?}, res: {response:?} ");
+                        self.reputations.reward(peer);
+                        let result = match response {
+                            Response::UnsupportedMsg(unsupported) => {
+                                Err(Error::UnsupportedMsg(unsupported))
+                            }
+                            Response::Busy { retry_after } => Err(Error::PeerBusy(retry_after)),
+                            response => Ok(response),
+                        };
+                        self.pending_requests
+                            .remove(&request_id)
+                            .ok_or(Error::ReceivedResponseDropped(request_id))?
+                            .send(result)
+                            .map_err(|_| Error::InternalMsgChannelDropped)?;
+                    }
                 }
-            },
+            }
             request_response::Event::OutboundFailure {
-                request_id, error, ..
+                peer,
+                request_id,
+                error,
             } => {
+                if let OutboundFailure::Timeout = error {
+                    self.report_peer(peer, PeerMisbehaviour::Timeout);
+                }
                 self.pending_requests
                     .remove(&request_id)
                     .ok_or(Error::ReceivedResponseDropped(request_id))?
@@ -0,0 +1,342 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The messages of `/safe/1`, as they were when the protocol got versioned. Messages are
+//! converted to and from these when sent over `/safe/1`, so that peers still on it can decode
+//! them. Only the messages which changed since are redefined here, the others being the same.
+//! Messages which can't be expressed as of `/safe/1` are not sent over it.
+
+use crate::{
+    network_transfers::Error as TransferError,
+    node::NodeId,
+    protocol::{
+        address::{ChunkAddress, DbcAddress, RegisterAddress},
+        chunk::Chunk,
+        error::{Error as ProtocolError, Result},
+        fees::{FeeCiphers, RequiredFee, SpendPriority, SpendQueuePosition},
+        messages::{self, Event, RegisterCmd},
+        register::{Entry, EntryHash, Permissions, Policy, Register, User},
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use sn_dbc::{DbcId, DbcTransaction, SignedSpend};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+/// A message which can't be expressed as of `/safe/1`.
+#[derive(Debug)]
+pub(super) struct NotInV1;
+
+/// `/safe/1` version of [`messages::Request`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize)]
+pub(super) enum Request {
+    Cmd(Cmd),
+    Query(Query),
+    Event(Event),
+}
+
+/// `/safe/1` version of [`messages::Cmd`], without replication.
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize)]
+pub(super) enum Cmd {
+    StoreChunk(Chunk),
+    Register(RegisterCmd),
+    SpendDbc {
+        signed_spend: Box<SignedSpend>,
+        source_tx: Box<DbcTransaction>,
+        fee_ciphers: BTreeMap<NodeId, FeeCiphers>,
+    },
+}
+
+/// `/safe/1` version of [`messages::Query`].
+#[derive(Serialize, Deserialize)]
+pub(super) enum Query {
+    GetChunk(ChunkAddress),
+    Register(RegisterQuery),
+    Spend(SpendQuery),
+}
+
+/// `/safe/1` version of [`messages::RegisterQuery`], without op log queries.
+#[derive(Serialize, Deserialize)]
+pub(super) enum RegisterQuery {
+    Get(RegisterAddress),
+    Read(RegisterAddress),
+    GetEntry {
+        address: RegisterAddress,
+        hash: EntryHash,
+    },
+    GetPolicy(RegisterAddress),
+    GetUserPermissions {
+        address: RegisterAddress,
+        user: User,
+    },
+    GetOwner(RegisterAddress),
+}
+
+/// `/safe/1` version of [`messages::SpendQuery`], without queued spend and double spend queries.
+#[derive(Serialize, Deserialize)]
+pub(super) enum SpendQuery {
+    GetFees {
+        dbc_id: DbcId,
+        priority: SpendPriority,
+    },
+    GetDbcSpend(DbcAddress),
+}
+
+/// `/safe/1` version of [`messages::Response`], peers being unable to report
+/// undecodable requests or being busy.
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize)]
+pub(super) enum Response {
+    Cmd(CmdResponse),
+    Query(QueryResponse),
+}
+
+/// `/safe/1` version of [`messages::QueryResponse`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize)]
+pub(super) enum QueryResponse {
+    GetFees(Result<(NodeId, RequiredFee)>),
+    GetDbcSpend(Result<SignedSpend>),
+    GetChunk(Result<Chunk>),
+    GetRegister(Result<Register>),
+    GetRegisterEntry(Result<Entry>),
+    GetRegisterOwner(Result<User>),
+    ReadRegister(Result<BTreeSet<(EntryHash, Entry)>>),
+    GetRegisterPolicy(Result<Policy>),
+    GetRegisterUserPermissions(Result<Permissions>),
+}
+
+/// `/safe/1` version of [`messages::CmdResponse`], where accepted spends weren't given a position.
+#[derive(Serialize, Deserialize)]
+pub(super) enum CmdResponse {
+    Spend(Result<()>),
+    StoreChunk(Result<()>),
+    CreateRegister(Result<()>),
+    EditRegister(Result<()>),
+}
+
+impl From<Request> for messages::Request {
+    fn from(req: Request) -> Self {
+        match req {
+            Request::Cmd(Cmd::StoreChunk(chunk)) => {
+                messages::Request::Cmd(messages::Cmd::StoreChunk(chunk))
+            }
+            Request::Cmd(Cmd::Register(cmd)) => {
+                messages::Request::Cmd(messages::Cmd::Register(cmd))
+            }
+            Request::Cmd(Cmd::SpendDbc {
+                signed_spend,
+                source_tx,
+                fee_ciphers,
+            }) => messages::Request::Cmd(messages::Cmd::SpendDbc {
+                signed_spend,
+                source_tx,
+                fee_ciphers,
+            }),
+            Request::Query(Query::GetChunk(address)) => {
+                messages::Request::Query(messages::Query::GetChunk(address))
+            }
+            Request::Query(Query::Register(query)) => {
+                messages::Request::Query(messages::Query::Register(query.into()))
+            }
+            Request::Query(Query::Spend(SpendQuery::GetFees { dbc_id, priority })) => {
+                messages::Request::Query(messages::Query::Spend(messages::SpendQuery::GetFees {
+                    dbc_id,
+                    priority,
+                }))
+            }
+            Request::Query(Query::Spend(SpendQuery::GetDbcSpend(address))) => {
+                messages::Request::Query(messages::Query::Spend(messages::SpendQuery::GetDbcSpend(
+                    address,
+                )))
+            }
+            Request::Event(event) => messages::Request::Event(event),
+        }
+    }
+}
+
+impl TryFrom<messages::Request> for Request {
+    type Error = NotInV1;
+
+    fn try_from(req: messages::Request) -> std::result::Result<Self, NotInV1> {
+        let req = match req {
+            messages::Request::Cmd(messages::Cmd::StoreChunk(chunk)) => {
+                Request::Cmd(Cmd::StoreChunk(chunk))
+            }
+            messages::Request::Cmd(messages::Cmd::Register(cmd)) => {
+                Request::Cmd(Cmd::Register(cmd))
+            }
+            messages::Request::Cmd(messages::Cmd::SpendDbc {
+                signed_spend,
+                source_tx,
+                fee_ciphers,
+            }) => Request::Cmd(Cmd::SpendDbc {
+                signed_spend,
+                source_tx,
+                fee_ciphers,
+            }),
+            messages::Request::Cmd(messages::Cmd::Replicate(_)) => return Err(NotInV1),
+            messages::Request::Query(messages::Query::GetChunk(address)) => {
+                Request::Query(Query::GetChunk(address))
+            }
+            messages::Request::Query(messages::Query::Register(query)) => {
+                Request::Query(Query::Register(query.try_into()?))
+            }
+            messages::Request::Query(messages::Query::Spend(query)) => {
+                let query = match query {
+                    messages::SpendQuery::GetFees { dbc_id, priority } => {
+                        SpendQuery::GetFees { dbc_id, priority }
+                    }
+                    messages::SpendQuery::GetDbcSpend(address) => SpendQuery::GetDbcSpend(address),
+                    messages::SpendQuery::GetPendingDbcSpend(_)
+                    | messages::SpendQuery::GetDoubleSpendProof(_) => return Err(NotInV1),
+                };
+                Request::Query(Query::Spend(query))
+            }
+            messages::Request::Event(event) => Request::Event(event),
+        };
+        Ok(req)
+    }
+}
+
+impl From<RegisterQuery> for messages::RegisterQuery {
+    fn from(query: RegisterQuery) -> Self {
+        match query {
+            RegisterQuery::Get(address) => messages::RegisterQuery::Get(address),
+            RegisterQuery::Read(address) => messages::RegisterQuery::Read(address),
+            RegisterQuery::GetEntry { address, hash } => {
+                messages::RegisterQuery::GetEntry { address, hash }
+            }
+            RegisterQuery::GetPolicy(address) => messages::RegisterQuery::GetPolicy(address),
+            RegisterQuery::GetUserPermissions { address, user } => {
+                messages::RegisterQuery::GetUserPermissions { address, user }
+            }
+            RegisterQuery::GetOwner(address) => messages::RegisterQuery::GetOwner(address),
+        }
+    }
+}
+
+impl TryFrom<messages::RegisterQuery> for RegisterQuery {
+    type Error = NotInV1;
+
+    fn try_from(query: messages::RegisterQuery) -> std::result::Result<Self, NotInV1> {
+        let query = match query {
+            messages::RegisterQuery::Get(address) => RegisterQuery::Get(address),
+            messages::RegisterQuery::Read(address) => RegisterQuery::Read(address),
+            messages::RegisterQuery::GetEntry { address, hash } => {
+                RegisterQuery::GetEntry { address, hash }
+            }
+            messages::RegisterQuery::GetPolicy(address) => RegisterQuery::GetPolicy(address),
+            messages::RegisterQuery::GetUserPermissions { address, user } => {
+                RegisterQuery::GetUserPermissions { address, user }
+            }
+            messages::RegisterQuery::GetOwner(address) => RegisterQuery::GetOwner(address),
+            messages::RegisterQuery::GetOpLog(_) => return Err(NotInV1),
+        };
+        Ok(query)
+    }
+}
+
+impl From<Response> for messages::Response {
+    fn from(resp: Response) -> Self {
+        use messages::{CmdResponse as Cmd, QueryResponse as Query};
+
+        match resp {
+            Response::Cmd(resp) => messages::Response::Cmd(match resp {
+                // Peers on `/safe/1` don't say where they queued the spend, it's taken as next.
+                CmdResponse::Spend(result) => Cmd::Spend(result.map(|()| SpendQueuePosition {
+                    position: 0,
+                    eta: Duration::ZERO,
+                })),
+                CmdResponse::StoreChunk(result) => Cmd::StoreChunk(result),
+                CmdResponse::CreateRegister(result) => Cmd::CreateRegister(result),
+                CmdResponse::EditRegister(result) => Cmd::EditRegister(result),
+            }),
+            Response::Query(resp) => messages::Response::Query(match resp {
+                QueryResponse::GetFees(result) => Query::GetFees(result),
+                QueryResponse::GetDbcSpend(result) => Query::GetDbcSpend(result),
+                QueryResponse::GetChunk(result) => Query::GetChunk(result),
+                QueryResponse::GetRegister(result) => Query::GetRegister(result),
+                QueryResponse::GetRegisterEntry(result) => Query::GetRegisterEntry(result),
+                QueryResponse::GetRegisterOwner(result) => Query::GetRegisterOwner(result),
+                QueryResponse::ReadRegister(result) => Query::ReadRegister(result),
+                QueryResponse::GetRegisterPolicy(result) => Query::GetRegisterPolicy(result),
+                QueryResponse::GetRegisterUserPermissions(result) => {
+                    Query::GetRegisterUserPermissions(result)
+                }
+            }),
+        }
+    }
+}
+
+impl TryFrom<messages::Response> for Response {
+    type Error = NotInV1;
+
+    fn try_from(resp: messages::Response) -> std::result::Result<Self, NotInV1> {
+        use messages::{CmdResponse as Cmd, QueryResponse as Query};
+
+        let resp = match resp {
+            messages::Response::Cmd(resp) => Response::Cmd(match resp {
+                Cmd::Spend(result) => CmdResponse::Spend(v1_result(result.map(|_| ()))?),
+                Cmd::StoreChunk(result) => CmdResponse::StoreChunk(v1_result(result)?),
+                Cmd::CreateRegister(result) => CmdResponse::CreateRegister(v1_result(result)?),
+                Cmd::EditRegister(result) => CmdResponse::EditRegister(v1_result(result)?),
+                Cmd::Replicate(_) => return Err(NotInV1),
+            }),
+            messages::Response::Query(resp) => Response::Query(match resp {
+                Query::GetFees(result) => QueryResponse::GetFees(v1_result(result)?),
+                Query::GetDbcSpend(result) => QueryResponse::GetDbcSpend(v1_result(result)?),
+                Query::GetChunk(result) => QueryResponse::GetChunk(v1_result(result)?),
+                Query::GetRegister(result) => QueryResponse::GetRegister(v1_result(result)?),
+                Query::GetRegisterEntry(result) => {
+                    QueryResponse::GetRegisterEntry(v1_result(result)?)
+                }
+                Query::GetRegisterOwner(result) => {
+                    QueryResponse::GetRegisterOwner(v1_result(result)?)
+                }
+                Query::ReadRegister(result) => QueryResponse::ReadRegister(v1_result(result)?),
+                Query::GetRegisterPolicy(result) => {
+                    QueryResponse::GetRegisterPolicy(v1_result(result)?)
+                }
+                Query::GetRegisterUserPermissions(result) => {
+                    QueryResponse::GetRegisterUserPermissions(v1_result(result)?)
+                }
+                Query::GetDoubleSpendProof(_) | Query::GetRegisterOpLog(_) => return Err(NotInV1),
+            }),
+            messages::Response::UnsupportedMsg(_) | messages::Response::Busy { .. } => {
+                return Err(NotInV1)
+            }
+        };
+        Ok(resp)
+    }
+}
+
+// The result as is, unless it's an error added since `/safe/1`.
+fn v1_result<T>(result: Result<T>) -> std::result::Result<Result<T>, NotInV1> {
+    match result {
+        Err(
+            ProtocolError::RegisterSnapshotMismatch(_)
+            | ProtocolError::NodeShuttingDown
+            | ProtocolError::ReplicationNotFromCloseGroup(_)
+            | ProtocolError::Transfers(
+                TransferError::DoubleSpendDetected(_)
+                | TransferError::DoubleSpendNotFound(_)
+                | TransferError::SpendNotInTx(_)
+                | TransferError::MissingSiblingSpends(_)
+                | TransferError::SiblingSpendsNotFound { .. }
+                | TransferError::InvalidSpentTx(..),
+            ),
+        ) => Err(NotInV1),
+        result => Ok(result),
+    }
+}
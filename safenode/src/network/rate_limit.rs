@@ -0,0 +1,131 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use libp2p::PeerId;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// Number of requests per second a peer can sustain.
+const REQUESTS_PER_SEC: f64 = 50.0;
+// Number of requests a peer can send in a burst, after having been idle.
+const REQUESTS_BURST: f64 = 100.0;
+/// Interval at which the buckets of idle peers are pruned.
+pub(super) const BUCKETS_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Tokens refill at a fixed rate up to the burst size, each request taking one.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Per-peer token buckets, limiting the rate of the requests each peer can send us.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    buckets: HashMap<PeerId, TokenBucket>,
+    rate: f64,
+    burst: f64,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(REQUESTS_PER_SEC, REQUESTS_BURST)
+    }
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            buckets: HashMap::new(),
+            rate,
+            burst,
+        }
+    }
+
+    /// Take a token for a request from the peer, or return how long
+    /// until the next token is available if the peer exceeded its rate.
+    pub(crate) fn try_acquire(&mut self, peer: PeerId, now: Instant) -> Result<(), Duration> {
+        let bucket = self.buckets.entry(peer).or_insert(TokenBucket {
+            tokens: self.burst,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    /// Forget the peers whose buckets have refilled up to the burst size, as a new bucket
+    /// would be no different. The buckets of disconnected peers are kept until then,
+    /// so that reconnecting doesn't grant a peer a fresh burst.
+    pub(crate) fn prune(&mut self, now: Instant) {
+        let (rate, burst) = (self.rate, self.burst);
+        self.buckets.retain(|_, bucket| {
+            let elapsed = now.saturating_duration_since(bucket.last_refill);
+            bucket.tokens + elapsed.as_secs_f64() * rate < burst
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_allows_bursts_then_refills_at_rate() {
+        let mut limiter = RateLimiter::new(10.0, 5.0);
+        let (peer, other) = (PeerId::random(), PeerId::random());
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            assert!(limiter.try_acquire(peer, start).is_ok());
+        }
+        let retry_after = limiter.try_acquire(peer, start);
+        assert_eq!(retry_after, Err(Duration::from_millis(100)));
+
+        // Other peers have their own buckets
+        assert!(limiter.try_acquire(other, start).is_ok());
+
+        let later = start + Duration::from_millis(100);
+        assert!(limiter.try_acquire(peer, later).is_ok());
+        assert!(limiter.try_acquire(peer, later).is_err());
+    }
+
+    #[test]
+    fn rate_limiter_prunes_only_refilled_buckets() {
+        let mut limiter = RateLimiter::new(10.0, 5.0);
+        let peer = PeerId::random();
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            assert!(limiter.try_acquire(peer, start).is_ok());
+        }
+
+        // A drained bucket is kept, e.g. across a reconnection of the peer.
+        limiter.prune(start + Duration::from_millis(100));
+        assert!(limiter.try_acquire(peer, start).is_err());
+
+        // Once refilled, it's pruned, a new bucket being just as full.
+        let refilled = start + Duration::from_millis(500);
+        limiter.prune(refilled);
+        assert!(limiter.buckets.is_empty());
+        for _ in 0..5 {
+            assert!(limiter.try_acquire(peer, refilled).is_ok());
+        }
+        assert!(limiter.try_acquire(peer, refilled).is_err());
+    }
+}
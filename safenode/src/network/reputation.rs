@@ -0,0 +1,298 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::SwarmDriver;
+
+use libp2p::PeerId;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// The score of a peer we have no record of.
+pub const INITIAL_SCORE: i32 = 0;
+// Scores can't go above this, so a long history of good behaviour can't hide new misbehaviour.
+const MAX_SCORE: i32 = 100;
+// Peers below this score are only returned among the closest peers when there's no one better.
+const LOW_SCORE: i32 = -20;
+// Peers reaching this score are disconnected and banned.
+const BAN_SCORE: i32 = -100;
+// Increase of the score for each request a peer responds to.
+const RESPONSE_REWARD: i32 = 1;
+// How long a peer stays banned. It's then let back in with a low score.
+const BAN_DURATION: Duration = Duration::from_secs(60 * 60);
+/// How often the scores decay.
+pub(super) const SCORE_DECAY_INTERVAL: Duration = Duration::from_secs(60);
+// How much scores are brought back towards the initial score at each decay,
+// so that old behaviour, good or bad, is eventually forgotten.
+const SCORE_DECAY: i32 = 1;
+
+/// The misbehaviours peers are scored down for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerMisbehaviour {
+    /// Sent data signed with a signature which doesn't validate.
+    InvalidSignature,
+    /// Sent data which doesn't match the address it was requested from.
+    WrongData,
+    /// Notified us of a double spend attempt which isn't one.
+    FalseDoubleSpendNotice,
+    /// Didn't respond to a request in time.
+    Timeout,
+    /// Sent a message larger than the maximum size of its kind.
+    OversizeMsg,
+}
+
+impl PeerMisbehaviour {
+    // How much the misbehaviour lowers the peer's score.
+    fn penalty(&self) -> i32 {
+        match self {
+            // Timeouts also happen to honest peers, e.g. when churning
+            Self::Timeout => 5,
+            Self::OversizeMsg => 20,
+            Self::FalseDoubleSpendNotice => 25,
+            Self::InvalidSignature | Self::WrongData => 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reputation {
+    score: i32,
+    banned_until: Option<Instant>,
+}
+
+/// Scores of the peers we've interacted with. Peers whose score is back to
+/// the initial one are forgotten, as are those which disconnected with a good score.
+#[derive(Debug, Default)]
+pub(crate) struct Reputations {
+    peers: HashMap<PeerId, Reputation>,
+}
+
+impl Reputations {
+    /// Lower the score of the peer, returning whether it's now to be banned.
+    /// Timeouts alone never lower a score below the low one, as they also happen
+    /// to honest peers, so they deprioritise a peer but don't get it banned.
+    pub(crate) fn report(
+        &mut self,
+        peer: PeerId,
+        misbehaviour: PeerMisbehaviour,
+        now: Instant,
+    ) -> bool {
+        let reputation = self.peers.entry(peer).or_insert(Reputation {
+            score: INITIAL_SCORE,
+            banned_until: None,
+        });
+        if reputation.banned_until.is_some() {
+            return false;
+        }
+
+        let score = reputation.score.saturating_sub(misbehaviour.penalty());
+        reputation.score = match misbehaviour {
+            PeerMisbehaviour::Timeout => score.max(LOW_SCORE.min(reputation.score)),
+            _ => score,
+        };
+        if reputation.score <= BAN_SCORE {
+            reputation.banned_until = Some(now + BAN_DURATION);
+            return true;
+        }
+        false
+    }
+
+    /// Raise the score of the peer, as it responded to our request.
+    pub(crate) fn reward(&mut self, peer: PeerId) {
+        let reputation = self.peers.entry(peer).or_insert(Reputation {
+            score: INITIAL_SCORE,
+            banned_until: None,
+        });
+        if reputation.banned_until.is_none() {
+            reputation.score = (reputation.score + RESPONSE_REWARD).min(MAX_SCORE);
+        }
+    }
+
+    pub(crate) fn score(&self, peer: &PeerId) -> i32 {
+        self.peers
+            .get(peer)
+            .map_or(INITIAL_SCORE, |reputation| reputation.score)
+    }
+
+    pub(crate) fn is_low(&self, peer: &PeerId) -> bool {
+        self.score(peer) <= LOW_SCORE
+    }
+
+    /// Banned peers stay banned till their ban expires, their score not being raised meanwhile.
+    pub(crate) fn is_banned(&self, peer: &PeerId) -> bool {
+        matches!(self.peers.get(peer), Some(reputation) if reputation.banned_until.is_some())
+    }
+
+    pub(crate) fn scores(&self) -> HashMap<PeerId, i32> {
+        self.peers
+            .iter()
+            .map(|(peer, reputation)| (*peer, reputation.score))
+            .collect()
+    }
+
+    /// Forget the peer which disconnected, unless it's banned or its score is
+    /// still below the initial one, so it can't clear it by reconnecting.
+    pub(crate) fn disconnected(&mut self, peer: &PeerId) {
+        if !self.is_banned(peer) && self.score(peer) >= INITIAL_SCORE {
+            let _ = self.peers.remove(peer);
+        }
+    }
+
+    /// Bring the scores back towards the initial one, and lift the expired bans,
+    /// the peers being let back in with a low score. Returns the peers no longer banned.
+    pub(crate) fn decay(&mut self, now: Instant) -> Vec<PeerId> {
+        let mut unbanned = vec![];
+        for (peer, reputation) in self.peers.iter_mut() {
+            match reputation.banned_until {
+                Some(until) if until <= now => {
+                    reputation.banned_until = None;
+                    reputation.score = LOW_SCORE;
+                    unbanned.push(*peer);
+                }
+                Some(_) => {}
+                None if reputation.score > INITIAL_SCORE => {
+                    reputation.score = (reputation.score - SCORE_DECAY).max(INITIAL_SCORE);
+                }
+                None => {
+                    reputation.score = (reputation.score + SCORE_DECAY).min(INITIAL_SCORE);
+                }
+            }
+        }
+        self.peers.retain(|_, reputation| {
+            reputation.banned_until.is_some() || reputation.score != INITIAL_SCORE
+        });
+        unbanned
+    }
+}
+
+impl SwarmDriver {
+    /// Lower the score of the peer, disconnecting and banning it once its score is too low.
+    pub(super) fn report_peer(&mut self, peer: PeerId, misbehaviour: PeerMisbehaviour) {
+        let ban = self.reputations.report(peer, misbehaviour, Instant::now());
+        let score = self.reputations.score(&peer);
+        warn!("Peer {peer:?} misbehaved with {misbehaviour:?}, its score is now {score}");
+        if ban {
+            warn!("Banning peer {peer:?}");
+            let _ = self.swarm.behaviour_mut().kademlia.remove_peer(&peer);
+            self.close_group_cache.remove_peer(&peer);
+            // Closes the existing connections and denies any new ones
+            self.swarm.behaviour_mut().blocked_peers.block_peer(peer);
+        }
+    }
+
+    /// Decay the scores of peers, letting back in those whose ban expired.
+    pub(super) fn decay_reputations(&mut self) {
+        for peer in self.reputations.decay(Instant::now()) {
+            info!("The ban of peer {peer:?} expired");
+            self.swarm.behaviour_mut().blocked_peers.unblock_peer(peer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reputation_misbehaving_peer_is_deprioritised_then_banned_once() {
+        let mut reputations = Reputations::default();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        assert!(!reputations.report(peer, PeerMisbehaviour::OversizeMsg, now));
+        assert!(reputations.is_low(&peer));
+        assert!(!reputations.is_banned(&peer));
+
+        assert!(!reputations.report(peer, PeerMisbehaviour::WrongData, now));
+        assert!(reputations.report(peer, PeerMisbehaviour::InvalidSignature, now));
+        assert!(reputations.is_banned(&peer));
+
+        // Only the report crossing the threshold triggers the ban
+        assert!(!reputations.report(peer, PeerMisbehaviour::Timeout, now));
+        assert!(reputations.is_banned(&peer));
+    }
+
+    #[test]
+    fn reputation_rewards_are_capped() {
+        let mut reputations = Reputations::default();
+        let peer = PeerId::random();
+
+        for _ in 0..2 * MAX_SCORE {
+            reputations.reward(peer);
+        }
+        assert_eq!(reputations.score(&peer), MAX_SCORE);
+
+        // The capped score can't shield the peer from being deprioritised
+        for _ in 0..5 {
+            let _ = reputations.report(
+                peer,
+                PeerMisbehaviour::FalseDoubleSpendNotice,
+                Instant::now(),
+            );
+        }
+        assert!(reputations.is_low(&peer));
+    }
+
+    #[test]
+    fn reputation_timeouts_alone_never_ban() {
+        let mut reputations = Reputations::default();
+        let peer = PeerId::random();
+
+        for _ in 0..100 {
+            assert!(!reputations.report(peer, PeerMisbehaviour::Timeout, Instant::now()));
+        }
+        assert!(reputations.is_low(&peer));
+        assert!(!reputations.is_banned(&peer));
+        assert_eq!(reputations.score(&peer), LOW_SCORE);
+    }
+
+    #[test]
+    fn reputation_scores_decay_and_bans_expire() {
+        let mut reputations = Reputations::default();
+        let banned = PeerId::random();
+        let low = PeerId::random();
+        let good = PeerId::random();
+        let now = Instant::now();
+
+        assert!(!reputations.report(banned, PeerMisbehaviour::InvalidSignature, now));
+        assert!(reputations.report(banned, PeerMisbehaviour::InvalidSignature, now));
+        let _ = reputations.report(low, PeerMisbehaviour::OversizeMsg, now);
+        reputations.reward(good);
+
+        // A good score decays back to the initial one, and the peer is forgotten.
+        assert!(reputations.decay(now + SCORE_DECAY_INTERVAL).is_empty());
+        assert_eq!(reputations.score(&good), INITIAL_SCORE);
+        assert!(!reputations.peers.contains_key(&good));
+        assert_eq!(reputations.score(&low), LOW_SCORE + SCORE_DECAY);
+        assert!(!reputations.is_low(&low));
+
+        // The ban is lifted once expired, the peer being let back in with a low score.
+        assert_eq!(reputations.decay(now + BAN_DURATION), vec![banned]);
+        assert!(!reputations.is_banned(&banned));
+        assert!(reputations.is_low(&banned));
+    }
+
+    #[test]
+    fn reputation_is_forgotten_on_disconnection_unless_bad() {
+        let mut reputations = Reputations::default();
+        let good = PeerId::random();
+        let bad = PeerId::random();
+        let now = Instant::now();
+
+        reputations.reward(good);
+        let _ = reputations.report(bad, PeerMisbehaviour::WrongData, now);
+
+        reputations.disconnected(&good);
+        reputations.disconnected(&bad);
+        assert!(!reputations.peers.contains_key(&good));
+        // Reconnecting doesn't clear a bad score.
+        assert!(reputations.is_low(&bad));
+    }
+}
@@ -36,6 +36,13 @@ pub enum Error {
     /// Spend not found.
     #[error("Spend not found: {0:?}")]
     SpendNotFound(DbcAddress),
+    /// The Dbc was double spent, so it's unspendable. The conflicting spends
+    /// can be queried with `SpendQuery::GetDoubleSpendProof`.
+    #[error("The Dbc at {0:?} was double spent")]
+    DoubleSpendDetected(DbcAddress),
+    /// No double spend was recorded for the Dbc.
+    #[error("No double spend recorded for the Dbc at {0:?}")]
+    DoubleSpendNotFound(DbcAddress),
     /// A double spend attempt was detected.
     #[error("A double spend attempt was detected. Incoming and existing spend are not the same: {new:?}. Existing: {existing:?}")]
     DoubleSpendAttempt {
@@ -83,6 +90,25 @@ pub enum Error {
         "A parent tx of a requested spend could not be confirmed as valid. All parent signed spends of that tx {0:?}"
     )]
     InvalidSpendParent(BTreeSet<Box<SignedSpend>>),
+    /// The spent dbc is not among the inputs of the tx it's spent in.
+    #[error("The spent dbc {0:?} is not an input of the tx it's spent in.")]
+    SpendNotInTx(DbcAddress),
+    /// The spends of some of the inputs of the tx a dbc is spent in were not found,
+    /// so the tx can't be verified yet.
+    #[error("The spends of some of the inputs of the spent tx were not found: {0:?}")]
+    MissingSiblingSpends(Vec<DbcAddress>),
+    /// The spend was dropped from the queue, as the spends of some of the inputs of the tx
+    /// it's spent in were still not found after several attempts. It can be sent again.
+    #[error("The spend of {address:?} was dropped, the spends of some of the inputs of its tx were not found: {missing:?}")]
+    SiblingSpendsNotFound {
+        /// The address of the dropped spend.
+        address: DbcAddress,
+        /// The addresses of the spends which were not found.
+        missing: Vec<DbcAddress>,
+    },
+    /// The tx a dbc is spent in did not check out when verified with the blinded amounts of all its inputs.
+    #[error("The spent tx (with hash {0:?}) is invalid: {1}")]
+    InvalidSpentTx(sn_dbc::Hash, String),
     /// Not enough space to store the value.
     #[error("Not enough space")]
     NotEnoughSpace,
@@ -13,22 +13,58 @@ pub(crate) use self::error::{Error, Result};
 use crate::{
     node::NodeId,
     protocol::{
-        address::DbcAddress,
-        fees::{FeeCiphers, RequiredFee, SpendPriority, SpendQ},
+        address::{dbc_address, DbcAddress},
+        fees::{FeeCiphers, RequiredFee, SpendPriority, SpendQ, SpendQueuePosition},
+        messages::ReplicatedData,
     },
     storage::SpendStorage,
 };
 
 use sn_dbc::{DbcId, DbcTransaction, MainKey, SignedSpend, Token};
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
 
 const STARTING_FEE: u64 = 4000; // 0.000004 SNT
 
+/// Number of times a queued spend is retried while the spends of the other inputs of its tx are missing.
+const MAX_SIBLING_ATTEMPTS: usize = 10;
+/// Number of dropped spends remembered, as to tell why they're not found.
+const MAX_DROPPED_SPENDS: usize = 1024;
+
+/// A validated spend waiting in the queue to be recorded, along with the tx its dbc was
+/// created in, which is stored with it, as to let the nodes it's replicated to validate it.
+/// Queued spends are told apart by their signed spend only.
+#[derive(Clone, Debug)]
+pub(crate) struct QueuedSpend {
+    pub(crate) signed_spend: SignedSpend,
+    pub(crate) source_tx: DbcTransaction,
+}
+
+impl PartialEq for QueuedSpend {
+    fn eq(&self, other: &Self) -> bool {
+        self.signed_spend == other.signed_spend
+    }
+}
+
+impl Eq for QueuedSpend {}
+
+impl Hash for QueuedSpend {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.signed_spend.hash(state);
+    }
+}
+
 pub(super) struct Transfers {
     node_id: NodeId,
     node_reward_key: MainKey,
-    spend_queue: SpendQ<SignedSpend>,
+    spend_queue: SpendQ<QueuedSpend>,
+    sibling_attempts: BTreeMap<DbcAddress, usize>,
+    // The spends dropped as their siblings were not found, with the missing ones and when dropped.
+    dropped_spends: BTreeMap<DbcAddress, (Vec<DbcAddress>, Instant)>,
     storage: SpendStorage,
 }
 
@@ -39,6 +75,8 @@ impl Transfers {
             node_id,
             node_reward_key,
             spend_queue: SpendQ::with_fee(STARTING_FEE),
+            sibling_attempts: BTreeMap::new(),
+            dropped_spends: BTreeMap::new(),
             storage: SpendStorage::new(),
         }
     }
@@ -48,6 +86,14 @@ impl Transfers {
         self.storage.get(address).await
     }
 
+    /// Get the conflicting spends of a double spent Dbc from local store.
+    pub(crate) async fn get_double_spend_proof(
+        &self,
+        address: DbcAddress,
+    ) -> Result<(SignedSpend, SignedSpend)> {
+        self.storage.get_double_spend_proof(address).await
+    }
+
     /// Get the required fee for the specified spend priority.
     pub(crate) fn get_required_fee(
         &self,
@@ -80,54 +126,186 @@ impl Transfers {
         self.storage.try_add_double(a_spend, b_spend).await
     }
 
+    /// Adds a spend replicated by another node, after validating it along with its parents
+    /// as a new spend is, but for the fee, which was paid to the close group it was sent to.
+    pub(crate) async fn try_add_replicated(
+        &mut self,
+        signed_spend: &SignedSpend,
+        source_tx: &DbcTransaction,
+        parent_spends: BTreeSet<SignedSpend>,
+    ) -> Result<()> {
+        validate_source_tx(signed_spend, source_tx)?;
+        self.storage.validate(signed_spend).await?;
+        if !is_exempt_genesis_spend(signed_spend) {
+            validate_parent_spends(signed_spend, source_tx, parent_spends)?;
+        }
+        self.storage.try_add(signed_spend, source_tx).await
+    }
+
+    /// All the spends held, as to be replicated to other nodes.
+    pub(crate) async fn replicated_data(&self) -> Vec<ReplicatedData> {
+        self.storage.replicated_data().await
+    }
+
     /// Tries to add a new spend to the queue.
     ///
     /// All the provided data will be validated, and
-    /// if it is valid, the spend will be pushed onto the queue.
+    /// if it is valid, the spend will be pushed onto the queue,
+    /// returning its position in the queue.
     pub(crate) async fn try_add(
         &mut self,
         signed_spend: Box<SignedSpend>,
         source_tx: Box<DbcTransaction>,
         fee_ciphers: BTreeMap<NodeId, FeeCiphers>,
         parent_spends: BTreeSet<SignedSpend>,
-    ) -> Result<()> {
+    ) -> Result<SpendQueuePosition> {
         // 1. Validate the tx hash.
-        // Ensure that the provided src tx is the same as the
-        // one we have the hash of in the signed spend.
-        let provided_src_tx_hash = source_tx.hash();
-        let signed_src_tx_hash = signed_spend.src_tx_hash();
-
-        if provided_src_tx_hash != signed_src_tx_hash {
-            return Err(Error::TxSourceMismatch {
-                signed_src_tx_hash,
-                provided_src_tx_hash,
-            });
-        }
+        validate_source_tx(signed_spend.as_ref(), source_tx.as_ref())?;
+
+        // The genesis spend pays no fee, and has no parents, as it's where all tokens come from.
+        let is_genesis = is_exempt_genesis_spend(signed_spend.as_ref());
 
         // 2. Try extract the fee paid for this spend, and validate it.
-        let paid_fee = self.validate_fee(source_tx.as_ref(), fee_ciphers)?;
+        let paid_fee = if is_genesis {
+            Token::zero()
+        } else {
+            self.validate_fee(source_tx.as_ref(), fee_ciphers)?
+        };
 
         // 3. Validate the spend itself.
         self.storage.validate(signed_spend.as_ref()).await?;
 
         // 4. Validate the parents of the spend.
         // This also ensures that all parent's dst tx's are the same as the src tx of this spend.
-        validate_parent_spends(signed_spend.as_ref(), source_tx.as_ref(), parent_spends)?;
-
-        // This spend is valid and goes into the queue.
-        self.spend_queue.push(*signed_spend, paid_fee.as_nano());
-
-        // If the rate limit has elapsed..
-        if self.spend_queue.elapsed() {
-            // .. we process one from the queue.
-            // NB: This works for now. We can look at
-            // a timeout backstop in coming iterations.
-            if let Some((signed_spend, _)) = self.spend_queue.pop() {
-                return self.storage.try_add(&signed_spend).await;
+        if !is_genesis {
+            validate_parent_spends(signed_spend.as_ref(), source_tx.as_ref(), parent_spends)?;
+        }
+
+        // This spend is valid and goes into the queue, from which it's popped
+        // and recorded once the tx it's spent in is verified along with its siblings.
+        let queued = QueuedSpend {
+            signed_spend: *signed_spend,
+            source_tx: *source_tx,
+        };
+        let _ = self
+            .dropped_spends
+            .remove(&dbc_address(queued.signed_spend.dbc_id()));
+        self.spend_queue.push(queued.clone(), paid_fee.as_nano());
+        // The spend was just pushed, so it's always found.
+        let position = self
+            .spend_queue
+            .position(&queued)
+            .unwrap_or(SpendQueuePosition {
+                position: 0,
+                eta: self.spend_queue.time_to_next_pop(),
+            });
+
+        Ok(position)
+    }
+
+    /// Get a spend, either recorded or still queued, as to let the close groups
+    /// of its siblings verify the tx they're all spent in.
+    /// A spend dropped from the queue, as its siblings were not found, is reported as such.
+    pub(crate) async fn get_pending(&self, address: DbcAddress) -> Result<SignedSpend> {
+        match self.storage.get(address).await {
+            Err(Error::SpendNotFound(_)) => {}
+            result => return result,
+        }
+        if let Some(queued) = self
+            .spend_queue
+            .iter()
+            .map(|queued| &queued.signed_spend)
+            .find(|spend| dbc_address(spend.dbc_id()) == address)
+        {
+            return Ok(queued.clone());
+        }
+        match self.dropped_spends.get(&address) {
+            Some((missing, _)) => Err(Error::SiblingSpendsNotFound {
+                address,
+                missing: missing.clone(),
+            }),
+            None => Err(Error::SpendNotFound(address)),
+        }
+    }
+
+    /// Time left till the next queued spend can be recorded.
+    pub(crate) fn time_to_next_queued_spend(&self) -> Duration {
+        self.spend_queue.time_to_next_pop()
+    }
+
+    /// Pops the highest fee spend of the queue, along with its fee, if the rate limit has elapsed.
+    pub(crate) fn pop_queued_spend(&mut self) -> Option<(QueuedSpend, u64)> {
+        if !self.spend_queue.elapsed() {
+            return None;
+        }
+        self.spend_queue.pop()
+    }
+
+    /// Pops all the queued spends at once, regardless of the rate limit, e.g. when shutting down.
+    pub(crate) fn pop_all_queued_spends(&mut self) -> Vec<(QueuedSpend, u64)> {
+        self.sibling_attempts.clear();
+        std::iter::from_fn(|| self.spend_queue.pop()).collect()
+    }
+
+    /// Records a popped spend, once the tx it's spent in checks out
+    /// with the spends of its other inputs, i.e. its siblings.
+    pub(crate) async fn record(
+        &mut self,
+        queued: &QueuedSpend,
+        sibling_spends: &BTreeSet<SignedSpend>,
+    ) -> Result<()> {
+        let signed_spend = &queued.signed_spend;
+        match SpendStorage::verify_spent_tx(signed_spend, sibling_spends) {
+            // The attempts are kept count of till the spend is requeued or dropped.
+            Err(err @ Error::MissingSiblingSpends(_)) => return Err(err),
+            result => {
+                let _ = self
+                    .sibling_attempts
+                    .remove(&dbc_address(signed_spend.dbc_id()));
+                result?;
             }
         }
+        self.storage.try_add(signed_spend, &queued.source_tx).await
+    }
 
-        Ok(())
+    /// Pushes a popped spend whose siblings were not all found back onto the queue,
+    /// as they may still be on their way to their close groups. It's pushed with the fee
+    /// it was queued with, so it keeps its priority.
+    /// Returns false once it has been retried too many times, in which case it's dropped,
+    /// and reported as such when queried, along with the siblings which were not found.
+    pub(crate) fn requeue(
+        &mut self,
+        queued: QueuedSpend,
+        fee: u64,
+        missing: Vec<DbcAddress>,
+    ) -> bool {
+        let address = dbc_address(queued.signed_spend.dbc_id());
+        let attempts = self.sibling_attempts.entry(address).or_insert(0);
+        *attempts += 1;
+        if *attempts > MAX_SIBLING_ATTEMPTS {
+            let _ = self.sibling_attempts.remove(&address);
+            self.remember_dropped(address, missing);
+            return false;
+        }
+        self.spend_queue.push(queued, fee);
+        true
+    }
+
+    // Remember a dropped spend, forgetting the one dropped the longest ago if too many are held.
+    fn remember_dropped(&mut self, address: DbcAddress, missing: Vec<DbcAddress>) {
+        if self.dropped_spends.len() >= MAX_DROPPED_SPENDS {
+            let oldest = self
+                .dropped_spends
+                .iter()
+                .min_by_key(|(_, (_, dropped_at))| *dropped_at)
+                .map(|(address, _)| *address);
+            if let Some(oldest) = oldest {
+                let _ = self.dropped_spends.remove(&oldest);
+            }
+        }
+        let _ = self
+            .dropped_spends
+            .insert(address, (missing, Instant::now()));
     }
 
     fn validate_fee(
@@ -153,6 +331,33 @@ impl Transfers {
     }
 }
 
+/// Whether the spend is that of the genesis input of a local testnet, which pays no fee and has
+/// no parents. As the testnet genesis key is public, no spend is exempted outside local testnets.
+#[cfg(feature = "local-testnet")]
+fn is_exempt_genesis_spend(signed_spend: &SignedSpend) -> bool {
+    crate::protocol::dbc_genesis::is_genesis_spend(signed_spend)
+}
+
+#[cfg(not(feature = "local-testnet"))]
+fn is_exempt_genesis_spend(_signed_spend: &SignedSpend) -> bool {
+    false
+}
+
+/// Ensure that the provided src tx is the same as the
+/// one we have the hash of in the signed spend.
+fn validate_source_tx(signed_spend: &SignedSpend, source_tx: &DbcTransaction) -> Result<()> {
+    let provided_src_tx_hash = source_tx.hash();
+    let signed_src_tx_hash = signed_spend.src_tx_hash();
+
+    if provided_src_tx_hash != signed_src_tx_hash {
+        return Err(Error::TxSourceMismatch {
+            signed_src_tx_hash,
+            provided_src_tx_hash,
+        });
+    }
+    Ok(())
+}
+
 /// The src_tx is the tx where the dbc to spend, was created.
 /// The signed_spend.dbc_id() shall exist among its outputs.
 fn validate_parent_spends(
@@ -9,18 +9,24 @@
 use super::{
     error::{Error, Result},
     event::NodeEventsChannel,
-    Node, NodeEvent,
+    scheduler::{
+        FairQueue, BUSY_RETRY_AFTER, MAX_CONCURRENT_REQUESTS, MAX_QUEUED_REQUESTS,
+        MAX_QUEUED_REQUESTS_PER_PEER,
+    },
+    Node, NodeEvent, RunningNode,
 };
 
 use crate::{
-    network::{close_group_majority, NetworkEvent, SwarmDriver},
-    network_transfers::{Error as TransferError, Transfers},
+    network::{
+        close_group_majority, Network, NetworkEvent, PeerMisbehaviour, SwarmDriver, TransportMode,
+    },
+    network_transfers::{Error as TransferError, QueuedSpend, Transfers},
     protocol::{
-        address::{dbc_address, DbcAddress},
+        address::{dbc_address, RegisterAddress},
         error::Error as ProtocolError,
         messages::{
-            Cmd, CmdResponse, Event, Query, QueryResponse, RegisterCmd, Request, Response,
-            SpendQuery,
+            Cmd, CmdResponse, Event, Query, QueryResponse, RegisterCmd, RegisterQuery,
+            ReplicatedData, Request, Response, SpendQuery,
         },
         register::User,
     },
@@ -30,108 +36,171 @@ use crate::{
 use sn_dbc::{DbcTransaction, MainKey, SignedSpend};
 
 use futures::future::select_all;
-use libp2p::{request_response::ResponseChannel, PeerId};
-use std::{collections::BTreeSet, net::SocketAddr, time::Duration};
-use tokio::task::spawn;
+use itertools::Itertools;
+use libp2p::{request_response::ResponseChannel, Multiaddr, PeerId};
+use std::{
+    collections::BTreeSet,
+    net::SocketAddr,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{RwLock, Semaphore},
+    task::spawn,
+};
 use xor_name::XorName;
 
 impl Node {
     /// Asynchronously runs a new node instance, setting up the swarm driver,
-    /// creating a data storage, and handling network events. Returns the
-    /// created node and a `NodeEventsChannel` for listening to node-related
-    /// events.
+    /// creating a data storage, and handling network events. The node listens on the
+    /// address over the chosen transport(s). Returns a handle
+    /// to the running node, to listen to node-related events and to shut it down.
     ///
     /// # Returns
     ///
-    /// A tuple containing a `Node` instance and a `NodeEventsChannel`.
+    /// A `RunningNode`, exposing the node's `NodeEventsChannel`.
     ///
     /// # Errors
     ///
     /// Returns an error if there is a problem initializing the `SwarmDriver`.
-    pub async fn run(addr: SocketAddr) -> Result<NodeEventsChannel> {
-        let (network, mut network_event_receiver, swarm_driver) = SwarmDriver::new(addr)?;
+    pub async fn run(
+        addr: SocketAddr,
+        transport: TransportMode,
+        initial_peers: Vec<(PeerId, Multiaddr)>,
+    ) -> Result<RunningNode> {
+        let (network, mut network_event_receiver, swarm_driver) =
+            SwarmDriver::new(addr, transport)?;
         let node_events_channel = NodeEventsChannel::default();
         let node_id = super::to_node_id(network.peer_id);
 
-        let mut node = Self {
+        let node = Self {
             network,
             chunks: ChunkStorage::new(),
             registers: RegisterStorage::new(),
-            transfers: Transfers::new(node_id, MainKey::random()),
-            events_channel: node_events_channel.clone(),
+            transfers: Arc::new(RwLock::new(Transfers::new(node_id, MainKey::random()))),
+            register_op_fetches: Arc::default(),
+            events_channel: node_events_channel,
+            shutting_down: Arc::new(AtomicBool::new(false)),
         };
+        let running_node = RunningNode { node: node.clone() };
 
         let _handle = spawn(swarm_driver.run());
+        node.process_spend_queue();
+
+        // Dial the provided peers, either over QUIC or TCP according to their multiaddr.
+        for (peer_id, peer_addr) in initial_peers {
+            if let Err(err) = node.network.dial(peer_id, peer_addr.clone()).await {
+                warn!("Failed to dial peer {peer_id:?} at {peer_addr}: {err}");
+            }
+        }
+
         let _handle = spawn(async move {
+            // Requests are queued as they arrive and handled by a bounded pool of
+            // tasks, taking each peer's requests in turn.
+            let mut queue = FairQueue::new(MAX_QUEUED_REQUESTS, MAX_QUEUED_REQUESTS_PER_PEER);
+            let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
             loop {
-                let event = match network_event_receiver.recv().await {
-                    Some(event) => event,
-                    None => {
-                        error!("The `NetworkEvent` channel has been closed");
-                        continue;
+                tokio::select! {
+                    event = network_event_receiver.recv() => match event {
+                        Some(NetworkEvent::RequestReceived { peer, req, channel }) => {
+                            if let Err((req, channel)) = queue.push(peer, (req, channel)) {
+                                debug!("Too busy to handle request from {peer:?}: {req:?}");
+                                let resp = Response::Busy { retry_after: BUSY_RETRY_AFTER };
+                                if let Err(err) = node.network.send_response(resp, channel).await {
+                                    warn!("Error while sending response: {err:?}");
+                                }
+                            }
+                        }
+                        Some(NetworkEvent::PeerAdded) => node.handle_peer_added(),
+                        None => {
+                            if node.is_shutting_down() {
+                                debug!("The `NetworkEvent` channel has been closed on shutdown");
+                            } else {
+                                error!("The `NetworkEvent` channel has been closed");
+                            }
+                            return;
+                        }
+                    },
+                    permit = permits.clone().acquire_owned(), if !queue.is_empty() => {
+                        let (permit, (peer, (req, channel))) = match (permit, queue.pop()) {
+                            (Ok(permit), Some(next)) => (permit, next),
+                            _ => continue,
+                        };
+                        let node = node.clone();
+                        let _handle = spawn(async move {
+                            if let Err(err) = node.handle_request(peer, req, channel).await {
+                                warn!("Error handling request from {peer:?}: {err}");
+                            }
+                            drop(permit);
+                        });
                     }
-                };
-                if let Err(err) = node.handle_network_event(event).await {
-                    warn!("Error handling network event: {err}");
                 }
             }
         });
 
-        Ok(node_events_channel)
+        Ok(running_node)
     }
 
-    async fn handle_network_event(&mut self, event: NetworkEvent) -> Result<()> {
-        match event {
-            NetworkEvent::RequestReceived { req, channel } => {
-                self.handle_request(req, channel).await?
-            }
-            NetworkEvent::PeerAdded => {
-                self.events_channel.broadcast(NodeEvent::ConnectedToNetwork);
-                let target = {
-                    let mut rng = rand::thread_rng();
-                    XorName::random(&mut rng)
-                };
-
-                let network = self.network.clone();
-                let _handle = spawn(async move {
-                    trace!("Getting closest peers for target {target:?}");
-                    let result = network.node_get_closest_peers(target).await;
-                    trace!("For target {target:?}, get closest peers {result:?}");
-                });
-            }
-        }
+    fn handle_peer_added(&self) {
+        self.events_channel.broadcast(NodeEvent::ConnectedToNetwork);
+        let target = {
+            let mut rng = rand::thread_rng();
+            XorName::random(&mut rng)
+        };
 
-        Ok(())
+        let network = self.network.clone();
+        let _handle = spawn(async move {
+            trace!("Getting closest peers for target {target:?}");
+            let result = network.node_get_closest_peers(target).await;
+            trace!("For target {target:?}, get closest peers {result:?}");
+        });
     }
 
     async fn handle_request(
-        &mut self,
+        &self,
+        peer: PeerId,
         request: Request,
         response_channel: ResponseChannel<Response>,
     ) -> Result<()> {
         trace!("Handling request: {request:?}");
         let response = match request {
-            Request::Cmd(cmd) => Response::Cmd(self.handle_cmd(cmd).await),
+            Request::Cmd(cmd) => Response::Cmd(self.handle_cmd(peer, cmd).await),
             Request::Query(query) => Response::Query(self.handle_query(query).await),
             Request::Event(event) => {
                 match event {
                     Event::DoubleSpendAttempted(a_spend, b_spend) => {
-                        self.transfers
-                            .try_add_double(a_spend.as_ref(), b_spend.as_ref())
+                        let result = self
+                            .transfers
+                            .write()
                             .await
-                            .map_err(ProtocolError::Transfers)?;
+                            .try_add_double(a_spend.as_ref(), b_spend.as_ref())
+                            .await;
+                        if let Err(TransferError::NotADoubleSpendAttempt(..)) = result {
+                            self.report_peer(peer, PeerMisbehaviour::FalseDoubleSpendNotice)
+                                .await;
+                        }
+                        result.map_err(ProtocolError::Transfers)?;
                         return Ok(());
                     }
                 };
             }
         };
 
+        if let Response::Cmd(
+            CmdResponse::CreateRegister(Err(ProtocolError::InvalidSignature(_)))
+            | CmdResponse::EditRegister(Err(ProtocolError::InvalidSignature(_))),
+        ) = response
+        {
+            self.report_peer(peer, PeerMisbehaviour::InvalidSignature)
+                .await;
+        }
+
         self.send_response(response, response_channel).await;
 
         Ok(())
     }
 
-    async fn handle_query(&mut self, query: Query) -> QueryResponse {
+    async fn handle_query(&self, query: Query) -> QueryResponse {
         match query {
             Query::Register(query) => self.registers.read(&query, User::Anyone).await,
             Query::GetChunk(address) => {
@@ -144,23 +213,61 @@ impl Node {
                         // The client is asking for the fee to spend a specific dbc, and including the id of that dbc.
                         // The required fee content is encrypted to that dbc id, and so only the holder of the dbc secret
                         // key can unlock the contents.
-                        let required_fee = self.transfers.get_required_fee(dbc_id, priority);
+                        let required_fee = self
+                            .transfers
+                            .read()
+                            .await
+                            .get_required_fee(dbc_id, priority);
                         QueryResponse::GetFees(Ok(required_fee))
                     }
                     SpendQuery::GetDbcSpend(address) => {
                         let res = self
                             .transfers
+                            .read()
+                            .await
                             .get(address)
                             .await
                             .map_err(ProtocolError::Transfers);
                         QueryResponse::GetDbcSpend(res)
                     }
+                    SpendQuery::GetPendingDbcSpend(address) => {
+                        let res = self
+                            .transfers
+                            .read()
+                            .await
+                            .get_pending(address)
+                            .await
+                            .map_err(ProtocolError::Transfers);
+                        QueryResponse::GetDbcSpend(res)
+                    }
+                    SpendQuery::GetDoubleSpendProof(address) => {
+                        let res = self
+                            .transfers
+                            .read()
+                            .await
+                            .get_double_spend_proof(address)
+                            .await
+                            .map_err(ProtocolError::Transfers);
+                        QueryResponse::GetDoubleSpendProof(res)
+                    }
                 }
             }
         }
     }
 
-    async fn handle_cmd(&mut self, cmd: Cmd) -> CmdResponse {
+    async fn handle_cmd(&self, peer: PeerId, cmd: Cmd) -> CmdResponse {
+        if self.is_shutting_down() {
+            debug!("Rejecting cmd as shutting down: {cmd:?}");
+            let error = ProtocolError::NodeShuttingDown;
+            return match cmd {
+                Cmd::StoreChunk(_) => CmdResponse::StoreChunk(Err(error)),
+                Cmd::Register(RegisterCmd::Create(_)) => CmdResponse::CreateRegister(Err(error)),
+                Cmd::Register(RegisterCmd::Edit(_)) => CmdResponse::EditRegister(Err(error)),
+                Cmd::SpendDbc { .. } => CmdResponse::Spend(Err(error)),
+                Cmd::Replicate(_) => CmdResponse::Replicate(Err(error)),
+            };
+        }
+
         match cmd {
             Cmd::StoreChunk(chunk) => {
                 let resp = self.chunks.store(&chunk).await;
@@ -168,6 +275,10 @@ impl Node {
             }
             Cmd::Register(cmd) => {
                 let result = self.registers.write(&cmd).await;
+                let address = cmd.dst();
+                if result.is_ok() && self.registers.has_pending_cmds(&address).await {
+                    self.fetch_missing_register_ops(address).await;
+                }
                 match cmd {
                     RegisterCmd::Create(_) => CmdResponse::CreateRegister(result),
                     RegisterCmd::Edit(_) => CmdResponse::EditRegister(result),
@@ -193,24 +304,16 @@ impl Node {
 
                 // Then we try to add the spend to the transfers.
                 // This will validate all the necessary components of the spend.
-                let res = match self
+                // Only this critical section is serialised with the other spends.
+                let res = self
                     .transfers
-                    .try_add(signed_spend, source_tx, fee_ciphers, parent_spends)
+                    .write()
                     .await
-                {
+                    .try_add(signed_spend, source_tx, fee_ciphers, parent_spends)
+                    .await;
+                let res = match res {
                     Err(TransferError::DoubleSpendAttempt { new, existing }) => {
-                        warn!("Double spend attempted! New: {new:?}. Existing:  {existing:?}");
-                        if let Ok(event) =
-                            Event::double_spend_attempt(new.clone(), existing.clone())
-                        {
-                            match self.send_to_closest(&Request::Event(event)).await {
-                                Ok(_) => {}
-                                Err(err) => {
-                                    warn!("Failed to send double spend event to closest peers: {err:?}");
-                                }
-                            }
-                        }
-
+                        self.notify_double_spend_attempt(&new, &existing).await;
                         Err(ProtocolError::Transfers(
                             TransferError::DoubleSpendAttempt { new, existing },
                         ))
@@ -220,9 +323,226 @@ impl Node {
 
                 CmdResponse::Spend(res)
             }
+            Cmd::Replicate(data) => CmdResponse::Replicate(self.store_replicated(peer, data).await),
+        }
+    }
+
+    // Let the close group of a double spent dbc know about the attempt.
+    async fn notify_double_spend_attempt(&self, new: &SignedSpend, existing: &SignedSpend) {
+        warn!("Double spend attempted! New: {new:?}. Existing:  {existing:?}");
+        if let Ok(event) =
+            Event::double_spend_attempt(Box::new(new.clone()), Box::new(existing.clone()))
+        {
+            match self.send_to_closest(&Request::Event(event)).await {
+                Ok(_) => {}
+                Err(err) => {
+                    warn!("Failed to send double spend event to closest peers: {err:?}");
+                }
+            }
+        }
+    }
+
+    // Record the queued spends in the background, at the rate the spend queue allows,
+    // so they're recorded even when no other spend comes in to trigger it.
+    fn process_spend_queue(&self) {
+        let node = self.clone();
+        let _handle = spawn(async move {
+            while !node.is_shutting_down() {
+                let wait = node.transfers.read().await.time_to_next_queued_spend();
+                // Popping from an empty queue also resets its rate limit,
+                // so we don't spin while there's nothing to record.
+                tokio::time::sleep(wait).await;
+
+                let popped = node.transfers.write().await.pop_queued_spend();
+                if let Some((queued, fee)) = popped {
+                    match node.record_queued_spend(&queued).await {
+                        Err(TransferError::MissingSiblingSpends(missing)) => {
+                            debug!(
+                                "Spends missing for the tx of {:?}: {missing:?}",
+                                queued.signed_spend
+                            );
+                            let address = dbc_address(queued.signed_spend.dbc_id());
+                            let requeued =
+                                node.transfers
+                                    .write()
+                                    .await
+                                    .requeue(queued, fee, missing.clone());
+                            if !requeued {
+                                warn!("Dropped queued spend {address:?}, its siblings were not found: {missing:?}");
+                            }
+                        }
+                        result => node.handle_record_result(result).await,
+                    }
+                }
+            }
+        });
+    }
+
+    // Record a popped spend, after fetching its siblings, i.e. the spends of the other inputs
+    // of the tx it's spent in, from the network, as to verify that tx.
+    pub(super) async fn record_queued_spend(
+        &self,
+        queued: &QueuedSpend,
+    ) -> Result<(), TransferError> {
+        let sibling_spends = self.get_sibling_spends(&queued.signed_spend).await;
+        self.transfers
+            .write()
+            .await
+            .record(queued, &sibling_spends)
+            .await
+    }
+
+    pub(super) async fn handle_record_result(&self, result: Result<(), TransferError>) {
+        match result {
+            Ok(()) => {}
+            Err(TransferError::DoubleSpendAttempt { new, existing }) => {
+                self.notify_double_spend_attempt(&new, &existing).await;
+            }
+            Err(err) => warn!("Failed to record queued spend: {err}"),
+        }
+    }
+
+    // Store data handed over by a node leaving the network, which must be one of the peers
+    // of the close group of the data, as only they held it. Spends are validated along with
+    // their parents, as when first sent to their close group.
+    async fn store_replicated(
+        &self,
+        peer: PeerId,
+        data: ReplicatedData,
+    ) -> Result<(), ProtocolError> {
+        trace!("Storing replicated data: {:?}", data.dst());
+        let from_close_group = match self.network.node_get_closest_peers(data.name()).await {
+            Ok(close_group) => close_group.contains(&peer),
+            Err(err) => {
+                warn!("Failed to get the close group of {:?}: {err}", data.dst());
+                false
+            }
+        };
+        if !from_close_group {
+            warn!(
+                "Peer {peer:?} replicated {:?} without being in its close group",
+                data.dst()
+            );
+            return Err(ProtocolError::ReplicationNotFromCloseGroup(data.dst()));
+        }
+
+        match data {
+            ReplicatedData::Chunk(chunk) => self.chunks.store(&chunk).await,
+            ReplicatedData::RegisterWrite(cmd) => self.registers.write(&cmd).await,
+            ReplicatedData::RegisterLog(log) => self.registers.update(&log).await,
+            ReplicatedData::ValidSpend((signed_spend, source_tx)) => {
+                let parent_spends = match self.get_parent_spends(&source_tx).await {
+                    Ok(parent_spends) => parent_spends,
+                    Err(Error::Protocol(err)) => return Err(err),
+                    Err(error) => {
+                        return Err(ProtocolError::Transfers(
+                            TransferError::SpendParentCloseGroupIssue(error.to_string()),
+                        ))
+                    }
+                };
+                self.transfers
+                    .write()
+                    .await
+                    .try_add_replicated(&signed_spend, &source_tx, parent_spends)
+                    .await
+                    .map_err(ProtocolError::Transfers)
+            }
+            ReplicatedData::DoubleSpend((_, spends)) => {
+                let mut transfers = self.transfers.write().await;
+                for (a_spend, b_spend) in spends.iter().tuple_windows() {
+                    transfers
+                        .try_add_double(a_spend, b_spend)
+                        .await
+                        .map_err(ProtocolError::Transfers)?;
+                }
+                Ok(())
+            }
         }
     }
 
+    // Fetch the ops log of a Register from the close group peers in the background,
+    // so the cmds we buffered waiting for ops we don't hold yet can be applied.
+    // There is at most one fetch in flight per Register, backing off while ops keep missing.
+    async fn fetch_missing_register_ops(&self, address: RegisterAddress) {
+        if !self
+            .register_op_fetches
+            .lock()
+            .await
+            .try_start(address, Instant::now())
+        {
+            trace!("Not fetching missing ops of {address:?}, a fetch is in flight or backing off");
+            return;
+        }
+
+        let network = self.network.clone();
+        let registers = self.registers.clone();
+        let fetches = self.register_op_fetches.clone();
+        let _handle = spawn(async move {
+            let ops_missing = Self::fetch_register_ops(&network, &registers, address).await;
+            fetches
+                .lock()
+                .await
+                .finish(&address, ops_missing, Instant::now());
+        });
+    }
+
+    // Fetch the ops log of a Register from the close group peers one after the other,
+    // till the buffered cmds can be applied. Returns whether some are still missing ops.
+    async fn fetch_register_ops(
+        network: &Network,
+        registers: &RegisterStorage,
+        address: RegisterAddress,
+    ) -> bool {
+        let peers = match network.node_get_closest_peers(*address.name()).await {
+            Ok(peers) => peers,
+            Err(err) => {
+                warn!("Failed to get closest peers to fetch missing ops of {address:?}: {err}");
+                return true;
+            }
+        };
+
+        let request = Request::Query(Query::Register(RegisterQuery::GetOpLog(address)));
+        for peer in peers {
+            let response = tokio::time::timeout(
+                Duration::from_secs(10),
+                network.send_request(request.clone(), peer),
+            )
+            .await;
+            match response {
+                Ok(Ok(Response::Query(QueryResponse::GetRegisterOpLog(Ok(log)))))
+                    if log.address == address =>
+                {
+                    if let Err(err) = registers.update(&log).await {
+                        warn!("Failed to apply ops of {address:?} from {peer:?}: {err}");
+                        if let ProtocolError::InvalidSignature(_) = err {
+                            let _ = network
+                                .report_peer(peer, PeerMisbehaviour::InvalidSignature)
+                                .await;
+                        }
+                    }
+                }
+                Ok(Ok(Response::Query(QueryResponse::GetRegisterOpLog(Ok(log))))) => {
+                    warn!(
+                        "Peer {peer:?} sent ops of {:?} for {address:?}",
+                        log.address
+                    );
+                    let _ = network.report_peer(peer, PeerMisbehaviour::WrongData).await;
+                }
+                other => {
+                    trace!("Could not get ops of {address:?} from {peer:?}: {other:?}")
+                }
+            }
+
+            if !registers.has_pending_cmds(&address).await {
+                debug!("All buffered cmds of Register {address:?} have been applied");
+                return false;
+            }
+        }
+
+        warn!("Register {address:?} still holds cmds with missing dependencies");
+        true
+    }
+
     // This call makes sure we get the same spend from all in the close group.
     // If we receive a spend here, it is assumed to be valid. But we will verify
     // that anyway, in the code right after this for loop.
@@ -239,16 +559,47 @@ impl Node {
             // This call makes sure we get the same spend from all in the close group.
             // If we receive a spend here, it is assumed to be valid. But we will verify
             // that anyway, in the code right after this for loop.
-            let parent_spend = self.get_spend(parent_address).await?;
+            let parent_spend = self
+                .get_spend(SpendQuery::GetDbcSpend(parent_address))
+                .await?;
             let _ = all_parent_spends.insert(parent_spend);
         }
 
         Ok(all_parent_spends)
     }
 
+    // Fetch the spends of the other inputs of the tx the dbc is spent in, recorded or
+    // still queued, from their close groups. Those not found are left out.
+    async fn get_sibling_spends(&self, signed_spend: &SignedSpend) -> BTreeSet<SignedSpend> {
+        let sibling_addresses: Vec<_> = signed_spend
+            .spend
+            .dst_tx
+            .inputs
+            .iter()
+            .map(|input| input.dbc_id())
+            .filter(|dbc_id| dbc_id != signed_spend.dbc_id())
+            .map(|dbc_id| dbc_address(&dbc_id))
+            .collect();
+
+        let mut sibling_spends = BTreeSet::new();
+        for address in sibling_addresses {
+            match self
+                .get_spend(SpendQuery::GetPendingDbcSpend(address))
+                .await
+            {
+                Ok(spend) => {
+                    let _ = sibling_spends.insert(spend);
+                }
+                Err(err) => debug!("Sibling spend {address:?} not found: {err}"),
+            }
+        }
+        sibling_spends
+    }
+
     /// Retrieve a `Spend` from the closest peers
-    async fn get_spend(&self, address: DbcAddress) -> Result<SignedSpend> {
-        let request = Request::Query(Query::Spend(SpendQuery::GetDbcSpend(address)));
+    async fn get_spend(&self, query: SpendQuery) -> Result<SignedSpend> {
+        let address = query.dst();
+        let request = Request::Query(Query::Spend(query));
         info!("Getting the closest peers to {:?}", request.dst());
 
         let responses = self.send_to_closest(&request).await?;
@@ -309,6 +660,12 @@ impl Node {
         Err(super::Error::Protocol(ProtocolError::UnexpectedResponses))
     }
 
+    async fn report_peer(&self, peer: PeerId, misbehaviour: PeerMisbehaviour) {
+        if let Err(err) = self.network.report_peer(peer, misbehaviour).await {
+            warn!("Error while reporting peer {peer:?} for {misbehaviour:?}: {err:?}");
+        }
+    }
+
     async fn send_response(&self, resp: Response, response_channel: ResponseChannel<Response>) {
         if let Err(err) = self.network.send_response(resp, response_channel).await {
             warn!("Error while sending response: {err:?}");
@@ -332,7 +689,7 @@ impl Node {
     // If `get_all_responses` is true, we wait for the responses from all the peers. Will return an
     // error if the request timeouts.
     // If `get_all_responses` is false, we return the first successful response that we get
-    async fn send_and_get_responses(
+    pub(super) async fn send_and_get_responses(
         &self,
         peers: Vec<PeerId>,
         req: &Request,
@@ -38,4 +38,17 @@ impl NodeEventsChannel {
 pub enum NodeEvent {
     /// The node has been connected to the network
     ConnectedToNetwork,
+    /// The node started shutting down, no longer accepting writes.
+    ShuttingDown,
+    /// The node handed the data it holds over to the peers taking over its share of the data.
+    DataHandedOver {
+        /// Number of items at least one peer accepted.
+        handed_over: usize,
+        /// Number of items no peer accepted.
+        failed: usize,
+    },
+    /// The node flushed its stores.
+    StoresFlushed,
+    /// The node closed its connections with all peers, and has stopped.
+    ConnectionsClosed,
 }
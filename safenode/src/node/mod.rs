@@ -9,10 +9,13 @@
 mod api;
 mod error;
 mod event;
+mod op_fetches;
+mod scheduler;
+mod shutdown;
 
-pub use self::event::NodeEvent;
+pub use self::{event::NodeEvent, shutdown::RunningNode};
 
-use self::{error::Error, event::NodeEventsChannel};
+use self::{error::Error, event::NodeEventsChannel, op_fetches::OpFetches};
 
 use crate::{
     network::Network,
@@ -22,17 +25,32 @@ use crate::{
 
 use libp2p::PeerId;
 use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    sync::{atomic::AtomicBool, Arc},
+};
+use tokio::sync::{Mutex, RwLock};
 use xor_name::{XorName, XOR_NAME_LEN};
 
 /// `Node` represents a single node in the distributed network. It handles
 /// network events, processes incoming requests, interacts with the data
 /// storage, and broadcasts node-related events.
+///
+/// Each request is handled by its own task on a clone of the `Node`, the storage
+/// components handling their own locking.
+#[derive(Clone)]
 pub struct Node {
     network: Network,
     chunks: ChunkStorage,
     registers: RegisterStorage,
-    transfers: Transfers,
+    // Spends are validated and recorded while holding the write lock,
+    // so that no double spend attempt can slip in between.
+    transfers: Arc<RwLock<Transfers>>,
+    // The fetches of the ops missing from the Registers we hold cmds for.
+    register_op_fetches: Arc<Mutex<OpFetches>>,
     events_channel: NodeEventsChannel,
+    // Set once shutting down, to reject any further write.
+    shutting_down: Arc<AtomicBool>,
 }
 
 /// A unique identifier for a node in the network,
@@ -42,6 +60,12 @@ pub struct Node {
 )]
 pub struct NodeId(XorName);
 
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}", self.0)
+    }
+}
+
 /// Returns a `NodeId` representation of the `PeerId`.
 pub fn to_node_id(peer_id: PeerId) -> NodeId {
     let mut xorname_bytes = [0u8; XOR_NAME_LEN];
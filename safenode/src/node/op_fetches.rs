@@ -0,0 +1,114 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::protocol::address::RegisterAddress;
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Time to wait after a fetch of the missing ops of a Register before fetching them again.
+const MIN_FETCH_BACKOFF: Duration = Duration::from_secs(5);
+/// The longest time to wait between two fetches of the ops of a Register still missing some.
+const MAX_FETCH_BACKOFF: Duration = Duration::from_secs(300);
+/// Maximum number of Registers whose fetches are tracked.
+const MAX_TRACKED_REGISTERS: usize = 10_000;
+
+#[derive(Debug)]
+struct FetchState {
+    in_flight: bool,
+    // How long to wait after the next fetch, if it leaves ops missing.
+    backoff: Duration,
+    not_before: Instant,
+}
+
+/// Tracks the fetches of the missing ops of Registers, so that there is at most one
+/// in flight per Register, and a Register whose ops keep missing is fetched less and less often.
+#[derive(Debug, Default)]
+pub(super) struct OpFetches {
+    fetches: HashMap<RegisterAddress, FetchState>,
+}
+
+impl OpFetches {
+    /// Whether a fetch of the Register's ops can start now, marking it as in flight if so.
+    pub(super) fn try_start(&mut self, address: RegisterAddress, now: Instant) -> bool {
+        if let Some(state) = self.fetches.get_mut(&address) {
+            if state.in_flight || now < state.not_before {
+                return false;
+            }
+            state.in_flight = true;
+            return true;
+        }
+
+        if self.fetches.len() >= MAX_TRACKED_REGISTERS {
+            self.fetches
+                .retain(|_, state| state.in_flight || now < state.not_before);
+            if self.fetches.len() >= MAX_TRACKED_REGISTERS {
+                return false;
+            }
+        }
+
+        let _ = self.fetches.insert(
+            address,
+            FetchState {
+                in_flight: true,
+                backoff: MIN_FETCH_BACKOFF,
+                not_before: now,
+            },
+        );
+        true
+    }
+
+    /// Mark the fetch as done. If it left ops missing, the wait before the next one is doubled.
+    pub(super) fn finish(&mut self, address: &RegisterAddress, ops_missing: bool, now: Instant) {
+        if let Some(state) = self.fetches.get_mut(address) {
+            if !ops_missing {
+                state.backoff = MIN_FETCH_BACKOFF;
+            }
+            state.in_flight = false;
+            state.not_before = now + state.backoff;
+            if ops_missing {
+                state.backoff = (state.backoff * 2).min(MAX_FETCH_BACKOFF);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn op_fetches_one_in_flight_per_register_with_backoff() {
+        let mut fetches = OpFetches::default();
+        let address =
+            RegisterAddress::new(xor_name::XorName::random(&mut rand::thread_rng()), 43_000);
+        let other =
+            RegisterAddress::new(xor_name::XorName::random(&mut rand::thread_rng()), 43_000);
+        let start = Instant::now();
+
+        assert!(fetches.try_start(address, start));
+        assert!(!fetches.try_start(address, start));
+        assert!(fetches.try_start(other, start));
+
+        // Ops still missing, so it's fetched again only after the backoff, then twice as long.
+        fetches.finish(&address, true, start);
+        assert!(!fetches.try_start(address, start + MIN_FETCH_BACKOFF / 2));
+        let next = start + MIN_FETCH_BACKOFF;
+        assert!(fetches.try_start(address, next));
+        fetches.finish(&address, true, next);
+        assert!(!fetches.try_start(address, next + MIN_FETCH_BACKOFF));
+        let next = next + MIN_FETCH_BACKOFF * 2;
+        assert!(fetches.try_start(address, next));
+
+        // Once all ops were fetched, the backoff is reset.
+        fetches.finish(&address, false, next);
+        assert!(fetches.try_start(address, next + MIN_FETCH_BACKOFF));
+    }
+}
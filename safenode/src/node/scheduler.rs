@@ -0,0 +1,116 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    time::Duration,
+};
+
+/// Maximum number of requests being handled at once.
+pub(super) const MAX_CONCURRENT_REQUESTS: usize = 16;
+/// Maximum number of requests waiting to be handled, from all peers.
+pub(super) const MAX_QUEUED_REQUESTS: usize = 1024;
+/// Maximum number of requests waiting to be handled, from a single peer.
+pub(super) const MAX_QUEUED_REQUESTS_PER_PEER: usize = 64;
+/// How long peers are told to wait before retrying a request we're too busy to queue.
+pub(super) const BUSY_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Bounded queue taking items from each key in turn, so a key with many
+/// items queued doesn't delay the items of the other keys.
+#[derive(Debug)]
+pub(super) struct FairQueue<K, T> {
+    queues: HashMap<K, VecDeque<T>>,
+    // The keys with queued items, in the order they're next served.
+    turns: VecDeque<K>,
+    len: usize,
+    capacity: usize,
+    capacity_per_key: usize,
+}
+
+impl<K: Clone + Eq + Hash, T> FairQueue<K, T> {
+    pub(super) fn new(capacity: usize, capacity_per_key: usize) -> Self {
+        Self {
+            queues: HashMap::new(),
+            turns: VecDeque::new(),
+            len: 0,
+            capacity,
+            capacity_per_key,
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Queue the item, giving it back if either the whole queue or the key's one is full.
+    pub(super) fn push(&mut self, key: K, item: T) -> Result<(), T> {
+        if self.len >= self.capacity {
+            return Err(item);
+        }
+        let queue = self.queues.entry(key.clone()).or_default();
+        if queue.len() >= self.capacity_per_key {
+            return Err(item);
+        }
+        if queue.is_empty() {
+            self.turns.push_back(key);
+        }
+        queue.push_back(item);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Take the next item of the key whose turn it is.
+    pub(super) fn pop(&mut self) -> Option<(K, T)> {
+        let key = self.turns.pop_front()?;
+        let queue = self.queues.get_mut(&key)?;
+        let item = queue.pop_front()?;
+        self.len -= 1;
+        if queue.is_empty() {
+            let _ = self.queues.remove(&key);
+        } else {
+            self.turns.push_back(key.clone());
+        }
+        Some((key, item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fair_queue_serves_keys_in_turn() {
+        let mut queue = FairQueue::new(10, 10);
+        for i in 0..3 {
+            assert!(queue.push("chatty", i).is_ok());
+        }
+        assert!(queue.push("quiet", 10).is_ok());
+
+        let popped: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(
+            popped,
+            vec![("chatty", 0), ("quiet", 10), ("chatty", 1), ("chatty", 2)]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn fair_queue_is_bounded_overall_and_per_key() {
+        let mut queue = FairQueue::new(3, 2);
+        assert!(queue.push("a", 0).is_ok());
+        assert!(queue.push("a", 1).is_ok());
+        assert_eq!(queue.push("a", 2), Err(2));
+
+        assert!(queue.push("b", 3).is_ok());
+        assert_eq!(queue.push("c", 4), Err(4));
+
+        let _ = queue.pop();
+        assert!(queue.push("c", 4).is_ok());
+    }
+}
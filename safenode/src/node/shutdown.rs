@@ -0,0 +1,138 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{error::Result, event::NodeEventsChannel, Node, NodeEvent};
+
+use crate::{
+    network::PeerAgent,
+    protocol::messages::{Cmd, CmdResponse, ReplicatedData, Request, Response},
+};
+
+use futures::{stream, StreamExt};
+use libp2p::PeerId;
+use std::{collections::HashMap, sync::atomic::Ordering, time::Duration};
+
+// Number of items being handed over at once.
+const HAND_OVER_CONCURRENCY: usize = 16;
+// How long to wait for the connections with all peers to be closed.
+const CLOSE_CONNECTIONS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A node started with `Node::run`, whose events can be listened to, and which can be shut down.
+pub struct RunningNode {
+    pub(super) node: Node,
+}
+
+impl RunningNode {
+    /// Returns the channel where the node's events are broadcasted.
+    pub fn node_events_channel(&self) -> &NodeEventsChannel {
+        &self.node.events_channel
+    }
+
+    /// Returns the agent and protocol versions of the connected peers which identified themselves.
+    pub async fn peers_agent(&self) -> Result<HashMap<PeerId, PeerAgent>> {
+        Ok(self.node.network.peers_agent().await?)
+    }
+
+    /// Gracefully shuts the node down: stops accepting writes, hands the data it holds over
+    /// to the peers taking over its share of the data, flushes its stores, then closes the
+    /// connections with all peers. A `NodeEvent` is broadcasted as each phase completes.
+    pub async fn shutdown(self) -> Result<()> {
+        self.node.shutdown().await
+    }
+}
+
+impl Node {
+    pub(super) fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        info!("Shutting down the node");
+        self.shutting_down.store(true, Ordering::SeqCst);
+        self.events_channel.broadcast(NodeEvent::ShuttingDown);
+
+        // Record the queued spends, so they're handed over along with the others.
+        let queued_spends = self.transfers.write().await.pop_all_queued_spends();
+        for (queued, _) in queued_spends {
+            let result = self.record_queued_spend(&queued).await;
+            self.handle_record_result(result).await;
+        }
+
+        let (handed_over, failed) = self.hand_over_data().await;
+        info!("Handed {handed_over} items over, failed to hand {failed} over");
+        self.events_channel.broadcast(NodeEvent::DataHandedOver {
+            handed_over,
+            failed,
+        });
+
+        // All the stores are kept in memory for now, so there's nothing to write to disk
+        // yet. The Registers ops logs will be flushed here once they're persisted.
+        self.events_channel.broadcast(NodeEvent::StoresFlushed);
+
+        match tokio::time::timeout(CLOSE_CONNECTIONS_TIMEOUT, self.network.shutdown()).await {
+            Ok(result) => result?,
+            Err(_) => warn!("Timed out waiting for the connections with all peers to be closed"),
+        }
+        self.events_channel.broadcast(NodeEvent::ConnectionsClosed);
+
+        Ok(())
+    }
+
+    // Push all the data we hold to the closest peers to it, other than us, so the peer
+    // next in line takes our place in its close group. Returns the number of items
+    // handed over, and the number of those no peer accepted.
+    async fn hand_over_data(&self) -> (usize, usize) {
+        let mut data = Vec::new();
+        for address in self.chunks.addrs().await {
+            match self.chunks.get(&address).await {
+                Ok(chunk) => data.push(ReplicatedData::Chunk(chunk)),
+                Err(err) => warn!("Failed to load chunk {address:?} to hand it over: {err}"),
+            }
+        }
+        data.extend(
+            self.registers
+                .replicated_logs()
+                .await
+                .into_iter()
+                .map(ReplicatedData::RegisterLog),
+        );
+        data.extend(self.transfers.read().await.replicated_data().await);
+
+        let total = data.len();
+        let handed_over = stream::iter(data)
+            .map(|data| self.hand_over(data))
+            .buffer_unordered(HAND_OVER_CONCURRENCY)
+            .filter(|accepted| futures::future::ready(*accepted))
+            .count()
+            .await;
+
+        (handed_over, total - handed_over)
+    }
+
+    // Send the data to its closest peers, returning whether at least one of them accepted it.
+    async fn hand_over(&self, data: ReplicatedData) -> bool {
+        let name = data.name();
+        let peers = match self.network.client_get_closest_peers(name).await {
+            Ok(peers) => peers,
+            Err(err) => {
+                warn!("Failed to get the closest peers to hand {name:?} over: {err}");
+                return false;
+            }
+        };
+
+        let request = Request::Cmd(Cmd::Replicate(data));
+        let responses = self.send_and_get_responses(peers, &request, true).await;
+        let accepted = responses
+            .iter()
+            .any(|resp| matches!(resp, Ok(Response::Cmd(CmdResponse::Replicate(Ok(()))))));
+        if !accepted {
+            warn!("No peer accepted {name:?}: {responses:?}");
+        }
+        accepted
+    }
+}
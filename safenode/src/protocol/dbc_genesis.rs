@@ -7,9 +7,11 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use sn_dbc::{
-    rng, Dbc, DbcTransaction, Error as DbcError, Hash, InputHistory, MainKey, RevealedAmount,
-    RevealedInput, Token, TransactionBuilder,
+    rng, Dbc, DbcTransaction, DerivationIndex, Error as DbcError, Hash, InputHistory, MainKey,
+    RevealedAmount, RevealedInput, Token, TransactionBuilder,
 };
+#[cfg(feature = "local-testnet")]
+use sn_dbc::{DbcId, SignedSpend};
 
 use std::fmt::Debug;
 use thiserror::Error;
@@ -23,6 +25,17 @@ const TOTAL_SUPPLY: u64 = u32::MAX as u64 * u64::pow(10, 9);
 /// thus creating a total of 1,288,490,189,000,000,000 available units.
 pub const GENESIS_DBC_AMOUNT: u64 = (0.3 * TOTAL_SUPPLY as f64) as u64;
 
+/// The (BLS hex-encoded) key holding the genesis DBC on local testnets.
+/// It is not kept secret, as the tokens of a local testnet have no value,
+/// which is why it's only compiled in with the `local-testnet` feature.
+#[cfg(feature = "local-testnet")]
+const TESTNET_GENESIS_KEY: &str =
+    "1dd36a484397ee39f37a497c2ed49e314dc929025b91a20b1a6e54e54bffe6de";
+
+/// The derivation index of the genesis input, i.e. the input of the Genesis Tx.
+/// As it's fixed, its spend can only be recorded once, and so there can only be one Genesis DBC.
+const GENESIS_INPUT_DERIVATION_INDEX: DerivationIndex = [0; 32];
+
 /// A specialised `Result` type for dbc_genesis crate.
 pub type GenesisResult<T> = Result<T, GenesisError>;
 
@@ -37,27 +50,72 @@ pub enum GenesisError {
     /// FailedToParseReason
     #[error("Failed to parse reason: {0}")]
     FailedToParseReason(#[from] DbcError),
+    /// The genesis key could not be parsed.
+    #[error("Invalid genesis key: {0}")]
+    InvalidGenesisKey(String),
+}
+
+/// Returns the key holding the Genesis DBC on local testnets.
+#[cfg(feature = "local-testnet")]
+#[allow(clippy::result_large_err)]
+pub fn testnet_genesis_key() -> GenesisResult<MainKey> {
+    let bytes = hex::decode(TESTNET_GENESIS_KEY)
+        .map_err(|err| GenesisError::InvalidGenesisKey(err.to_string()))?;
+    let bytes_fixed_len: [u8; bls::SK_SIZE] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| GenesisError::InvalidGenesisKey("wrong length".to_string()))?;
+    let secret_key = bls::SecretKey::from_bytes(bytes_fixed_len)
+        .map_err(|err| GenesisError::InvalidGenesisKey(err.to_string()))?;
+    Ok(MainKey::new(secret_key))
+}
+
+/// Returns true if this is the spend of the genesis input of the local testnet,
+/// which has no parents to validate it with, as it's where all tokens come from.
+#[cfg(feature = "local-testnet")]
+pub fn is_genesis_spend(signed_spend: &SignedSpend) -> bool {
+    let genesis_input_id = match testnet_genesis_key() {
+        Ok(key) => genesis_input_id(&key),
+        Err(_) => return false,
+    };
+    signed_spend.dbc_id() == &genesis_input_id
+        && signed_spend.src_tx_hash() == genesis_src_tx().hash()
+}
+
+// The id of the input of the Genesis Tx, derived from the genesis key at a fixed index.
+#[cfg(feature = "local-testnet")]
+fn genesis_input_id(genesis_main_key: &MainKey) -> DbcId {
+    genesis_main_key
+        .derive_key(&GENESIS_INPUT_DERIVATION_INDEX)
+        .dbc_id()
+}
+
+/// The src tx of the genesis input is empty, as there was nothing before it.
+pub(crate) fn genesis_src_tx() -> DbcTransaction {
+    DbcTransaction {
+        inputs: vec![],
+        outputs: vec![],
+    }
 }
 
 /// Generate the genesis DBC.
 /// The genesis DBC is the first DBC in the network. It is created without
 /// a source transaction, as there was nothing before it.
+/// The spend of its input, found in the DBC, must be recorded by the network for it to be spent.
 #[allow(clippy::result_large_err)]
 pub fn create_genesis_dbc(genesis_main_key: &MainKey) -> GenesisResult<Dbc> {
     let rng = &mut rng::thread_rng();
 
     let dbc_id_src = genesis_main_key.random_dbc_id_src(rng);
-    let derived_key = genesis_main_key.derive_key(&dbc_id_src.derivation_index);
+    let derived_key = genesis_main_key.derive_key(&GENESIS_INPUT_DERIVATION_INDEX);
     let revealed_amount = RevealedAmount::from_amount(GENESIS_DBC_AMOUNT, rng);
 
-    // Use the same key as the input and output of Genesis Tx.
+    // The input of the Genesis Tx is derived from the same key as its output, but at a fixed index,
+    // so that the spend of the input does not spend the Genesis DBC itself.
     // The src tx is empty as this is the first DBC.
     let genesis_input = InputHistory {
         input: RevealedInput::new(derived_key, revealed_amount),
-        input_src_tx: DbcTransaction {
-            inputs: vec![],
-            outputs: vec![],
-        },
+        input_src_tx: genesis_src_tx(),
     };
 
     let dbc_builder = TransactionBuilder::default()
@@ -86,3 +144,23 @@ pub fn create_genesis_dbc(genesis_main_key: &MainKey) -> GenesisResult<Dbc> {
 
     Ok(genesis_dbc)
 }
+
+#[cfg(all(test, feature = "local-testnet"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_testnet_genesis_spend_is_recognised() -> GenesisResult<()> {
+        let genesis_dbc = create_genesis_dbc(&testnet_genesis_key()?)?;
+        assert!(genesis_dbc.signed_spends.iter().all(is_genesis_spend));
+        // The spend of the genesis input does not spend the genesis dbc itself.
+        assert!(genesis_dbc
+            .signed_spends
+            .iter()
+            .all(|spend| spend.dbc_id() != &genesis_dbc.id()));
+
+        let other_dbc = create_genesis_dbc(&MainKey::random())?;
+        assert!(!other_dbc.signed_spends.iter().any(is_genesis_spend));
+        Ok(())
+    }
+}
@@ -7,7 +7,7 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    address::{ChunkAddress, RegisterAddress},
+    address::{ChunkAddress, DataAddress, RegisterAddress},
     authority::PublicKey,
     register::{EntryHash, User},
 };
@@ -54,6 +54,9 @@ pub enum Error {
         /// Stored Register address
         reg_addr: RegisterAddress,
     },
+    /// The state of a Register snapshot doesn't match the cmd which created the Register.
+    #[error("Register snapshot doesn't match the cmd which created the Register: {0:?}")]
+    RegisterSnapshotMismatch(RegisterAddress),
     /// Access denied for user
     #[error("Access denied for user: {0:?}")]
     AccessDenied(User),
@@ -95,4 +98,10 @@ pub enum Error {
     /// Failed to write file, likely due to a system Io error
     #[error("Failed to write file")]
     FailedToWriteFile,
+    /// The node is shutting down, handing its data over to other nodes.
+    #[error("The node is shutting down and no longer accepts writes")]
+    NodeShuttingDown,
+    /// Replicated data is only accepted from the peers of its close group.
+    #[error("Replicated data for {0:?} was not sent by a peer of its close group")]
+    ReplicationNotFromCloseGroup(DataAddress),
 }
@@ -51,7 +51,7 @@ pub use self::{
     priority::SpendPriority,
     required_fee::RequiredFee,
     required_fee_content::RequiredFeeContent,
-    spend_queue::{SpendQ, SpendQSnapshot, SpendQStats},
+    spend_queue::{SpendQ, SpendQSnapshot, SpendQStats, SpendQueuePosition, SPEND_POP_INTERVAL},
 };
 
 #[cfg(test)]
@@ -26,9 +26,13 @@
 use super::SpendPriority;
 
 use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize};
 use std::{hash::Hash, time::Duration};
 use tokio::time::Instant;
 
+/// Minimum time between two spends being popped from the queue, see `SpendQ::elapsed`.
+pub const SPEND_POP_INTERVAL: Duration = Duration::from_secs(1);
+
 /// The queue of pending spends, sorted by
 /// the fee paid.
 ///
@@ -88,6 +92,16 @@ pub struct SpendQStats {
     pub std_dev: u64,
 }
 
+/// Where a spend sits in the queue of spends waiting to be recorded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SpendQueuePosition {
+    /// The number of spends to be popped before this one, as of when it was queued.
+    pub position: usize,
+    /// The estimated time till the spend is popped. Spends with a higher fee
+    /// queued in the meantime can delay it.
+    pub eta: Duration,
+}
+
 impl SpendQStats {
     /// Validate that the fee is high enough to push it to the spend queue,
     /// otherwise it is dropped and an error returned to Client.
@@ -200,7 +214,30 @@ impl<T: Eq + Hash> SpendQ<T> {
     /// -> 38400 tps with 307200 nodes
     /// -> etc..
     pub fn elapsed(&self) -> bool {
-        Instant::now() - self.last_pop > Duration::from_secs(1)
+        Instant::now() - self.last_pop >= SPEND_POP_INTERVAL
+    }
+
+    /// Time left till the next item can be popped.
+    pub fn time_to_next_pop(&self) -> Duration {
+        SPEND_POP_INTERVAL.saturating_sub(Instant::now() - self.last_pop)
+    }
+
+    /// The position of the item in the queue, and the estimated time till it's popped,
+    /// or `None` if it's not in the queue.
+    pub fn position(&self, item: &T) -> Option<SpendQueuePosition> {
+        let (_, priority) = self.queue.get(item)?;
+        let position = self
+            .queue
+            .iter()
+            .filter(|(_, other)| *other > priority)
+            .count();
+        let eta = self.time_to_next_pop() + SPEND_POP_INTERVAL * position as u32;
+        Some(SpendQueuePosition { position, eta })
+    }
+
+    /// The items in the queue, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.queue.iter().map(|(item, _)| item)
     }
 
     /// Return a snapshot of the fees in the queue, with preserved order.
@@ -598,4 +635,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn spendq_position_is_by_fee_and_eta_by_pop_interval() {
+        let mut spendq = SpendQ::<usize>::with_fee(0);
+        spendq.push(1, 10);
+        spendq.push(2, 30);
+        spendq.push(3, 20);
+
+        // Two spends with a higher fee are ahead, and nothing was popped in the last interval.
+        let position = spendq.position(&1).map(|position| position.position);
+        assert_eq!(position, Some(2));
+        let eta = spendq.position(&1).map(|position| position.eta);
+        assert!(
+            matches!(eta, Some(eta) if eta >= 2 * SPEND_POP_INTERVAL && eta <= 3 * SPEND_POP_INTERVAL)
+        );
+        assert_eq!(spendq.position(&4), None);
+
+        let _ = spendq.pop();
+        assert!(!spendq.elapsed());
+        assert_eq!(
+            spendq.position(&1).map(|position| position.position),
+            Some(1)
+        );
+    }
 }
@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::RegisterCmd;
+use super::{RegisterCmd, ReplicatedData};
 
 use crate::{
     node::NodeId,
@@ -55,6 +55,8 @@ pub enum Cmd {
         #[debug(skip)]
         fee_ciphers: BTreeMap<NodeId, FeeCiphers>,
     },
+    /// Data handed over by a node leaving the network, to the peers taking over its share of the data.
+    Replicate(ReplicatedData),
 }
 
 impl Cmd {
@@ -66,6 +68,7 @@ impl Cmd {
             Cmd::SpendDbc { signed_spend, .. } => {
                 DataAddress::Spend(dbc_address(signed_spend.dbc_id()))
             }
+            Cmd::Replicate(data) => data.dst(),
         }
     }
 }
@@ -19,8 +19,8 @@ pub use self::{
     event::Event,
     query::Query,
     register::{
-        CreateRegister, EditRegister, RegisterCmd, RegisterQuery, ReplicatedRegisterLog,
-        SignedRegisterCreate, SignedRegisterEdit,
+        CreateRegister, EditRegister, RegisterCmd, RegisterQuery, RegisterSnapshot,
+        ReplicatedRegisterLog, SignedRegisterCreate, SignedRegisterEdit,
     },
     response::{CmdResponse, QueryResponse},
     spend::SpendQuery,
@@ -31,10 +31,10 @@ use super::{
     chunk::Chunk,
 };
 
-use sn_dbc::SignedSpend;
+use sn_dbc::{DbcTransaction, SignedSpend};
 
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeSet, fmt::Debug};
+use std::{collections::BTreeSet, fmt::Debug, time::Duration};
 use xor_name::XorName;
 
 /// Send a request to other peers in the network
@@ -55,6 +55,24 @@ pub enum Response {
     Cmd(CmdResponse),
     /// The response to a query.
     Query(QueryResponse),
+    /// The peer could not decode our request, e.g. as it runs an incompatible version.
+    UnsupportedMsg(UnsupportedMsg),
+    /// The peer is too busy to handle our request, which can be retried after the given time.
+    Busy {
+        /// How long to wait before retrying the request.
+        retry_after: Duration,
+    },
+}
+
+/// Details of a request a peer could not decode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsupportedMsg {
+    /// The protocol the request was received over, e.g. `/safe/2`.
+    pub protocol: String,
+    /// The protocols supported by the peer, newest first.
+    pub supported: Vec<String>,
+    /// Why the request could not be decoded.
+    pub reason: String,
 }
 
 /// Messages to replicated data among nodes on the network
@@ -67,8 +85,8 @@ pub enum ReplicatedData {
     RegisterWrite(RegisterCmd),
     /// An entire op log of a register.
     RegisterLog(ReplicatedRegisterLog),
-    /// A valid spend.
-    ValidSpend(SignedSpend),
+    /// A valid spend, along with the tx its dbc was created in, to validate its parents with.
+    ValidSpend((SignedSpend, DbcTransaction)),
     /// A dbc marked as having attempted double spend.
     DoubleSpend((DbcAddress, BTreeSet<SignedSpend>)),
 }
@@ -91,7 +109,7 @@ impl ReplicatedData {
             Self::Chunk(chunk) => *chunk.name(),
             Self::RegisterLog(log) => *log.address.name(),
             Self::RegisterWrite(cmd) => *cmd.dst().name(),
-            Self::ValidSpend(spend) => dbc_name(spend.dbc_id()),
+            Self::ValidSpend((spend, _)) => dbc_name(spend.dbc_id()),
             Self::DoubleSpend((address, _)) => *address.name(),
         }
     }
@@ -102,7 +120,7 @@ impl ReplicatedData {
             Self::Chunk(chunk) => DataAddress::Chunk(*chunk.address()),
             Self::RegisterLog(log) => DataAddress::Register(log.address),
             Self::RegisterWrite(cmd) => DataAddress::Register(cmd.dst()),
-            Self::ValidSpend(spend) => DataAddress::Spend(dbc_address(spend.dbc_id())),
+            Self::ValidSpend((spend, _)) => DataAddress::Spend(dbc_address(spend.dbc_id())),
             Self::DoubleSpend((address, _)) => DataAddress::Spend(*address),
         }
     }
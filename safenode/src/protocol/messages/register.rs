@@ -6,13 +6,13 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+#[allow(unused_imports)] // needed by rustdocs links
+use crate::protocol::messages::QueryResponse;
 use crate::protocol::{
     address::RegisterAddress,
     authority::DataAuthority,
-    register::{Entry, EntryHash, Policy, RegisterOp, User},
+    register::{Entry, EntryHash, Policy, Register, RegisterOp, User},
 };
-#[allow(unused_imports)] // needed by rustdocs links
-use crate::protocol::{messages::QueryResponse, register::Register};
 
 use serde::{Deserialize, Serialize};
 use xor_name::XorName;
@@ -22,10 +22,24 @@ use xor_name::XorName;
 pub struct ReplicatedRegisterLog {
     /// Register address
     pub address: RegisterAddress,
-    /// Register ops log
+    /// Snapshot of the Register the ops log has been compacted into, if any.
+    pub snapshot: Option<RegisterSnapshot>,
+    /// Register ops log, made after the snapshot if there is one.
     pub op_log: Vec<RegisterCmd>,
 }
 
+/// Snapshot of a [`Register`], which the ops applied to it have been compacted into.
+/// It holds the state of the Register, i.e. its CRDT, instead of the edit cmds merged into it,
+/// along with the signed cmd which created it, so that any replica receiving it can verify
+/// the Register was created by its owner, and with the policy the state holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterSnapshot {
+    /// The signed cmd which created the [`Register`].
+    pub create: SignedRegisterCreate,
+    /// The state of the [`Register`] once the compacted cmds were applied to it.
+    pub register: Register,
+}
+
 /// [`Register`] read operations.
 #[derive(Hash, Eq, PartialEq, PartialOrd, Clone, Serialize, Deserialize, Debug)]
 pub enum RegisterQuery {
@@ -76,6 +90,13 @@ pub enum RegisterQuery {
     ///
     /// [`GetRegisterOwner`]: QueryResponse::GetRegisterOwner
     GetOwner(RegisterAddress),
+    /// Retrieve the log of signed cmds of the [`Register`] at the given address,
+    /// used by nodes to fetch ops they are missing from their peers.
+    ///
+    /// This should eventually lead to a [`GetRegisterOpLog`] response.
+    ///
+    /// [`GetRegisterOpLog`]: QueryResponse::GetRegisterOpLog
+    GetOpLog(RegisterAddress),
 }
 
 /// A [`Register`] cmd that is stored in a log on Adults.
@@ -168,7 +189,8 @@ impl RegisterQuery {
             | Self::GetPolicy(ref address)
             | Self::GetUserPermissions { ref address, .. }
             | Self::GetEntry { ref address, .. }
-            | Self::GetOwner(ref address) => *address,
+            | Self::GetOwner(ref address)
+            | Self::GetOpLog(ref address) => *address,
         }
     }
 }
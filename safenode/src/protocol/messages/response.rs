@@ -11,7 +11,8 @@ use crate::{
     protocol::{
         chunk::Chunk,
         error::Result,
-        fees::RequiredFee,
+        fees::{RequiredFee, SpendQueuePosition},
+        messages::ReplicatedRegisterLog,
         register::{Entry, EntryHash, Permissions, Policy, Register, User},
     },
 };
@@ -41,10 +42,17 @@ pub enum QueryResponse {
     /// nodes as to consider it a valid spend. The specific rules
     /// on how many nodes are enough, are found here: (TODO).
     ///
-    /// Response to [`GetDbcSpend`]
+    /// Response to [`GetDbcSpend`] and [`GetPendingDbcSpend`]
     ///
     /// [`GetDbcSpend`]: crate::protocol::messages::SpendQuery::GetDbcSpend
+    /// [`GetPendingDbcSpend`]: crate::protocol::messages::SpendQuery::GetPendingDbcSpend
     GetDbcSpend(Result<SignedSpend>),
+    /// The two conflicting spends of a double spent Dbc, as recorded by the queried node.
+    ///
+    /// Response to [`GetDoubleSpendProof`]
+    ///
+    /// [`GetDoubleSpendProof`]: crate::protocol::messages::SpendQuery::GetDoubleSpendProof
+    GetDoubleSpendProof(Result<(SignedSpend, SignedSpend)>),
     //
     // ===== Chunk =====
     //
@@ -67,6 +75,8 @@ pub enum QueryResponse {
     GetRegisterPolicy(Result<Policy>),
     /// Response to [`RegisterQuery::GetUserPermissions`].
     GetRegisterUserPermissions(Result<Permissions>),
+    /// Response to [`RegisterQuery::GetOpLog`].
+    GetRegisterOpLog(Result<ReplicatedRegisterLog>),
 }
 
 /// The response to a Cmd, containing the query result.
@@ -75,8 +85,9 @@ pub enum CmdResponse {
     //
     // ===== Dbc Spends =====
     //
-    /// Response to DbcCmd::Spend.
-    Spend(Result<()>),
+    /// Response to DbcCmd::Spend, with the position of the spend
+    /// in the queue of spends waiting to be recorded.
+    Spend(Result<SpendQueuePosition>),
     //
     // ===== Chunk =====
     //
@@ -89,4 +100,9 @@ pub enum CmdResponse {
     CreateRegister(Result<()>),
     /// Response to RegisterCmd::Edit.
     EditRegister(Result<()>),
+    //
+    // ===== Replication =====
+    //
+    /// Response to Cmd::Replicate.
+    Replicate(Result<()>),
 }
@@ -27,6 +27,12 @@ pub enum SpendQuery {
     },
     /// Query for a `Spend` of a Dbc with at the given address.
     GetDbcSpend(DbcAddress),
+    /// Query for a `Spend` of a Dbc at the given address, whether it's recorded or still queued,
+    /// as to verify the tx it's spent in along with the spends of the other inputs of that tx.
+    GetPendingDbcSpend(DbcAddress),
+    /// Query for the conflicting `Spend`s recorded for a Dbc at the given address,
+    /// proving that the Dbc was double spent, and so is unspendable.
+    GetDoubleSpendProof(DbcAddress),
 }
 
 impl SpendQuery {
@@ -34,7 +40,9 @@ impl SpendQuery {
     pub fn dst(&self) -> DbcAddress {
         match self {
             Self::GetFees { dbc_id, .. } => dbc_address(dbc_id),
-            Self::GetDbcSpend(ref address) => *address,
+            Self::GetDbcSpend(ref address)
+            | Self::GetPendingDbcSpend(ref address)
+            | Self::GetDoubleSpendProof(ref address) => *address,
         }
     }
 }
@@ -32,10 +32,10 @@ use std::{
 use xor_name::XorName;
 
 /// Arbitrary maximum size of a register entry.
-const MAX_REG_ENTRY_SIZE: usize = MIN_ENCRYPTABLE_BYTES / 3; // 1024 bytes
+pub const MAX_REG_ENTRY_SIZE: usize = MIN_ENCRYPTABLE_BYTES / 3; // 1024 bytes
 
 /// Maximum number of entries of a register.
-const MAX_REG_NUM_ENTRIES: u16 = 1024;
+pub const MAX_REG_NUM_ENTRIES: u16 = 1024;
 
 /// Register mutation operation to apply to Register.
 pub type RegisterOp<T> = CrdtOperation<T>;
@@ -112,6 +112,18 @@ impl Register {
         self.crdt.read()
     }
 
+    /// Return the hashes of the entries the provided op was written atop of,
+    /// which are not held by this replica yet.
+    pub fn missing_deps(&self, op: &RegisterOp<Entry>) -> BTreeSet<EntryHash> {
+        self.crdt.missing_children(op)
+    }
+
+    /// Return the hashes of the entries which the entry corresponding
+    /// to the provided 'hash' was written atop of, if present.
+    pub fn children(&self, hash: EntryHash) -> Result<BTreeSet<EntryHash>> {
+        self.crdt.children(hash).ok_or(Error::NoSuchEntry(hash))
+    }
+
     /// Return user permissions, if applicable.
     pub fn permissions(&self, user: User) -> Result<Permissions> {
         self.policy.permissions(user).ok_or(Error::NoSuchUser(user))
@@ -151,6 +151,24 @@ impl RegisterCrdt {
         self.data.node(hash.0).map(|node| &node.value)
     }
 
+    /// Get the hashes of the entries the entry corresponding to the provided `hash` was written atop of.
+    pub(crate) fn children(&self, hash: EntryHash) -> Option<BTreeSet<EntryHash>> {
+        self.data
+            .node(hash.0)
+            .map(|node| node.children.iter().copied().map(EntryHash).collect())
+    }
+
+    /// Get the hashes of the entries the op was written atop of which are not in the register yet.
+    pub(crate) fn missing_children(&self, op: &CrdtOperation<Entry>) -> BTreeSet<EntryHash> {
+        op.crdt_op
+            .children
+            .iter()
+            .filter(|hash| self.data.node(**hash).is_none())
+            .copied()
+            .map(EntryHash)
+            .collect()
+    }
+
     /// Read current entries (multiple entries occur on concurrent writes).
     pub(crate) fn read(&self) -> BTreeSet<(EntryHash, Entry)> {
         self.data
@@ -39,7 +39,14 @@ pub(crate) use self::{
     online::create_transfer as create_online_transfer,
 };
 
-use sn_dbc::{Dbc, DbcIdSource, DerivedKey, PublicAddress, RevealedAmount, Token};
+use crate::{
+    node::NodeId,
+    protocol::fees::{FeeCiphers, SpendQueuePosition},
+};
+
+use sn_dbc::{Dbc, DbcId, DbcIdSource, DerivedKey, PublicAddress, RevealedAmount, Token};
+
+use std::collections::BTreeMap;
 
 /// The input details necessary to
 /// carry out a transfer of tokens.
@@ -52,6 +59,8 @@ pub struct Inputs {
     pub recipients: Vec<(Token, DbcIdSource)>,
     /// Any surplus amount after spending the necessary input dbcs.
     pub change: (Token, PublicAddress),
+    /// The fees paid to each node for spending the input dbcs.
+    pub fees: BTreeMap<NodeId, Token>,
 }
 
 /// The created dbcs and change dbc from a transfer
@@ -64,6 +73,15 @@ pub struct Outputs {
     /// The dbc holding surplus tokens after
     /// spending the necessary input dbcs.
     pub change_dbc: Option<Dbc>,
+    /// The fees paid to each node for spending the input dbcs,
+    /// which are included in the created dbcs.
+    pub fees: BTreeMap<NodeId, Token>,
+    /// The fee ciphers of the nodes to pay for spending each input dbc,
+    /// to be sent along with the spend of that dbc.
+    pub fee_ciphers: BTreeMap<DbcId, BTreeMap<NodeId, FeeCiphers>>,
+    /// Where the spend of each input dbc sits in the spend queue of its close group,
+    /// once sent to the network. Empty as long as the spends haven't been sent.
+    pub spend_queue_positions: BTreeMap<DbcId, SpendQueuePosition>,
 }
 
 /// A resulting dbc from a token transfer.
@@ -13,6 +13,8 @@ use sn_dbc::{
     TransactionBuilder,
 };
 
+use std::collections::BTreeMap;
+
 /// A function for creating an offline transfer of tokens.
 /// This is done by creating new dbcs to the recipients (and a change dbc if any)
 /// by selecting from the available input dbcs, and creating the necessary
@@ -106,6 +108,8 @@ fn select_inputs(
         dbcs_to_spend,
         recipients,
         change: (change_amount, change_to),
+        // No fees are paid offline.
+        fees: BTreeMap::new(),
     })
 }
 
@@ -130,6 +134,7 @@ fn transfer(send_inputs: Inputs) -> Result<Outputs> {
         dbcs_to_spend,
         recipients,
         change: (change, change_to),
+        fees,
     } = send_inputs;
 
     let mut inputs = vec![];
@@ -187,5 +192,8 @@ fn transfer(send_inputs: Inputs) -> Result<Outputs> {
     Ok(Outputs {
         created_dbcs,
         change_dbc,
+        fees,
+        fee_ciphers: BTreeMap::new(),
+        spend_queue_positions: BTreeMap::new(),
     })
 }
@@ -13,7 +13,7 @@ use crate::{
     network::close_group_majority,
     node::NodeId,
     protocol::{
-        fees::{RequiredFee, SpendPriority},
+        fees::{FeeCiphers, RequiredFee, SpendPriority},
         messages::{Query, QueryResponse, Request, Response, SpendQuery},
     },
 };
@@ -47,10 +47,47 @@ pub(crate) async fn create_transfer(
 ) -> Result<Outputs> {
     // We need to select the necessary number of dbcs from those that we were passed.
     // This will also account for any fees.
-    let selected_inputs = select_inputs(available_dbcs, recipients, change_to, client).await?;
-    create_transfer_with(selected_inputs)
+    let (selected_inputs, fee_cipher_params) =
+        select_inputs(available_dbcs, recipients, change_to, client).await?;
+    let mut outputs = create_transfer_with(selected_inputs)?;
+    outputs.fee_ciphers = create_fee_ciphers(&outputs.created_dbcs, fee_cipher_params)?;
+    Ok(outputs)
 }
 
+/// The fee paid to a node, for spending an input dbc, is encrypted to the node's reward address,
+/// so that the node can find its fee among the outputs of the tx when the spend is sent to it.
+#[allow(clippy::result_large_err)]
+fn create_fee_ciphers(
+    created_dbcs: &[CreatedDbc],
+    fee_cipher_params: FeeCipherParams,
+) -> Result<BTreeMap<DbcId, BTreeMap<NodeId, FeeCiphers>>> {
+    let mut all_fee_ciphers = BTreeMap::new();
+    for (dbc_id, node_params) in fee_cipher_params {
+        let mut fee_ciphers = BTreeMap::new();
+        for (node_id, (required_fee, dbc_id_src)) in node_params {
+            let fee_dbc_id = dbc_id_src.dbc_id();
+            let fee_dbc = created_dbcs
+                .iter()
+                .find(|created| created.dbc.id() == fee_dbc_id)
+                .ok_or_else(|| {
+                    Error::DbcReissueFailed(format!("No fee dbc was created for node {node_id}"))
+                })?;
+            let amount = fee_dbc_id.encrypt(&fee_dbc.amount);
+            let derivation_index = required_fee
+                .content
+                .reward_address
+                .encrypt(&dbc_id_src.derivation_index);
+            let _ = fee_ciphers.insert(node_id, FeeCiphers::new(amount, derivation_index));
+        }
+        let _ = all_fee_ciphers.insert(dbc_id, fee_ciphers);
+    }
+    Ok(all_fee_ciphers)
+}
+
+/// The required fee of each node to pay for spending an input dbc,
+/// and the dbc id source of the fee output created for the node.
+type FeeCipherParams = BTreeMap<DbcId, BTreeMap<NodeId, (RequiredFee, DbcIdSource)>>;
+
 /// Select the necessary number of dbcs from those that we were passed.
 #[allow(clippy::result_large_err)]
 async fn select_inputs(
@@ -58,7 +95,7 @@ async fn select_inputs(
     mut recipients: Vec<(Token, DbcIdSource)>,
     change_to: PublicAddress,
     client: &Client,
-) -> Result<Inputs> {
+) -> Result<(Inputs, FeeCipherParams)> {
     // We'll combine one or more input dbcs and reissue:
     // - one output dbc per recipient,
     // - one output dbc for each node that will be paid a fee,
@@ -78,8 +115,9 @@ async fn select_inputs(
         })?;
 
     let mut change_amount = total_output_amount;
-    let mut all_fee_cipher_params = BTreeMap::new();
+    let mut all_fee_cipher_params: FeeCipherParams = BTreeMap::new();
     let mut fees_paid = Token::zero();
+    let mut fees_per_node: BTreeMap<NodeId, Token> = BTreeMap::new();
 
     for (dbc, derived_key) in available_dbcs {
         let dbc_id = dbc.id();
@@ -157,6 +195,16 @@ async fn select_inputs(
                     let _ = fee_cipher_params.insert(*node_id, (required_fee.clone(), dbc_id_src));
                 });
 
+            // Sum up the fees per node, a node may be paid for several inputs.
+            for ((node_id, _), fee) in &decrypted_node_fees {
+                let paid = fees_per_node.entry(*node_id).or_insert_with(Token::zero);
+                *paid = paid.checked_add(*fee).ok_or_else(|| {
+                    Error::DbcReissueFailed(
+                        "Overflow occurred while summing the fees paid to a node.".to_string(),
+                    )
+                })?;
+            }
+
             let _ = all_fee_cipher_params.insert(dbc_id, fee_cipher_params);
 
             fees_paid = fees_paid.checked_add(fee_per_input).ok_or_else(|| {
@@ -220,11 +268,13 @@ async fn select_inputs(
     // If not enough spendable was found, this check will return an error.
     verify_amounts(total_input_amount, total_output_amount)?;
 
-    Ok(Inputs {
+    let inputs = Inputs {
         dbcs_to_spend,
         recipients,
         change: (change_amount, change_to),
-    })
+        fees: fees_per_node,
+    };
+    Ok((inputs, all_fee_cipher_params))
 }
 
 // Make sure total input amount gathered with input dbcs are enough for the output amount
@@ -248,6 +298,7 @@ fn create_transfer_with(selected_inputs: Inputs) -> Result<Outputs> {
         dbcs_to_spend,
         recipients,
         change: (change, change_to),
+        fees,
     } = selected_inputs;
 
     let mut inputs = vec![];
@@ -305,6 +356,10 @@ fn create_transfer_with(selected_inputs: Inputs) -> Result<Outputs> {
     Ok(Outputs {
         created_dbcs,
         change_dbc,
+        fees,
+        // Set once the fee outputs are known, by the caller.
+        fee_ciphers: BTreeMap::new(),
+        spend_queue_positions: BTreeMap::new(),
     })
 }
 
@@ -0,0 +1,122 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Hex encoding of addresses, dbc ids and dbcs, so that they can be handed between users
+//! out of band, e.g. a receiver gives a `DbcIdSource` to a sender, who gives back the dbc.
+
+use super::error::{Error, Result};
+
+use sn_dbc::{Dbc, DbcIdSource, PublicAddress};
+
+use hex::{decode, encode};
+use std::path::Path;
+use tokio::{fs, io::AsyncWriteExt};
+
+/// Returns the hex encoding of the address.
+pub fn address_to_hex(address: &PublicAddress) -> String {
+    encode(address.to_bytes())
+}
+
+/// Returns the address encoded by `address_to_hex`.
+pub fn address_from_hex(hex: &str) -> Result<PublicAddress> {
+    let bytes = decode(hex.trim()).map_err(|_| Error::FailedToDecodeHexToKey)?;
+    let bytes_fixed_len: [u8; bls::PK_SIZE] = bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| Error::FailedToParseBlsKey)?;
+    let public_key = bls::PublicKey::from_bytes(bytes_fixed_len)?;
+    Ok(PublicAddress::new(public_key))
+}
+
+/// Returns the hex encoding of the dbc id source.
+pub fn dbc_id_src_to_hex(dbc_id_src: &DbcIdSource) -> Result<String> {
+    Ok(encode(bincode::serialize(dbc_id_src)?))
+}
+
+/// Returns the dbc id source encoded by `dbc_id_src_to_hex`.
+pub fn dbc_id_src_from_hex(hex: &str) -> Result<DbcIdSource> {
+    let bytes = decode(hex.trim()).map_err(|_| Error::FailedToDecodeHexToKey)?;
+    Ok(bincode::deserialize(&bytes)?)
+}
+
+/// Returns the hex encoding of the dbc.
+pub fn dbc_to_hex(dbc: &Dbc) -> Result<String> {
+    Ok(encode(bincode::serialize(dbc)?))
+}
+
+/// Returns the dbc encoded by `dbc_to_hex`.
+pub fn dbc_from_hex(hex: &str) -> Result<Dbc> {
+    let bytes = decode(hex.trim()).map_err(|_| Error::FailedToDecodeHexToDbc)?;
+    Ok(bincode::deserialize(&bytes)?)
+}
+
+/// Writes the dbc, hex-encoded, to the file, which must not exist yet,
+/// as to not overwrite another dbc.
+pub async fn write_dbc_file(path: &Path, dbc: &Dbc) -> Result<()> {
+    let hex = dbc_to_hex(dbc)?;
+    let mut file = match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await
+    {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(Error::DbcFileAlreadyExists(path.to_path_buf()))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    file.write_all(hex.as_bytes()).await?;
+    Ok(())
+}
+
+/// Returns the dbc from a file written with `write_dbc_file`.
+pub async fn read_dbc_file(path: &Path) -> Result<Dbc> {
+    dbc_from_hex(&fs::read_to_string(path).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::protocol::dbc_genesis::create_genesis_dbc;
+
+    use sn_dbc::MainKey;
+
+    use eyre::Result;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn keys_and_dbcs_to_and_from_hex() -> Result<()> {
+        let key = MainKey::random();
+
+        let address = key.public_address();
+        assert_eq!(address, address_from_hex(&address_to_hex(&address))?);
+
+        let dbc_id_src = key.random_dbc_id_src(&mut rand::thread_rng());
+        let decoded = dbc_id_src_from_hex(&dbc_id_src_to_hex(&dbc_id_src)?)?;
+        assert_eq!(dbc_id_src.dbc_id(), decoded.dbc_id());
+
+        let dbc = create_genesis_dbc(&key).expect("Genesis creation to succeed.");
+        let dir = tempdir()?;
+        let path = dir.path().join("dbc");
+        write_dbc_file(&path, &dbc).await?;
+        assert_eq!(dbc, read_dbc_file(&path).await?);
+        assert!(matches!(
+            write_dbc_file(&path, &dbc).await,
+            Err(Error::DbcFileAlreadyExists(_))
+        ));
+
+        assert!(matches!(
+            dbc_from_hex("not hex"),
+            Err(Error::FailedToDecodeHexToDbc)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Passphrase based encryption of the wallet files at rest.
+//! The key is derived from the passphrase with Argon2, using a random salt per encryption,
+//! and the data is encrypted with ChaCha20-Poly1305, so tampering is detected on decryption.
+
+use super::error::{Error, Result};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// Data encrypted with a passphrase, along with what's needed to decrypt it, but the passphrase.
+#[derive(Serialize, Deserialize)]
+struct Encrypted {
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+/// Encrypts the data with a key derived from the passphrase.
+pub(super) fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let salt: [u8; SALT_LEN] = rng.gen();
+    let nonce: [u8; NONCE_LEN] = rng.gen();
+
+    let cipher = cipher(passphrase, &salt)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|err| Error::Encryption(err.to_string()))?;
+
+    Ok(bincode::serialize(&Encrypted {
+        salt,
+        nonce,
+        ciphertext,
+    })?)
+}
+
+/// Decrypts data encrypted with `encrypt`, failing if the passphrase is not the one used then.
+pub(super) fn decrypt(passphrase: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+    let encrypted: Encrypted = bincode::deserialize(bytes)?;
+    let cipher = cipher(passphrase, &encrypted.salt)?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(&encrypted.nonce),
+            encrypted.ciphertext.as_slice(),
+        )
+        .map_err(|_| Error::WrongPassphrase)
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| Error::Encryption(err.to_string()))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_is_only_decrypted_with_the_same_passphrase() -> Result<()> {
+        let data = b"the wallet";
+        let encrypted = encrypt("correct horse", data)?;
+        assert_ne!(encrypted.as_slice(), data.as_slice());
+
+        assert_eq!(decrypt("correct horse", &encrypted)?, data.to_vec());
+        assert!(matches!(
+            decrypt("battery staple", &encrypted),
+            Err(Error::WrongPassphrase)
+        ));
+        Ok(())
+    }
+}
@@ -20,18 +20,63 @@ pub enum Error {
     /// A general error when a transfer fails.
     #[error("Failed to send tokens due to {0}")]
     CouldNotSendTokens(String),
+    /// A received dbc could not be verified against the spends recorded on the network.
+    #[error("Could not verify the received dbc: {0}")]
+    CouldNotVerifyTransfer(String),
     /// Failed to parse bytes into a bls key.
     #[error("Failed to parse bls key")]
     FailedToParseBlsKey,
     /// Failed to decode a hex string to a key.
     #[error("Could not decode hex string to key.")]
     FailedToDecodeHexToKey,
+    /// Failed to decode a hex string to a dbc.
+    #[error("Could not decode hex string to dbc.")]
+    FailedToDecodeHexToDbc,
     /// Failed to serialize a main key to hex.
     #[error("Could not serialize main key to hex: {0}")]
     FailedToHexEncodeKey(String),
+    /// The client used to store a wallet on the network was not created with the wallet's client key.
+    #[error("The client is not the owner of the wallet")]
+    NotTheWalletOwner,
+    /// The wallet stored on the network could not be decrypted with the wallet's key.
+    #[error("Failed to decrypt the wallet stored on the network")]
+    FailedToDecryptWallet,
+    /// Client error.
+    #[error("Client error: {0}")]
+    Client(#[from] crate::client::Error),
+    /// The wallet files are encrypted, and no passphrase was provided to unlock them.
+    #[error("The wallet is locked, a passphrase is required to unlock it")]
+    WalletLocked,
+    /// The passphrase is not the one the wallet files were encrypted with.
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+    /// Failed to encrypt the wallet files.
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    /// The mnemonic phrase of a key backup is invalid.
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+    /// A wallet already exists where one was to be restored.
+    #[error("A wallet already exists at {0:?}")]
+    WalletAlreadyExists(std::path::PathBuf),
+    /// Failed to create the genesis dbc.
+    #[error("Genesis error: {0}")]
+    Genesis(#[from] crate::protocol::dbc_genesis::GenesisError),
+    /// The spend of the genesis input was not recorded by the network in time.
+    #[error("The genesis spend was not recorded: {0:?}")]
+    GenesisSpendNotRecorded(crate::protocol::address::DbcAddress),
     /// Bls error.
     #[error("Bls error: {0}")]
     Bls(#[from] bls::error::Error),
+    /// Failed to export the ledger to JSON.
+    #[error("Failed to export the ledger: {0}")]
+    LedgerExport(#[from] serde_json::Error),
+    /// A dbc file is not overwritten, as to not lose the dbc it holds.
+    #[error("The dbc file {0:?} already exists")]
+    DbcFileAlreadyExists(std::path::PathBuf),
+    /// The wallet was serialised in a format version this software does not know of.
+    #[error("Unsupported wallet format version: {0}")]
+    UnsupportedWalletVersion(u8),
     /// Bincode error.
     #[error("Bincode error:: {0}")]
     Bincode(#[from] bincode::Error),
@@ -6,24 +6,44 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::error::{Error, Result};
+use super::{
+    encryption::{decrypt, encrypt},
+    error::{Error, Result},
+};
 
 use sn_dbc::MainKey;
 
+use bip39::Mnemonic;
 use hex::{decode, encode};
 use std::path::Path;
 use tokio::fs;
 
 /// Filename for storing the node's reward (BLS hex-encoded) main key.
 const MAIN_KEY_FILENAME: &str = "main_key";
+/// Filename for storing the main key encrypted with a passphrase.
+const ENCRYPTED_MAIN_KEY_FILENAME: &str = "main_key.encrypted";
 /// Filename for storing the node's reward (BLS hex-encoded) public address.
 const PUBLIC_ADDRESS_FILENAME: &str = "public_address";
 
 /// Writes the public address and main key (hex-encoded) to different locations at disk.
-pub(super) async fn store_new_keypair(root_dir: &Path, main_key: &MainKey) -> Result<()> {
+/// With a passphrase, the main key is encrypted with it, and any plain one removed.
+pub(super) async fn store_new_keypair(
+    root_dir: &Path,
+    main_key: &MainKey,
+    passphrase: Option<&str>,
+) -> Result<()> {
     let secret_key_path = root_dir.join(MAIN_KEY_FILENAME);
     let public_key_path = root_dir.join(PUBLIC_ADDRESS_FILENAME);
-    fs::write(secret_key_path, encode(main_key.to_bytes())).await?;
+    match passphrase {
+        Some(passphrase) => {
+            let encrypted = encrypt(passphrase, encode(main_key.to_bytes()).as_bytes())?;
+            fs::write(root_dir.join(ENCRYPTED_MAIN_KEY_FILENAME), encrypted).await?;
+            if secret_key_path.is_file() {
+                fs::remove_file(secret_key_path).await?;
+            }
+        }
+        None => fs::write(secret_key_path, encode(main_key.to_bytes())).await?,
+    }
     fs::write(
         public_key_path,
         encode(main_key.public_address().to_bytes()),
@@ -33,19 +53,61 @@ pub(super) async fn store_new_keypair(root_dir: &Path, main_key: &MainKey) -> Re
     Ok(())
 }
 
+/// Whether the main key is stored encrypted with a passphrase.
+pub(super) fn main_key_is_encrypted(root_dir: &Path) -> bool {
+    root_dir.join(ENCRYPTED_MAIN_KEY_FILENAME).is_file()
+}
+
 /// Returns Some(sn_dbc::MainKey) or None if file doesn't exist. It assumes it's hex-encoded.
-pub(super) async fn get_main_key(root_dir: &Path) -> Result<Option<MainKey>> {
+/// An encrypted main key takes precedence over a plain one, and requires the passphrase.
+pub(super) async fn get_main_key(
+    root_dir: &Path,
+    passphrase: Option<&str>,
+) -> Result<Option<MainKey>> {
+    let encrypted_path = root_dir.join(ENCRYPTED_MAIN_KEY_FILENAME);
     let path = root_dir.join(MAIN_KEY_FILENAME);
-    if !path.is_file() {
+    let secret_hex_bytes = if encrypted_path.is_file() {
+        let passphrase = passphrase.ok_or(Error::WalletLocked)?;
+        decrypt(passphrase, &fs::read(&encrypted_path).await?)?
+    } else if path.is_file() {
+        fs::read(&path).await?
+    } else {
         return Ok(None);
-    }
+    };
 
-    let secret_hex_bytes = fs::read(&path).await?;
     let secret = bls_secret_from_hex(secret_hex_bytes)?;
 
     Ok(Some(MainKey::new(secret)))
 }
 
+/// Returns the mnemonic phrase encoding the main key, to back it up.
+pub fn main_key_to_mnemonic(main_key: &MainKey) -> Result<String> {
+    let mnemonic = Mnemonic::from_entropy(&main_key.to_bytes())
+        .map_err(|err| Error::InvalidMnemonic(err.to_string()))?;
+    Ok(mnemonic.to_string())
+}
+
+/// Returns the main key encoded by the mnemonic phrase of a backup.
+pub fn main_key_from_mnemonic(phrase: &str) -> Result<MainKey> {
+    let mnemonic =
+        Mnemonic::parse(phrase).map_err(|err| Error::InvalidMnemonic(err.to_string()))?;
+    let secret = bls_secret_from_hex(encode(mnemonic.to_entropy()))?;
+    Ok(MainKey::new(secret))
+}
+
+/// Writes a backup of the main key, encrypted with the passphrase, to the file.
+pub async fn write_key_backup(path: &Path, main_key: &MainKey, passphrase: &str) -> Result<()> {
+    let encrypted = encrypt(passphrase, encode(main_key.to_bytes()).as_bytes())?;
+    fs::write(path, encrypted).await?;
+    Ok(())
+}
+
+/// Returns the main key from a backup file written with `write_key_backup`.
+pub async fn read_key_backup(path: &Path, passphrase: &str) -> Result<MainKey> {
+    let secret_hex_bytes = decrypt(passphrase, &fs::read(path).await?)?;
+    Ok(MainKey::new(bls_secret_from_hex(secret_hex_bytes)?))
+}
+
 /// Construct a BLS secret key from a hex-encoded string.
 #[allow(clippy::result_large_err)]
 fn bls_secret_from_hex<T: AsRef<[u8]>>(hex: T) -> Result<bls::SecretKey> {
@@ -60,7 +122,11 @@ fn bls_secret_from_hex<T: AsRef<[u8]>>(hex: T) -> Result<bls::SecretKey> {
 
 #[cfg(test)]
 mod test {
-    use super::{get_main_key, store_new_keypair, MainKey};
+    use super::{
+        get_main_key, main_key_from_mnemonic, main_key_to_mnemonic, read_key_backup,
+        store_new_keypair, write_key_backup, MainKey,
+    };
+    use crate::protocol::wallet::Error;
 
     use eyre::{eyre, Result};
     use tempfile::{tempdir, TempDir};
@@ -70,14 +136,52 @@ mod test {
         let main_key = MainKey::random();
         let dir = create_temp_dir()?;
         let root_dir = dir.path().to_path_buf();
-        store_new_keypair(&root_dir, &main_key).await?;
-        let secret_result = get_main_key(&root_dir)
+        store_new_keypair(&root_dir, &main_key, None).await?;
+        let secret_result = get_main_key(&root_dir, None)
+            .await?
+            .expect("There to be a key on disk.");
+        assert_eq!(secret_result.public_address(), main_key.public_address());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encrypted_key_to_and_from_file() -> Result<()> {
+        let main_key = MainKey::random();
+        let dir = create_temp_dir()?;
+        let root_dir = dir.path().to_path_buf();
+        store_new_keypair(&root_dir, &main_key, Some("passphrase")).await?;
+
+        assert!(matches!(
+            get_main_key(&root_dir, None).await,
+            Err(Error::WalletLocked)
+        ));
+        assert!(matches!(
+            get_main_key(&root_dir, Some("wrong")).await,
+            Err(Error::WrongPassphrase)
+        ));
+        let secret_result = get_main_key(&root_dir, Some("passphrase"))
             .await?
             .expect("There to be a key on disk.");
         assert_eq!(secret_result.public_address(), main_key.public_address());
         Ok(())
     }
 
+    #[tokio::test]
+    async fn key_backups_restore_the_key() -> Result<()> {
+        let main_key = MainKey::random();
+
+        let phrase = main_key_to_mnemonic(&main_key)?;
+        let restored = main_key_from_mnemonic(&phrase)?;
+        assert_eq!(restored.public_address(), main_key.public_address());
+
+        let dir = create_temp_dir()?;
+        let backup_path = dir.path().join("backup");
+        write_key_backup(&backup_path, &main_key, "passphrase").await?;
+        let restored = read_key_backup(&backup_path, "passphrase").await?;
+        assert_eq!(restored.public_address(), main_key.public_address());
+        Ok(())
+    }
+
     fn create_temp_dir() -> Result<TempDir> {
         tempdir().map_err(|e| eyre!("Failed to create temp dir: {}", e))
     }
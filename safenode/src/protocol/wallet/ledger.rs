@@ -0,0 +1,231 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A record of the tokens received and sent by a wallet, kept along with its dbcs.
+
+use super::error::Result;
+
+use crate::node::NodeId;
+
+use sn_dbc::{DbcId, PublicAddress, Token};
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Whether tokens were received by or sent from the wallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// Tokens deposited to the wallet.
+    Received,
+    /// Tokens sent to others from the wallet.
+    Sent,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Received => write!(f, "received"),
+            Self::Sent => write!(f, "sent"),
+        }
+    }
+}
+
+/// A deposit to, or a send from, the wallet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// Whether the tokens were received or sent.
+    pub direction: Direction,
+    /// The amount received, or sent to the recipients, fees excluded.
+    pub amount: Token,
+    /// The recipients of a send.
+    /// Empty for deposits, as a dbc does not tell who sent it.
+    pub counterparties: Vec<PublicAddress>,
+    /// The ids of the dbcs deposited, or created for the recipients.
+    pub dbc_ids: Vec<DbcId>,
+    /// The fees paid to each node for a send.
+    pub fees: BTreeMap<NodeId, Token>,
+    /// When the entry was recorded.
+    pub time: SystemTime,
+}
+
+impl LedgerEntry {
+    pub(super) fn received(amount: Token, dbc_ids: Vec<DbcId>) -> Self {
+        Self {
+            direction: Direction::Received,
+            amount,
+            counterparties: vec![],
+            dbc_ids,
+            fees: BTreeMap::new(),
+            time: SystemTime::now(),
+        }
+    }
+
+    pub(super) fn sent(
+        amount: Token,
+        counterparties: Vec<PublicAddress>,
+        dbc_ids: Vec<DbcId>,
+        fees: BTreeMap<NodeId, Token>,
+    ) -> Self {
+        Self {
+            direction: Direction::Sent,
+            amount,
+            counterparties,
+            dbc_ids,
+            fees,
+            time: SystemTime::now(),
+        }
+    }
+
+    /// The sum of the fees paid to all nodes.
+    pub fn total_fees(&self) -> Token {
+        Token::from_nano(self.fees.values().map(|fee| fee.as_nano()).sum())
+    }
+
+    fn to_record(&self) -> LedgerRecord {
+        LedgerRecord {
+            time: self
+                .time
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            direction: self.direction.to_string(),
+            amount: self.amount.to_string(),
+            fees: self.total_fees().to_string(),
+            counterparties: self
+                .counterparties
+                .iter()
+                .map(|address| hex::encode(address.to_bytes()))
+                .collect(),
+            dbc_ids: self
+                .dbc_ids
+                .iter()
+                .map(|id| hex::encode(id.to_bytes()))
+                .collect(),
+            node_fees: self
+                .fees
+                .iter()
+                .map(|(node_id, fee)| (node_id.to_string(), fee.to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// The formats the ledger can be exported to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedgerFormat {
+    /// One line per entry, with the header on the first line.
+    Csv,
+    /// An array of entries.
+    Json,
+}
+
+impl FromStr for LedgerFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Unknown ledger format: {other}, expected csv or json"
+            )),
+        }
+    }
+}
+
+/// An entry as exported, with keys in hex, amounts in tokens and time in seconds since the epoch.
+#[derive(Serialize)]
+struct LedgerRecord {
+    time: u64,
+    direction: String,
+    amount: String,
+    fees: String,
+    counterparties: Vec<String>,
+    dbc_ids: Vec<String>,
+    node_fees: BTreeMap<String, String>,
+}
+
+const CSV_HEADER: &str = "time,direction,amount,fees,counterparties,dbc_ids,node_fees";
+
+/// Exports the entries to the format.
+/// In csv, the values of a column holding several are separated by `;`,
+/// and each node's fee is given as `<node id>:<fee>`.
+pub(super) fn export(entries: &[LedgerEntry], format: LedgerFormat) -> Result<String> {
+    let records: Vec<_> = entries.iter().map(LedgerEntry::to_record).collect();
+    match format {
+        LedgerFormat::Json => Ok(serde_json::to_string_pretty(&records)?),
+        LedgerFormat::Csv => {
+            let mut csv = format!("{CSV_HEADER}\n");
+            for record in records {
+                let node_fees: Vec<_> = record
+                    .node_fees
+                    .iter()
+                    .map(|(node_id, fee)| format!("{node_id}:{fee}"))
+                    .collect();
+                // Writing to a String does not fail.
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{},{},{}",
+                    record.time,
+                    record.direction,
+                    record.amount,
+                    record.fees,
+                    record.counterparties.join(";"),
+                    record.dbc_ids.join(";"),
+                    node_fees.join(";"),
+                );
+            }
+            Ok(csv)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sn_dbc::MainKey;
+
+    #[test]
+    fn entries_are_exported_to_csv_and_json() -> eyre::Result<()> {
+        let key = MainKey::random();
+        let dbc_id_src = key.random_dbc_id_src(&mut rand::thread_rng());
+        let entries = vec![
+            LedgerEntry::received(Token::from_nano(10), vec![dbc_id_src.dbc_id()]),
+            LedgerEntry::sent(
+                Token::from_nano(5),
+                vec![key.public_address()],
+                vec![dbc_id_src.dbc_id()],
+                BTreeMap::from([(NodeId::default(), Token::from_nano(1))]),
+            ),
+        ];
+
+        let csv = export(&entries, LedgerFormat::Csv)?;
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].contains(",received,"));
+        assert!(lines[2].contains(",sent,"));
+        assert!(lines[2].contains(&hex::encode(key.public_address().to_bytes())));
+
+        let json: serde_json::Value = serde_json::from_str(&export(&entries, LedgerFormat::Json)?)?;
+        assert_eq!(json.as_array().map(Vec::len), Some(2));
+        assert_eq!(json[1]["direction"], "sent");
+        assert_eq!(
+            json[1]["node_fees"].as_object().map(|fees| fees.len()),
+            Some(1)
+        );
+
+        Ok(())
+    }
+}
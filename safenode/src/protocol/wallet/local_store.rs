@@ -7,14 +7,22 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    keys::{get_main_key, store_new_keypair},
-    wallet_file::{get_wallet, store_wallet},
-    DepositWallet, KeyLessWallet, Result, SendClient, SendWallet, Wallet,
+    keys::{
+        get_main_key, main_key_is_encrypted, main_key_to_mnemonic, store_new_keypair,
+        write_key_backup,
+    },
+    ledger::{self, LedgerEntry, LedgerFormat},
+    wallet_file::{get_wallet, store_wallet, wallet_is_encrypted},
+    DepositWallet, Error, KeyLessWallet, ReconcileWallet, Reconciliation, Result, SendClient,
+    SendWallet, SpendStatus, Wallet,
 };
 
-use crate::protocol::transfers::{CreatedDbc, Outputs as TransferDetails};
+use crate::protocol::{
+    fees::SpendQueuePosition,
+    transfers::{CreatedDbc, Outputs as TransferDetails},
+};
 
-use sn_dbc::{Dbc, DbcIdSource, MainKey, PublicAddress, Token};
+use sn_dbc::{Dbc, DbcId, DbcIdSource, MainKey, PublicAddress, Token};
 
 use async_trait::async_trait;
 use std::{
@@ -31,40 +39,112 @@ pub struct LocalWallet {
     wallet: KeyLessWallet,
     /// The dir of the wallet file.
     root_dir: PathBuf,
+    /// The passphrase the wallet files are encrypted with, if any.
+    passphrase: Option<String>,
 }
 
 impl LocalWallet {
     /// Stores the wallet to disk.
     pub async fn store(&self) -> Result<()> {
-        store_wallet(&self.root_dir, &self.wallet).await
+        store_wallet(&self.root_dir, &self.wallet, self.passphrase.as_deref()).await
     }
 
     /// Loads a serialized wallet from a path.
+    /// Fails with `Error::WalletLocked` if the wallet files are encrypted.
     pub async fn load_from(root_dir: &Path) -> Result<Self> {
-        let (key, wallet) = load_from_path(root_dir).await?;
+        Self::load_with(root_dir, None).await
+    }
+
+    /// Loads a serialized wallet from a path, decrypting its files with the passphrase.
+    /// Wallet files that are not encrypted yet are encrypted with it.
+    pub async fn unlock_from(root_dir: &Path, passphrase: &str) -> Result<Self> {
+        Self::load_with(root_dir, Some(passphrase)).await
+    }
+
+    /// Restores the wallet of the key, e.g. read from a backup, to a path where there is
+    /// no wallet yet, encrypting its files with the passphrase if any.
+    pub async fn restore_to(
+        root_dir: &Path,
+        key: MainKey,
+        passphrase: Option<&str>,
+    ) -> Result<Self> {
+        let existing = get_main_key(root_dir, passphrase).await;
+        if existing.map_or(true, |existing| existing.is_some()) {
+            return Err(Error::WalletAlreadyExists(root_dir.to_path_buf()));
+        }
+        store_new_keypair(root_dir, &key, passphrase).await?;
+        let wallet = Self {
+            key,
+            wallet: KeyLessWallet::new(),
+            root_dir: root_dir.to_path_buf(),
+            passphrase: passphrase.map(str::to_string),
+        };
+        wallet.store().await?;
+        Ok(wallet)
+    }
+
+    /// Returns the mnemonic phrase encoding the wallet's key, to back it up.
+    pub fn export_mnemonic(&self) -> Result<String> {
+        main_key_to_mnemonic(&self.key)
+    }
+
+    /// Writes a backup of the wallet's key, encrypted with the passphrase, to the file.
+    pub async fn export_backup(&self, path: &Path, passphrase: &str) -> Result<()> {
+        write_key_backup(path, &self.key, passphrase).await
+    }
+
+    /// The record of all tokens received and sent, oldest first.
+    pub fn ledger(&self) -> &[LedgerEntry] {
+        self.wallet.ledger()
+    }
+
+    /// Returns the ledger exported to the format.
+    pub fn export_ledger(&self, format: LedgerFormat) -> Result<String> {
+        ledger::export(self.wallet.ledger(), format)
+    }
+
+    async fn load_with(root_dir: &Path, passphrase: Option<&str>) -> Result<Self> {
+        let (key, wallet) = load_from_path(root_dir, passphrase).await?;
         Ok(Self {
             key,
             wallet,
             root_dir: root_dir.to_path_buf(),
+            passphrase: passphrase.map(str::to_string),
         })
     }
 }
 
 /// Loads a serialized wallet from a path.
-async fn load_from_path(root_dir: &Path) -> Result<(MainKey, KeyLessWallet)> {
-    let key = match get_main_key(root_dir).await? {
-        Some(key) => key,
+/// With a passphrase, the files which are not encrypted yet are rewritten encrypted with it.
+async fn load_from_path(
+    root_dir: &Path,
+    passphrase: Option<&str>,
+) -> Result<(MainKey, KeyLessWallet)> {
+    let key_was_encrypted = main_key_is_encrypted(root_dir);
+    let key = match get_main_key(root_dir, passphrase).await? {
+        Some(key) => {
+            if passphrase.is_some() && !key_was_encrypted {
+                store_new_keypair(root_dir, &key, passphrase).await?;
+            }
+            key
+        }
         None => {
             let key = MainKey::random();
-            store_new_keypair(root_dir, &key).await?;
+            store_new_keypair(root_dir, &key, passphrase).await?;
             key
         }
     };
-    let wallet = match get_wallet(root_dir).await? {
-        Some(wallet) => wallet,
+    let wallet_was_encrypted = wallet_is_encrypted(root_dir);
+    let wallet = match get_wallet(root_dir, passphrase).await? {
+        Some(wallet) => {
+            if passphrase.is_some() && !wallet_was_encrypted {
+                store_wallet(root_dir, &wallet, passphrase).await?;
+            }
+            wallet
+        }
         None => {
             let wallet = KeyLessWallet::new();
-            store_wallet(root_dir, &wallet).await?;
+            store_wallet(root_dir, &wallet, passphrase).await?;
             wallet
         }
     };
@@ -73,89 +153,113 @@ async fn load_from_path(root_dir: &Path) -> Result<(MainKey, KeyLessWallet)> {
 }
 
 impl KeyLessWallet {
-    fn new() -> Self {
+    pub(super) fn new() -> Self {
         Self {
             balance: Token::zero(),
             spent_dbcs: BTreeMap::new(),
             available_dbcs: BTreeMap::new(),
             dbcs_created_for_others: vec![],
+            ledger: vec![],
         }
     }
 
-    fn balance(&self) -> Token {
+    pub(super) fn balance(&self) -> Token {
         self.balance
     }
 
-    fn deposit(&mut self, dbcs: Vec<Dbc>, key: &MainKey) {
-        if dbcs.is_empty() {
+    pub(super) fn deposit(&mut self, dbcs: Vec<Dbc>, key: &MainKey) {
+        let received = self.add_dbcs(dbcs, key);
+        if received.is_empty() {
             return;
         }
 
-        let mut received_dbcs = dbcs
-            .into_iter()
-            .filter_map(|dbc| {
-                let id = dbc.id();
-                (!self.spent_dbcs.contains_key(&id)).then_some((id, dbc))
-            })
-            .filter_map(|(id, dbc)| dbc.derived_key(key).is_ok().then_some((id, dbc)))
-            .collect();
-
-        self.available_dbcs.append(&mut received_dbcs);
-
-        let new_balance = self
-            .available_dbcs
-            .iter()
-            .flat_map(|(_, dbc)| dbc.derived_key(key).map(|derived_key| (dbc, derived_key)))
-            .flat_map(|(dbc, derived_key)| dbc.revealed_input(&derived_key))
-            .fold(0, |total, amount| total + amount.revealed_amount().value());
-
-        self.balance = Token::from_nano(new_balance);
+        let amount = Token::from_nano(received.iter().map(|(_, amount)| amount.as_nano()).sum());
+        let dbc_ids = received.into_iter().map(|(id, _)| id).collect();
+        self.ledger.push(LedgerEntry::received(amount, dbc_ids));
     }
-}
 
-impl Wallet for LocalWallet {
-    fn address(&self) -> PublicAddress {
-        self.key.public_address()
-    }
+    /// Adds the dbcs that are ours and not held already,
+    /// returning their ids and amounts.
+    fn add_dbcs(&mut self, dbcs: Vec<Dbc>, key: &MainKey) -> Vec<(DbcId, Token)> {
+        if dbcs.is_empty() {
+            return vec![];
+        }
 
-    fn balance(&self) -> Token {
-        self.wallet.balance()
-    }
-}
+        let mut received = vec![];
+        for dbc in dbcs {
+            let id = dbc.id();
+            if self.spent_dbcs.contains_key(&id) || self.available_dbcs.contains_key(&id) {
+                continue;
+            }
+            let amount = match dbc
+                .derived_key(key)
+                .and_then(|derived_key| dbc.revealed_input(&derived_key))
+            {
+                Ok(input) => Token::from_nano(input.revealed_amount().value()),
+                Err(_) => continue,
+            };
+            let _ = self.available_dbcs.insert(id, dbc);
+            received.push((id, amount));
+        }
 
-impl DepositWallet for LocalWallet {
-    fn new_dbc_address(&self) -> DbcIdSource {
-        self.key.random_dbc_id_src(&mut rand::thread_rng())
+        self.update_balance(key);
+        received
     }
 
-    fn deposit(&mut self, dbcs: Vec<Dbc>) {
-        self.wallet.deposit(dbcs, &self.key);
+    pub(super) fn reconcile(
+        &mut self,
+        statuses: BTreeMap<DbcId, SpendStatus>,
+        key: &MainKey,
+    ) -> Reconciliation {
+        let mut reconciliation = Reconciliation::default();
+        for (id, status) in statuses {
+            match status {
+                SpendStatus::Spent => {
+                    if let Some(dbc) = self.available_dbcs.remove(&id) {
+                        let _ = self.spent_dbcs.insert(id, dbc);
+                        reconciliation.spent.push(id);
+                    }
+                }
+                SpendStatus::Unspent => {
+                    if let Some(dbc) = self.spent_dbcs.remove(&id) {
+                        let _ = self.available_dbcs.insert(id, dbc);
+                        reconciliation.restored.push(id);
+                    }
+                }
+                SpendStatus::DoubleSpent => {
+                    // A double spent dbc can never be spent, so it's kept with the spent ones.
+                    if let Some(dbc) = self.available_dbcs.remove(&id) {
+                        let _ = self.spent_dbcs.insert(id, dbc);
+                    }
+                    if self.spent_dbcs.contains_key(&id) {
+                        reconciliation.double_spent.push(id);
+                    }
+                }
+            }
+        }
+        self.update_balance(key);
+        reconciliation
     }
-}
 
-#[async_trait]
-impl SendWallet for LocalWallet {
-    async fn send<C: SendClient>(
+    pub(super) async fn send<C: SendClient>(
         &mut self,
-        to: Vec<(Token, PublicAddress)>,
+        key: &MainKey,
+        to: Vec<(Token, DbcIdSource)>,
         client: &C,
-    ) -> Result<Vec<CreatedDbc>> {
+    ) -> Result<(Vec<CreatedDbc>, BTreeMap<DbcId, SpendQueuePosition>)> {
         // do not make a pointless send to ourselves
 
         let to: Vec<_> = to
             .into_iter()
-            .filter_map(|(amount, address)| {
-                let dbc_id_src = address.random_dbc_id_src(&mut rand::thread_rng());
-                (address != self.address()).then_some((amount, dbc_id_src))
-            })
+            .filter(|(_, dbc_id_src)| dbc_id_src.public_address != key.public_address())
             .collect();
         if to.is_empty() {
-            return Ok(vec![]);
+            return Ok((vec![], BTreeMap::new()));
         }
 
         let mut available_dbcs = vec![];
-        for dbc in self.wallet.available_dbcs.values() {
-            if let Ok(derived_key) = dbc.derived_key(&self.key) {
+        for dbc in self.available_dbcs.values() {
+            if let Ok(derived_key) = dbc.derived_key(key) {
                 available_dbcs.push((dbc.clone(), derived_key));
             } else {
                 warn!(
@@ -165,10 +269,25 @@ impl SendWallet for LocalWallet {
             }
         }
 
+        let amount = Token::from_nano(to.iter().map(|(amount, _)| amount.as_nano()).sum());
+        let counterparties = to
+            .iter()
+            .map(|(_, dbc_id_src)| dbc_id_src.public_address)
+            .collect();
+        let dbc_ids = to
+            .iter()
+            .map(|(_, dbc_id_src)| dbc_id_src.dbc_id())
+            .collect();
+
         let TransferDetails {
             change_dbc,
             created_dbcs,
-        } = client.send(available_dbcs, to, self.address()).await?;
+            fees,
+            spend_queue_positions,
+            ..
+        } = client
+            .send(available_dbcs, to, key.public_address())
+            .await?;
 
         let spent_dbc_ids: BTreeSet<_> = created_dbcs
             .iter()
@@ -178,16 +297,122 @@ impl SendWallet for LocalWallet {
 
         let mut spent_dbcs = spent_dbc_ids
             .into_iter()
-            .filter_map(|id| self.wallet.available_dbcs.remove(id).map(|dbc| (*id, dbc)))
+            .filter_map(|id| self.available_dbcs.remove(id).map(|dbc| (*id, dbc)))
             .collect();
 
-        self.deposit(change_dbc.into_iter().collect());
-        self.wallet.spent_dbcs.append(&mut spent_dbcs);
-        self.wallet
-            .dbcs_created_for_others
-            .extend(created_dbcs.clone());
+        // The change is our own, and so not recorded as received.
+        let _ = self.add_dbcs(change_dbc.into_iter().collect(), key);
+        self.spent_dbcs.append(&mut spent_dbcs);
+        self.dbcs_created_for_others.extend(created_dbcs.clone());
+        self.ledger
+            .push(LedgerEntry::sent(amount, counterparties, dbc_ids, fees));
+
+        Ok((created_dbcs, spend_queue_positions))
+    }
+
+    /// The ids of all the dbcs held, both the available and the spent ones.
+    pub(super) fn dbc_ids(&self) -> Vec<DbcId> {
+        self.available_dbcs
+            .keys()
+            .chain(self.spent_dbcs.keys())
+            .copied()
+            .collect()
+    }
+
+    pub(super) fn ledger(&self) -> &[LedgerEntry] {
+        &self.ledger
+    }
+
+    /// Merges another state of the wallet into this one, e.g. a concurrently stored one:
+    /// the dbcs and ledger entries of both are kept, a dbc spent in either being spent.
+    pub(super) fn merge(&mut self, other: KeyLessWallet, key: &MainKey) {
+        self.spent_dbcs.extend(other.spent_dbcs);
+        self.available_dbcs.extend(other.available_dbcs);
+        self.available_dbcs
+            .retain(|id, _| !self.spent_dbcs.contains_key(id));
+
+        for created in other.dbcs_created_for_others {
+            if !self
+                .dbcs_created_for_others
+                .iter()
+                .any(|held| held.dbc.id() == created.dbc.id())
+            {
+                self.dbcs_created_for_others.push(created);
+            }
+        }
+
+        for entry in other.ledger {
+            if !self.ledger.contains(&entry) {
+                self.ledger.push(entry);
+            }
+        }
+        self.ledger.sort_by_key(|entry| entry.time);
+
+        self.update_balance(key);
+    }
+
+    fn update_balance(&mut self, key: &MainKey) {
+        let new_balance = self
+            .available_dbcs
+            .iter()
+            .flat_map(|(_, dbc)| dbc.derived_key(key).map(|derived_key| (dbc, derived_key)))
+            .flat_map(|(dbc, derived_key)| dbc.revealed_input(&derived_key))
+            .fold(0, |total, amount| total + amount.revealed_amount().value());
+
+        self.balance = Token::from_nano(new_balance);
+    }
+}
+
+impl Wallet for LocalWallet {
+    fn address(&self) -> PublicAddress {
+        self.key.public_address()
+    }
+
+    fn balance(&self) -> Token {
+        self.wallet.balance()
+    }
+}
+
+#[async_trait]
+impl DepositWallet for LocalWallet {
+    fn new_dbc_address(&self) -> DbcIdSource {
+        self.key.random_dbc_id_src(&mut rand::thread_rng())
+    }
+
+    fn deposit(&mut self, dbcs: Vec<Dbc>) {
+        self.wallet.deposit(dbcs, &self.key);
+    }
+
+    async fn deposit_and_store(&mut self, dbcs: Vec<Dbc>) -> Result<()> {
+        self.deposit(dbcs);
+        self.store().await
+    }
+}
+
+#[async_trait]
+impl ReconcileWallet for LocalWallet {
+    fn dbc_ids(&self) -> Vec<DbcId> {
+        self.wallet.dbc_ids()
+    }
+
+    async fn reconcile(
+        &mut self,
+        statuses: BTreeMap<DbcId, SpendStatus>,
+    ) -> Result<Reconciliation> {
+        let reconciliation = self.wallet.reconcile(statuses, &self.key);
+        self.store().await?;
+        Ok(reconciliation)
+    }
+}
 
-        Ok(created_dbcs)
+#[async_trait]
+impl SendWallet for LocalWallet {
+    async fn send<C: SendClient>(
+        &mut self,
+        to: Vec<(Token, DbcIdSource)>,
+        client: &C,
+    ) -> Result<(Vec<CreatedDbc>, BTreeMap<DbcId, SpendQueuePosition>)> {
+        self.wallet.send(&self.key, to, client).await
     }
 }
 
@@ -217,9 +442,9 @@ mod tests {
 
         wallet.deposit(vec![genesis], &key);
 
-        store_wallet(&root_dir, &wallet).await?;
+        store_wallet(&root_dir, &wallet, None).await?;
 
-        let deserialized = get_wallet(&root_dir)
+        let deserialized = get_wallet(&root_dir, None)
             .await?
             .expect("There to be a wallet on disk.");
 
@@ -242,6 +467,7 @@ mod tests {
             key,
             wallet: KeyLessWallet::new(),
             root_dir: dir.path().to_path_buf(),
+            passphrase: None,
         };
 
         assert_eq!(public_address, deposit_only.address());
@@ -258,6 +484,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn reconcile_moves_dbcs_by_their_spend_status() -> Result<()> {
+        use super::SpendStatus;
+        use std::collections::BTreeMap;
+
+        let key = MainKey::random();
+        let mut wallet = KeyLessWallet::new();
+        let genesis = create_genesis_dbc(&key).expect("Genesis creation to succeed.");
+        let id = genesis.id();
+        wallet.deposit(vec![genesis], &key);
+
+        // Found spent, e.g. after a crash in the middle of a send.
+        let reconciliation = wallet.reconcile(BTreeMap::from([(id, SpendStatus::Spent)]), &key);
+        assert_eq!(reconciliation.spent, vec![id]);
+        assert_eq!(Token::zero(), wallet.balance());
+        assert!(wallet.spent_dbcs.contains_key(&id));
+
+        // The spend was never accepted.
+        let reconciliation = wallet.reconcile(BTreeMap::from([(id, SpendStatus::Unspent)]), &key);
+        assert_eq!(reconciliation.restored, vec![id]);
+        assert_eq!(GENESIS_DBC_AMOUNT, wallet.balance().as_nano());
+
+        let reconciliation =
+            wallet.reconcile(BTreeMap::from([(id, SpendStatus::DoubleSpent)]), &key);
+        assert_eq!(reconciliation.double_spent, vec![id]);
+        assert_eq!(Token::zero(), wallet.balance());
+        assert!(wallet.available_dbcs.is_empty());
+
+        Ok(())
+    }
+
     /// -----------------------------------
     /// <-------> DepositWallet <--------->
     /// -----------------------------------
@@ -273,6 +530,7 @@ mod tests {
             key: MainKey::random(),
             wallet: KeyLessWallet::new(),
             root_dir: dir.path().to_path_buf(),
+            passphrase: None,
         };
 
         deposit_only.deposit(vec![]);
@@ -300,6 +558,7 @@ mod tests {
             key,
             wallet: KeyLessWallet::new(),
             root_dir: dir.path().to_path_buf(),
+            passphrase: None,
         };
 
         deposit_only.deposit(vec![genesis]);
@@ -322,6 +581,7 @@ mod tests {
             key: MainKey::random(),
             wallet: KeyLessWallet::new(),
             root_dir: dir.path().to_path_buf(),
+            passphrase: None,
         };
 
         local_wallet.deposit(vec![genesis]);
@@ -375,6 +635,58 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn deposit_and_store_persists_the_deposit() -> Result<()> {
+        // Bring in the necessary traits.
+        use super::{DepositWallet, Wallet};
+
+        let dir = create_temp_dir()?;
+        let root_dir = dir.path().to_path_buf();
+
+        let mut depositor = LocalWallet::load_from(&root_dir).await?;
+        let genesis = create_genesis_dbc(&depositor.key).expect("Genesis creation to succeed.");
+        depositor.deposit_and_store(vec![genesis]).await?;
+
+        let deserialized = LocalWallet::load_from(&root_dir).await?;
+        assert_eq!(GENESIS_DBC_AMOUNT, deserialized.balance().as_nano());
+        assert_eq!(1, deserialized.wallet.available_dbcs.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unlocking_encrypts_the_wallet_files() -> Result<()> {
+        // Bring in the necessary traits.
+        use super::{DepositWallet, Wallet};
+        use crate::protocol::wallet::Error;
+
+        let dir = create_temp_dir()?;
+        let root_dir = dir.path().to_path_buf();
+
+        let mut depositor = LocalWallet::load_from(&root_dir).await?;
+        let genesis = create_genesis_dbc(&depositor.key).expect("Genesis creation to succeed.");
+        depositor.deposit(vec![genesis]);
+        depositor.store().await?;
+
+        let unlocked = LocalWallet::unlock_from(&root_dir, "passphrase").await?;
+        assert_eq!(depositor.address(), unlocked.address());
+        assert_eq!(GENESIS_DBC_AMOUNT, unlocked.balance().as_nano());
+
+        assert!(matches!(
+            LocalWallet::load_from(&root_dir).await,
+            Err(Error::WalletLocked)
+        ));
+        assert!(matches!(
+            LocalWallet::unlock_from(&root_dir, "wrong").await,
+            Err(Error::WrongPassphrase)
+        ));
+
+        let unlocked = LocalWallet::unlock_from(&root_dir, "passphrase").await?;
+        assert_eq!(GENESIS_DBC_AMOUNT, unlocked.balance().as_nano());
+
+        Ok(())
+    }
+
     /// --------------------------------
     /// <-------> SendWallet <--------->
     /// --------------------------------
@@ -398,10 +710,15 @@ mod tests {
         let send_amount = 100;
         let recipient_key = MainKey::random();
         let recipient_public_address = recipient_key.public_address();
-        let to = vec![(Token::from_nano(send_amount), recipient_public_address)];
-        let created_dbcs = sender.send(to, &MockSendClient).await?;
+        let to = vec![(
+            Token::from_nano(send_amount),
+            recipient_public_address.random_dbc_id_src(&mut rand::thread_rng()),
+        )];
+        let (created_dbcs, positions) = sender.send(to, &MockSendClient).await?;
 
         assert_eq!(1, created_dbcs.len());
+        // Nothing was sent to the network.
+        assert!(positions.is_empty());
         assert_eq!(GENESIS_DBC_AMOUNT - send_amount, sender.balance().as_nano());
 
         let recipient_dbc = &created_dbcs[0];
@@ -430,8 +747,11 @@ mod tests {
         let send_amount = 100;
         let recipient_key = MainKey::random();
         let recipient_public_address = recipient_key.public_address();
-        let to = vec![(Token::from_nano(send_amount), recipient_public_address)];
-        let _created_dbcs = sender.send(to, &MockSendClient).await?;
+        let to = vec![(
+            Token::from_nano(send_amount),
+            recipient_public_address.random_dbc_id_src(&mut rand::thread_rng()),
+        )];
+        let _sent = sender.send(to, &MockSendClient).await?;
 
         sender.store().await?;
 
@@ -495,6 +815,52 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn ledger_records_deposits_and_sends() -> Result<()> {
+        // Bring in the necessary traits.
+        use super::{DepositWallet, SendWallet};
+        use crate::protocol::wallet::Direction;
+
+        let dir = create_temp_dir()?;
+        let root_dir = dir.path().to_path_buf();
+
+        let mut sender = LocalWallet::load_from(&root_dir).await?;
+        let sender_dbc = create_genesis_dbc(&sender.key).expect("Genesis creation to succeed.");
+        let genesis_id = sender_dbc.id();
+        sender.deposit(vec![sender_dbc.clone()]);
+        // Depositing a dbc already held is not recorded again.
+        sender.deposit(vec![sender_dbc]);
+
+        let send_amount = 100;
+        let recipient_public_address = MainKey::random().public_address();
+        let to = vec![(
+            Token::from_nano(send_amount),
+            recipient_public_address.random_dbc_id_src(&mut rand::thread_rng()),
+        )];
+        let (created_dbcs, _) = sender.send(to, &MockSendClient).await?;
+        sender.store().await?;
+
+        // The change deposited by the send is not recorded as received.
+        let ledger = sender.ledger();
+        assert_eq!(2, ledger.len());
+
+        assert_eq!(Direction::Received, ledger[0].direction);
+        assert_eq!(GENESIS_DBC_AMOUNT, ledger[0].amount.as_nano());
+        assert_eq!(vec![genesis_id], ledger[0].dbc_ids);
+
+        assert_eq!(Direction::Sent, ledger[1].direction);
+        assert_eq!(send_amount, ledger[1].amount.as_nano());
+        assert_eq!(vec![recipient_public_address], ledger[1].counterparties);
+        assert_eq!(vec![created_dbcs[0].dbc.id()], ledger[1].dbc_ids);
+        // No fees are paid for an offline transfer.
+        assert_eq!(Token::zero(), ledger[1].total_fees());
+
+        let deserialized = LocalWallet::load_from(&root_dir).await?;
+        assert_eq!(sender.ledger(), deserialized.ledger());
+
+        Ok(())
+    }
+
     fn create_temp_dir() -> Result<TempDir> {
         tempdir().map_err(|e| eyre!("Failed to create temp dir: {}", e))
     }
@@ -30,24 +30,40 @@
 //!
 //! We will already now pave for that, by mimicing that flow for the local storage of a Wallet.
 //! First though, a simpler local storage will be used. But after that a local register store can be implemented.
+//!
+//! Meanwhile, the `NetworkWallet` stores the whole state of a wallet on the network, encrypted,
+//! in an owner-only Register, so that it can be recovered on any machine from its `MainKey`.
 
+mod encoding;
+mod encryption;
 mod error;
 mod keys;
+mod ledger;
 mod local_store;
 mod network_store;
 mod wallet_file;
 
 pub use self::{
+    encoding::{
+        address_from_hex, address_to_hex, dbc_from_hex, dbc_id_src_from_hex, dbc_id_src_to_hex,
+        dbc_to_hex, read_dbc_file, write_dbc_file,
+    },
     error::{Error, Result},
+    keys::{main_key_from_mnemonic, main_key_to_mnemonic, read_key_backup, write_key_backup},
+    ledger::{Direction, LedgerEntry, LedgerFormat},
     local_store::LocalWallet,
-    // network_store::NetworkWallet,
+    network_store::NetworkWallet,
 };
 
-use super::transfers::{CreatedDbc, Outputs as TransferDetails};
+use super::{
+    fees::SpendQueuePosition,
+    transfers::{CreatedDbc, Outputs as TransferDetails},
+};
 
-use sn_dbc::{Dbc, DbcIdSource, DerivedKey, PublicAddress, Token};
+use sn_dbc::{Dbc, DbcId, DbcIdSource, DerivedKey, PublicAddress, Token};
 
 use async_trait::async_trait;
+use std::collections::BTreeMap;
 
 /// A SendClient is used to transfer tokens to other addresses.
 ///
@@ -89,24 +105,64 @@ pub trait Wallet {
 /// of a deposit wallet, can also send tokens to other addresses.
 #[async_trait]
 pub trait SendWallet: DepositWallet {
-    /// Sends the given tokens to the given addresses.
-    /// Returns the new dbcs that were created.
+    /// Sends the given tokens to the given dbc ids, derived from the recipients' addresses.
+    /// Returns the new dbcs that were created, and where the spend of each input dbc sits
+    /// in the spend queue of its close group.
     /// Depending on the implementation of the send client, this may
-    /// also register the transaction with the network.
+    /// also register the transaction with the network, the positions being empty otherwise.
     async fn send<C: SendClient>(
         &mut self,
-        to: Vec<(Token, PublicAddress)>,
+        to: Vec<(Token, DbcIdSource)>,
         client: &C,
-    ) -> Result<Vec<CreatedDbc>>;
+    ) -> Result<(Vec<CreatedDbc>, BTreeMap<DbcId, SpendQueuePosition>)>;
 }
 
 /// A deposit wallet is a wallet that can receive tokens from other wallets.
 /// It can however not send tokens to other addresses.
+#[async_trait]
 pub trait DepositWallet: Wallet {
     /// Used to generate a new dbc id for receiving tokens.
     fn new_dbc_address(&self) -> DbcIdSource;
     /// Will only deposit those that are actually accessible by this wallet.
     fn deposit(&mut self, dbcs: Vec<Dbc>);
+    /// Deposits the dbcs as `deposit` does, then persists the wallet,
+    /// so the received tokens are not lost.
+    async fn deposit_and_store(&mut self, dbcs: Vec<Dbc>) -> Result<()>;
+}
+
+/// A reconcile wallet is a send wallet whose dbcs can be reconciled with
+/// their spends on the network, e.g. after a crash in the middle of a send,
+/// or after the network rejected a spend.
+#[async_trait]
+pub trait ReconcileWallet: SendWallet {
+    /// The ids of all the dbcs held, both the available and the spent ones.
+    fn dbc_ids(&self) -> Vec<DbcId>;
+    /// Applies the spend statuses of the dbcs found on the network,
+    /// and persists the resulting wallet.
+    async fn reconcile(&mut self, statuses: BTreeMap<DbcId, SpendStatus>)
+        -> Result<Reconciliation>;
+}
+
+/// The status of the spend of a dbc on the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpendStatus {
+    /// No spend of the dbc was accepted by the network.
+    Unspent,
+    /// The dbc was spent, or its spend was accepted and is queued.
+    Spent,
+    /// The dbc was double spent, and so can never be spent.
+    DoubleSpent,
+}
+
+/// The changes made to a wallet when reconciling it with the network.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Dbcs held as available, which were found spent.
+    pub spent: Vec<DbcId>,
+    /// Dbcs held as spent, whose spend was never accepted, and which are available again.
+    pub restored: Vec<DbcId>,
+    /// Dbcs found double spent, which are no longer available.
+    pub double_spent: Vec<DbcId>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -115,13 +171,15 @@ pub(super) struct KeyLessWallet {
     balance: Token,
     /// These are dbcs we've owned, that have been
     /// spent when sending tokens to other addresses.
-    spent_dbcs: std::collections::BTreeMap<sn_dbc::DbcId, Dbc>,
+    spent_dbcs: BTreeMap<DbcId, Dbc>,
     /// These are the dbcs we own that are not yet spent.
-    available_dbcs: std::collections::BTreeMap<sn_dbc::DbcId, Dbc>,
+    available_dbcs: BTreeMap<DbcId, Dbc>,
     /// These are the dbcs we've created by
     /// sending tokens to other addresses.
     /// They are not owned by us, but we
     /// keep them here so we can track our
     /// transfer history.
     dbcs_created_for_others: Vec<CreatedDbc>,
+    /// The record of all tokens received and sent.
+    ledger: Vec<LedgerEntry>,
 }
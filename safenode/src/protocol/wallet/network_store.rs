@@ -5,3 +5,230 @@
 // under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    wallet_file::{deserialize_wallet, serialize_wallet},
+    DepositWallet, Error, KeyLessWallet, ReconcileWallet, Reconciliation, Result, SendClient,
+    SendWallet, SpendStatus, Wallet,
+};
+
+use crate::{
+    client::{ChunkedRegister, Client, Error as ClientError},
+    protocol::{error::Error as ProtocolError, fees::SpendQueuePosition, transfers::CreatedDbc},
+};
+
+use sn_dbc::{Dbc, DbcId, DbcIdSource, MainKey, PublicAddress, Token};
+
+use async_trait::async_trait;
+use bls::{Ciphertext, SecretKey};
+use std::collections::BTreeMap;
+use xor_name::XorName;
+
+// Tag of the Registers holding wallets.
+const WALLET_REGISTER_TAG: u64 = 1_000;
+// Index the key owning the Register of a wallet is derived from its main key with.
+const OWNER_KEY_INDEX: &[u8] = b"network-wallet-owner";
+
+/// A wallet whose state is stored on the network, encrypted to its key, as the latest
+/// entry of a Register only its owner can write to. The Register is found and owned
+/// through keys derived from the wallet's `MainKey`, so that the wallet can be
+/// recovered on a new machine from that key alone.
+pub struct NetworkWallet {
+    /// The secret key with which we can access
+    /// all the tokens in the available_dbcs.
+    key: MainKey,
+    /// The wallet containing all data.
+    wallet: KeyLessWallet,
+    /// The Register the encrypted wallet is written to.
+    register: ChunkedRegister,
+}
+
+impl NetworkWallet {
+    /// The key the client storing the wallet must be created with, as the owner of its Register.
+    pub fn client_key(key: &MainKey) -> Result<SecretKey> {
+        Ok(secret_key(key)?.derive_child(OWNER_KEY_INDEX))
+    }
+
+    /// Loads the wallet from the network, creating an empty one there if there is none yet.
+    /// The client must have been created with the wallet's `client_key`.
+    pub async fn load(client: Client, key: MainKey) -> Result<Self> {
+        let owner = Self::client_key(&key)?.public_key();
+        if client.signer_pk() != owner {
+            return Err(Error::NotTheWalletOwner);
+        }
+        let name = XorName::from_content(&owner.to_bytes());
+
+        match ChunkedRegister::retrieve(client.clone(), name, WALLET_REGISTER_TAG).await {
+            Ok(register) => {
+                // Concurrent stores may have left branches, in which case we merge them all,
+                // and the next store writes the merged wallet over them.
+                let mut wallet = KeyLessWallet::new();
+                for (_, entry) in register.read().await? {
+                    wallet.merge(decrypt(&key, &entry)?, &key);
+                }
+                Ok(Self {
+                    key,
+                    wallet,
+                    register,
+                })
+            }
+            Err(ClientError::Protocol(ProtocolError::RegisterNotFound(_))) => {
+                let register =
+                    ChunkedRegister::create_owner_only(client, name, WALLET_REGISTER_TAG).await?;
+                let mut wallet = Self {
+                    key,
+                    wallet: KeyLessWallet::new(),
+                    register,
+                };
+                wallet.store().await?;
+                Ok(wallet)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Stores the wallet to the network.
+    pub async fn store(&mut self) -> Result<()> {
+        let entry = encrypt(&self.key, &self.wallet)?;
+        self.register.write_merging_branches(&entry).await?;
+        Ok(())
+    }
+}
+
+impl Wallet for NetworkWallet {
+    fn address(&self) -> PublicAddress {
+        self.key.public_address()
+    }
+
+    fn balance(&self) -> Token {
+        self.wallet.balance()
+    }
+}
+
+#[async_trait]
+impl DepositWallet for NetworkWallet {
+    fn new_dbc_address(&self) -> DbcIdSource {
+        self.key.random_dbc_id_src(&mut rand::thread_rng())
+    }
+
+    /// The deposit is only kept locally until the wallet is stored.
+    fn deposit(&mut self, dbcs: Vec<Dbc>) {
+        self.wallet.deposit(dbcs, &self.key);
+    }
+
+    /// The wallet is stored to the network along with the deposit.
+    async fn deposit_and_store(&mut self, dbcs: Vec<Dbc>) -> Result<()> {
+        self.deposit(dbcs);
+        self.store().await
+    }
+}
+
+#[async_trait]
+impl SendWallet for NetworkWallet {
+    /// The wallet is stored to the network once the tokens are sent,
+    /// so the spent dbcs are not lost if this machine is.
+    async fn send<C: SendClient>(
+        &mut self,
+        to: Vec<(Token, DbcIdSource)>,
+        client: &C,
+    ) -> Result<(Vec<CreatedDbc>, BTreeMap<DbcId, SpendQueuePosition>)> {
+        let sent = self.wallet.send(&self.key, to, client).await?;
+        self.store().await?;
+        Ok(sent)
+    }
+}
+
+#[async_trait]
+impl ReconcileWallet for NetworkWallet {
+    fn dbc_ids(&self) -> Vec<DbcId> {
+        self.wallet.dbc_ids()
+    }
+
+    async fn reconcile(
+        &mut self,
+        statuses: BTreeMap<DbcId, SpendStatus>,
+    ) -> Result<Reconciliation> {
+        let reconciliation = self.wallet.reconcile(statuses, &self.key);
+        self.store().await?;
+        Ok(reconciliation)
+    }
+}
+
+// The bls secret key of the main key.
+fn secret_key(key: &MainKey) -> Result<SecretKey> {
+    let bytes: [u8; bls::SK_SIZE] = key
+        .to_bytes()
+        .as_slice()
+        .try_into()
+        .map_err(|_| Error::FailedToParseBlsKey)?;
+    Ok(SecretKey::from_bytes(bytes)?)
+}
+
+fn encrypt(key: &MainKey, wallet: &KeyLessWallet) -> Result<Vec<u8>> {
+    let bytes = serialize_wallet(wallet)?;
+    let ciphertext = secret_key(key)?.public_key().encrypt(bytes);
+    Ok(bincode::serialize(&ciphertext)?)
+}
+
+fn decrypt(key: &MainKey, entry: &[u8]) -> Result<KeyLessWallet> {
+    let ciphertext: Ciphertext = bincode::deserialize(entry)?;
+    let bytes = secret_key(key)?
+        .decrypt(&ciphertext)
+        .ok_or(Error::FailedToDecryptWallet)?;
+    // Decrypting with another key doesn't fail as such, but yields bytes which aren't a wallet.
+    deserialize_wallet(&bytes).map_err(|err| match err {
+        Error::Bincode(_) => Error::FailedToDecryptWallet,
+        err => err,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::protocol::dbc_genesis::{create_genesis_dbc, GENESIS_DBC_AMOUNT};
+
+    #[test]
+    fn wallet_entry_is_only_decrypted_with_the_wallet_key() -> eyre::Result<()> {
+        let key = MainKey::random();
+        let mut wallet = KeyLessWallet::new();
+        let genesis = create_genesis_dbc(&key).expect("Genesis creation to succeed.");
+        wallet.deposit(vec![genesis], &key);
+
+        let entry = encrypt(&key, &wallet)?;
+        let decrypted = decrypt(&key, &entry)?;
+        assert_eq!(GENESIS_DBC_AMOUNT, decrypted.balance().as_nano());
+
+        assert!(matches!(
+            decrypt(&MainKey::random(), &entry),
+            Err(Error::FailedToDecryptWallet)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merged_branches_hold_the_dbcs_of_all_with_spent_ones_winning() -> eyre::Result<()> {
+        let key = MainKey::random();
+        let genesis = create_genesis_dbc(&key).expect("Genesis creation to succeed.");
+        let genesis_id = genesis.id();
+
+        // One branch still holds the genesis dbc as available, the other one spent it.
+        let mut available = KeyLessWallet::new();
+        available.deposit(vec![genesis.clone()], &key);
+        let mut spent = KeyLessWallet::new();
+        let _ = spent.spent_dbcs.insert(genesis_id, genesis);
+
+        let mut merged = KeyLessWallet::new();
+        merged.merge(decrypt(&key, &encrypt(&key, &available)?)?, &key);
+        merged.merge(decrypt(&key, &encrypt(&key, &spent)?)?, &key);
+
+        assert!(merged.spent_dbcs.contains_key(&genesis_id));
+        assert!(merged.available_dbcs.is_empty());
+        assert_eq!(Token::zero(), merged.balance());
+        // The deposit recorded in the first branch is kept.
+        assert_eq!(1, merged.ledger().len());
+
+        Ok(())
+    }
+}
@@ -6,31 +6,174 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::{error::Result, KeyLessWallet};
+use super::{
+    encryption::{decrypt, encrypt},
+    error::{Error, Result},
+    KeyLessWallet,
+};
 
-use std::path::Path;
+use crate::protocol::transfers::CreatedDbc;
+
+use sn_dbc::{Dbc, DbcId, Token};
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
 use tokio::fs;
 
 // Filename for storing a wallet.
 const WALLET_FILENAME: &str = "wallet";
+// Filename for storing a wallet encrypted with a passphrase.
+const ENCRYPTED_WALLET_FILENAME: &str = "wallet.encrypted";
+// Serialised wallets start with this, followed by the version of their format, as to tell
+// them from those serialised before the format was versioned, which are of version 0.
+const WALLET_FORMAT_PREFIX: &[u8] = b"safe-wallet";
+// Version 1 added the ledger.
+const WALLET_FORMAT_VERSION: u8 = 1;
+
+/// A wallet as serialised before its format was versioned, without a ledger.
+#[derive(Serialize, Deserialize)]
+struct KeyLessWalletV0 {
+    balance: Token,
+    spent_dbcs: BTreeMap<DbcId, Dbc>,
+    available_dbcs: BTreeMap<DbcId, Dbc>,
+    dbcs_created_for_others: Vec<CreatedDbc>,
+}
+
+impl From<KeyLessWalletV0> for KeyLessWallet {
+    fn from(wallet: KeyLessWalletV0) -> Self {
+        Self {
+            balance: wallet.balance,
+            spent_dbcs: wallet.spent_dbcs,
+            available_dbcs: wallet.available_dbcs,
+            dbcs_created_for_others: wallet.dbcs_created_for_others,
+            // The tokens received and sent were not recorded yet.
+            ledger: vec![],
+        }
+    }
+}
 
 /// Writes the `KeyLessWallet` to the specified path.
-pub(super) async fn store_wallet(root_dir: &Path, wallet: &KeyLessWallet) -> Result<()> {
+/// With a passphrase, the wallet is encrypted with it, and any plain one removed.
+pub(super) async fn store_wallet(
+    root_dir: &Path,
+    wallet: &KeyLessWallet,
+    passphrase: Option<&str>,
+) -> Result<()> {
     let wallet_path = root_dir.join(WALLET_FILENAME);
-    let bytes = bincode::serialize(&wallet)?;
-    fs::write(wallet_path, bytes).await?;
+    let bytes = serialize_wallet(wallet)?;
+    match passphrase {
+        Some(passphrase) => {
+            let encrypted = encrypt(passphrase, &bytes)?;
+            fs::write(root_dir.join(ENCRYPTED_WALLET_FILENAME), encrypted).await?;
+            if wallet_path.is_file() {
+                fs::remove_file(wallet_path).await?;
+            }
+        }
+        None => fs::write(wallet_path, bytes).await?,
+    }
     Ok(())
 }
 
+/// Whether the wallet is stored encrypted with a passphrase.
+pub(super) fn wallet_is_encrypted(root_dir: &Path) -> bool {
+    root_dir.join(ENCRYPTED_WALLET_FILENAME).is_file()
+}
+
 /// Returns `Some(KeyLessWallet)` or None if file doesn't exist.
-pub(super) async fn get_wallet(root_dir: &Path) -> Result<Option<KeyLessWallet>> {
+/// An encrypted wallet takes precedence over a plain one, and requires the passphrase.
+pub(super) async fn get_wallet(
+    root_dir: &Path,
+    passphrase: Option<&str>,
+) -> Result<Option<KeyLessWallet>> {
+    let encrypted_path = root_dir.join(ENCRYPTED_WALLET_FILENAME);
     let path = root_dir.join(WALLET_FILENAME);
-    if !path.is_file() {
+    let bytes = if encrypted_path.is_file() {
+        let passphrase = passphrase.ok_or(Error::WalletLocked)?;
+        decrypt(passphrase, &fs::read(&encrypted_path).await?)?
+    } else if path.is_file() {
+        fs::read(&path).await?
+    } else {
         return Ok(None);
-    }
+    };
 
-    let bytes = fs::read(&path).await?;
-    let wallet = bincode::deserialize(&bytes)?;
+    let wallet = deserialize_wallet(&bytes)?;
 
     Ok(Some(wallet))
 }
+
+/// Serialises the wallet, prefixed with the version of its format.
+pub(super) fn serialize_wallet(wallet: &KeyLessWallet) -> Result<Vec<u8>> {
+    let mut bytes = WALLET_FORMAT_PREFIX.to_vec();
+    bytes.push(WALLET_FORMAT_VERSION);
+    bytes.extend(bincode::serialize(wallet)?);
+    Ok(bytes)
+}
+
+/// Deserialises a wallet of any known format version, migrating it to the current one.
+pub(super) fn deserialize_wallet(bytes: &[u8]) -> Result<KeyLessWallet> {
+    let versioned = match bytes.strip_prefix(WALLET_FORMAT_PREFIX) {
+        Some(versioned) => versioned,
+        None => {
+            let wallet: KeyLessWalletV0 = bincode::deserialize(bytes)?;
+            return Ok(wallet.into());
+        }
+    };
+    match versioned.split_first() {
+        Some((&WALLET_FORMAT_VERSION, wallet)) => Ok(bincode::deserialize(wallet)?),
+        Some((version, _)) => Err(Error::UnsupportedWalletVersion(*version)),
+        None => Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::protocol::dbc_genesis::{create_genesis_dbc, GENESIS_DBC_AMOUNT};
+
+    use eyre::{eyre, Result};
+    use sn_dbc::MainKey;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn wallet_serialised_without_ledger_is_loaded() -> Result<()> {
+        let key = MainKey::random();
+        let genesis = create_genesis_dbc(&key).expect("Genesis creation to succeed.");
+        let genesis_id = genesis.id();
+        let v0 = KeyLessWalletV0 {
+            balance: Token::from_nano(GENESIS_DBC_AMOUNT),
+            spent_dbcs: BTreeMap::new(),
+            available_dbcs: BTreeMap::from([(genesis_id, genesis)]),
+            dbcs_created_for_others: vec![],
+        };
+
+        let dir = tempdir().map_err(|e| eyre!("Failed to create temp dir: {}", e))?;
+        fs::write(dir.path().join(WALLET_FILENAME), bincode::serialize(&v0)?).await?;
+
+        let wallet = get_wallet(dir.path(), None)
+            .await?
+            .ok_or_else(|| eyre!("There to be a wallet."))?;
+        assert_eq!(GENESIS_DBC_AMOUNT, wallet.balance().as_nano());
+        assert!(wallet.available_dbcs.contains_key(&genesis_id));
+        assert!(wallet.ledger().is_empty());
+
+        // It's stored in the current format from then on.
+        store_wallet(dir.path(), &wallet, None).await?;
+        let bytes = fs::read(dir.path().join(WALLET_FILENAME)).await?;
+        assert!(bytes.starts_with(WALLET_FORMAT_PREFIX));
+        let reloaded = deserialize_wallet(&bytes)?;
+        assert_eq!(GENESIS_DBC_AMOUNT, reloaded.balance().as_nano());
+
+        Ok(())
+    }
+
+    #[test]
+    fn wallet_of_unknown_version_is_rejected() {
+        let mut bytes = WALLET_FORMAT_PREFIX.to_vec();
+        bytes.push(WALLET_FORMAT_VERSION + 1);
+        assert!(matches!(
+            deserialize_wallet(&bytes),
+            Err(Error::UnsupportedWalletVersion(version)) if version == WALLET_FORMAT_VERSION + 1
+        ));
+    }
+}
@@ -65,8 +65,8 @@ impl ChunkStorage {
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub(super) async fn addrs(&self) -> Vec<ChunkAddress> {
+    /// Addresses of all the chunks held.
+    pub(crate) async fn addrs(&self) -> Vec<ChunkAddress> {
         self.cache
             .read()
             .await
@@ -9,29 +9,48 @@
 use crate::protocol::{
     address::RegisterAddress,
     error::{Error, Result},
-    messages::RegisterCmd,
+    messages::{RegisterCmd, RegisterSnapshot},
     register::Register,
 };
 
 use clru::CLruCache;
 use std::{num::NonZeroUsize, sync::Arc};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tracing::trace;
 
 const REGISTERS_CACHE_SIZE: usize = 20 * 1024 * 1024;
 
 pub(super) type RegisterLog = Vec<RegisterCmd>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub(super) struct StoredRegister {
+    // Current state of the Register, with all ops from the snapshot and log applied.
     pub(super) state: Option<Register>,
+    // Snapshot the older ops have been compacted into, if any.
+    pub(super) snapshot: Option<RegisterSnapshot>,
+    // Ops applied after the snapshot, or since creation if there is no snapshot yet.
     pub(super) op_log: RegisterLog,
+    // Edit cmds which cannot be applied yet, since either the Register
+    // hasn't been created or they were written atop of entries we don't hold yet.
+    pub(super) pending: RegisterLog,
 }
 
-/// A store for Registers
+impl StoredRegister {
+    /// Whether nothing is held for the Register, not even cmds waiting for their dependencies.
+    pub(super) fn is_empty(&self) -> bool {
+        self.state.is_none()
+            && self.snapshot.is_none()
+            && self.op_log.is_empty()
+            && self.pending.is_empty()
+    }
+}
+
+/// A store for Registers.
+/// Each Register is behind its own lock, held while it's read and modified, so that
+/// it's modified in place, and concurrent writes to different Registers don't wait on each other.
 #[derive(Clone)]
 pub(super) struct RegisterStore {
-    cache: Arc<RwLock<CLruCache<RegisterAddress, StoredRegister>>>,
+    cache: Arc<RwLock<CLruCache<RegisterAddress, Arc<Mutex<StoredRegister>>>>>,
 }
 
 impl Default for RegisterStore {
@@ -45,7 +64,6 @@ impl Default for RegisterStore {
 }
 
 impl RegisterStore {
-    #[cfg(test)]
     pub(super) async fn addrs(&self) -> Vec<RegisterAddress> {
         self.cache
             .read()
@@ -65,41 +83,42 @@ impl RegisterStore {
         }
     }
 
-    /// Opens the log of RegisterCmds for a given Register address.
-    /// Creates a new log if no data is found
-    pub(super) async fn get(&self, address: &RegisterAddress) -> StoredRegister {
+    /// Returns the Register stored at the address, if any.
+    pub(super) async fn get(
+        &self,
+        address: &RegisterAddress,
+    ) -> Option<Arc<Mutex<StoredRegister>>> {
         trace!("Getting Register ops log: {address:?}");
-        if let Some(stored_reg) = self.cache.read().await.peek(address) {
-            stored_reg.clone()
-        } else {
-            StoredRegister {
-                state: None,
-                op_log: RegisterLog::new(),
-            }
-        }
+        self.cache.read().await.peek(address).cloned()
     }
 
-    /// Persists a RegisterLog
-    pub(super) async fn store_register_ops_log(
+    /// Returns the Register stored at the address, storing an empty one if there is none.
+    /// Its lock is to be held from reading it till it's been modified, so no write is lost.
+    pub(super) async fn get_or_create(
         &self,
-        _log: &RegisterLog, // we'll need to write these ops to disk when disk store is implemented
-        reg: StoredRegister,
-        address: RegisterAddress,
-    ) -> Result<()> {
-        let log_len = reg.op_log.len();
-        trace!("Storing Register ops log with {log_len} cmd/s: {address:?}",);
-
-        let _ = self.cache.write().await.try_put_or_modify(
-            address,
-            |_, _| Ok::<StoredRegister, Error>(reg.clone()),
-            |_, stored_reg, _| {
-                *stored_reg = reg.clone();
-                Ok(())
-            },
-            (),
-        )?;
+        address: &RegisterAddress,
+    ) -> Arc<Mutex<StoredRegister>> {
+        let mut cache = self.cache.write().await;
+        if let Some(stored_reg) = cache.get(address) {
+            return stored_reg.clone();
+        }
+        let stored_reg = Arc::new(Mutex::new(StoredRegister::default()));
+        let _ = cache.put(*address, stored_reg.clone());
+        stored_reg
+    }
 
-        trace!("Register ops log of {log_len} cmd/s stored successfully: {address:?}",);
-        Ok(())
+    /// Removes the Register stored at the address if nothing is held for it,
+    /// e.g. when the first cmd received for it was invalid.
+    pub(super) async fn remove_if_empty(&self, address: &RegisterAddress) {
+        let mut cache = self.cache.write().await;
+        let empty = match cache.peek(address).map(|stored_reg| stored_reg.try_lock()) {
+            Some(Ok(stored_reg)) => stored_reg.is_empty(),
+            // It's being modified, so it may not be empty anymore.
+            Some(Err(_)) | None => false,
+        };
+        if empty {
+            trace!("Removing empty Register: {address:?}");
+            let _ = cache.pop(address);
+        }
     }
 }
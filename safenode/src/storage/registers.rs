@@ -12,14 +12,20 @@ use crate::protocol::{
     address::RegisterAddress,
     error::{Error, Result},
     messages::{
-        EditRegister, QueryResponse, RegisterCmd, RegisterQuery, ReplicatedRegisterLog,
+        QueryResponse, RegisterCmd, RegisterQuery, RegisterSnapshot, ReplicatedRegisterLog,
         SignedRegisterCreate, SignedRegisterEdit,
     },
-    register::{Action, EntryHash, Register, User},
+    register::{Action, EntryHash, Register, User, MAX_REG_NUM_ENTRIES},
 };
 
 use bincode::serialize;
 
+// Maximum number of edit cmds buffered per Register while waiting for their dependencies.
+const MAX_PENDING_CMDS: usize = MAX_REG_NUM_ENTRIES as usize;
+
+// Number of cmds in a Register's ops log after which they are compacted into a snapshot.
+const SNAPSHOT_INTERVAL: usize = 128;
+
 /// Operations over the Register data type and its storage.
 #[derive(Clone, Default)]
 pub(crate) struct RegisterStorage {
@@ -39,40 +45,83 @@ impl RegisterStorage {
     pub(crate) async fn write(&self, cmd: &RegisterCmd) -> Result<()> {
         info!("Writing register cmd: {cmd:?}");
         let addr = cmd.dst();
-        // Let's first try to load and reconstruct the replica of targetted Register
-        // we have in local storage, to then try to apply the new command onto it.
-        let mut stored_reg = self.try_load_stored_register(&addr).await?;
-
-        self.try_to_apply_cmd_against_register_state(cmd, &mut stored_reg)?;
+        // Let's first load the replica of targetted Register we have in local storage,
+        // to then try to apply the new command onto it, in place.
+        let stored_reg = self.register_store.get_or_create(&addr).await;
+        let mut stored_reg = stored_reg.lock().await;
+
+        let result = self.try_to_apply_cmd_against_register_state(cmd, &mut stored_reg);
+        if result.is_ok() {
+            self.compact_if_needed(&mut stored_reg);
+        }
 
-        // Everything went fine, let's store the updated Register
-        self.register_store
-            .store_register_ops_log(&vec![cmd.clone()], stored_reg, addr)
-            .await
+        let empty = stored_reg.is_empty();
+        drop(stored_reg);
+        if empty {
+            self.register_store.remove_if_empty(&addr).await;
+        }
+        result
     }
 
     /// Update our Register's replica on receiving data from other nodes.
-    #[allow(dead_code)]
-    pub(super) async fn update(&self, data: &ReplicatedRegisterLog) -> Result<()> {
+    /// The snapshot, if any, is verified against the signed cmd which created the Register before
+    /// its state is merged into ours, and every cmd of the ops log is verified before being applied.
+    pub(crate) async fn update(&self, data: &ReplicatedRegisterLog) -> Result<()> {
         let addr = data.address;
         debug!("Updating Register store: {addr:?}");
-        let mut stored_reg = self.try_load_stored_register(&addr).await?;
+        let stored_reg = self.register_store.get_or_create(&addr).await;
+        let mut stored_reg = stored_reg.lock().await;
+
+        if let Some(snapshot) = &data.snapshot {
+            if let Err(err) = self.merge_snapshot(addr, snapshot, &mut stored_reg) {
+                warn!("Discarding ReplicatedRegisterLog snapshot of {addr:?}: {err:?}");
+            }
+        }
 
-        let mut log_to_write = Vec::new();
         for replicated_cmd in &data.op_log {
-            if let Err(err) =
+            let result = if replicated_cmd.dst() != addr {
+                Err(Error::RegisterAddrMismatch {
+                    cmd_dst_addr: replicated_cmd.dst(),
+                    reg_addr: addr,
+                })
+            } else {
                 self.try_to_apply_cmd_against_register_state(replicated_cmd, &mut stored_reg)
-            {
+            };
+            if let Err(err) = result {
                 warn!("Discarding ReplicatedRegisterLog cmd {replicated_cmd:?}: {err:?}",);
-            } else {
-                log_to_write.push(replicated_cmd.clone());
             }
         }
+        self.compact_if_needed(&mut stored_reg);
 
-        // Write the new cmds all to disk
-        self.register_store
-            .store_register_ops_log(&log_to_write, stored_reg, addr)
-            .await
+        let empty = stored_reg.is_empty();
+        drop(stored_reg);
+        if empty {
+            self.register_store.remove_if_empty(&addr).await;
+        }
+        Ok(())
+    }
+
+    /// Returns true if there are edit cmds for the Register waiting for ops we don't hold yet,
+    /// either its `Create` cmd or the entries they were written atop of.
+    pub(crate) async fn has_pending_cmds(&self, addr: &RegisterAddress) -> bool {
+        match self.register_store.get(addr).await {
+            Some(stored_reg) => !stored_reg.lock().await.pending.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Op logs of all the Registers held, as to be replicated to other nodes.
+    pub(crate) async fn replicated_logs(&self) -> Vec<ReplicatedRegisterLog> {
+        let mut logs = Vec::new();
+        for address in self.register_store.addrs().await {
+            match self.get_register_replica(&address).await {
+                // Registers we only hold cmds waiting for their dependencies of aren't replicated
+                Ok(log) if log.snapshot.is_some() || !log.op_log.is_empty() => logs.push(log),
+                Ok(_) => {}
+                Err(err) => warn!("Failed to load Register {address:?} to replicate it: {err}"),
+            }
+        }
+        logs
     }
 
     /// --- Reading ---
@@ -83,6 +132,7 @@ impl RegisterStorage {
             Get(address) => self.get(*address, requester).await,
             Read(address) => self.read_register(*address, requester).await,
             GetOwner(address) => self.get_owner(*address, requester).await,
+            GetOpLog(address) => self.get_op_log(*address, requester).await,
             GetEntry { address, hash } => self.get_entry(*address, *hash, requester).await,
             GetPolicy(address) => self.get_policy(*address, requester).await,
             GetUserPermissions { address, user } => {
@@ -98,13 +148,17 @@ impl RegisterStorage {
         action: Action,
         requester: User,
     ) -> Result<Register> {
-        let stored_reg = self.try_load_stored_register(address).await?;
-        if let Some(register) = stored_reg.state {
+        let stored_reg = match self.register_store.get(address).await {
+            Some(stored_reg) => stored_reg,
+            None => return Err(Error::RegisterNotFound(*address)),
+        };
+        let stored_reg = stored_reg.lock().await;
+        if let Some(register) = &stored_reg.state {
             register
                 .check_permissions(action, Some(requester))
                 .map_err(Error::from)?;
 
-            Ok(register)
+            Ok(register.clone())
         } else {
             Err(Error::RegisterNotFound(*address))
         }
@@ -172,6 +226,15 @@ impl RegisterStorage {
         QueryResponse::GetRegisterPolicy(result)
     }
 
+    async fn get_op_log(&self, address: RegisterAddress, requester: User) -> QueryResponse {
+        let result = match self.get_register(&address, Action::Read, requester).await {
+            Ok(_) => self.get_register_replica(&address).await,
+            Err(error) => Err(error),
+        };
+
+        QueryResponse::GetRegisterOpLog(result)
+    }
+
     // ========================================================================
     // =========================== Helpers ====================================
     // ========================================================================
@@ -179,45 +242,197 @@ impl RegisterStorage {
     // Private helper which does all verification and tries to apply given cmd to given Register
     // state. It accumulates the cmd, if valid, into the log so further calls can be made with
     // the same state and log, as used by the `update` function.
-    // Note the cmd is always pushed to the log even if it's a duplicated cmd.
+    // Edit cmds which cannot be applied yet, since the Register hasn't been created or they were
+    // written atop of entries we don't hold, are buffered till their dependencies are satisfied.
+    // Edit cmds already applied, e.g. when replicated back to us, are not pushed to the log again.
     fn try_to_apply_cmd_against_register_state(
         &self,
         cmd: &RegisterCmd,
         stored_reg: &mut StoredRegister,
     ) -> Result<()> {
-        // If we have the target Register, try to apply the cmd, otherwise let's keep
-        // the cmd in the log anyway, whenever we receive the 'Register create' cmd
-        // it can be reconstructed from all cmds we hold in the log. If this is a 'Register create'
-        // cmd let's verify it's valid before accepting it, however 'Edits cmds' cannot be
-        // verified until we have the `Register create` cmd.
         match (stored_reg.state.as_mut(), cmd) {
             (Some(_), RegisterCmd::Create { .. }) => return Ok(()), // no op, since already created
-            (Some(ref mut register), RegisterCmd::Edit(_)) => self.apply(cmd, register)?,
+            (Some(ref mut register), RegisterCmd::Edit(SignedRegisterEdit { op, .. })) => {
+                if Self::is_applied(cmd, register) {
+                    trace!(
+                        "Skipping Register edit cmd already applied: {:?}",
+                        cmd.dst()
+                    );
+                    return Ok(());
+                }
+                let missing = register.missing_deps(&op.edit);
+                if !missing.is_empty() {
+                    trace!(
+                        "Buffering Register edit cmd missing entries {missing:?}: {addr:?}",
+                        addr = cmd.dst()
+                    );
+                    return self.buffer_pending_cmd(cmd, stored_reg);
+                }
+                self.apply(cmd, register)?;
+            }
             (None, RegisterCmd::Create(cmd)) => {
                 // the target Register is not in our store or we don't have the 'Register create',
-                // let's verify the create cmd we received is valid and try to apply stored cmds we may have.
+                // let's verify the create cmd we received is valid before accepting it.
                 let SignedRegisterCreate { op, auth } = cmd;
                 auth.verify_authority(serialize(op).map_err(|e| Error::Bincode(e.to_string()))?)?;
 
                 trace!("Creating new register: {:?}", cmd.dst());
-                // let's do a final check, let's try to apply all cmds to it,
-                // those which are new cmds were not validated yet, so let's do it now.
-                let mut register =
+                let register =
                     Register::new(*op.policy.owner(), op.name, op.tag, op.policy.clone());
+                stored_reg.state = Some(register);
+            }
+            (None, RegisterCmd::Edit(_)) => {
+                // we cannot validate it till we have the 'Register create' cmd
+                return self.buffer_pending_cmd(cmd, stored_reg);
+            }
+        }
 
-                for cmd in &stored_reg.op_log {
-                    self.apply(cmd, &mut register)?;
+        stored_reg.op_log.push(cmd.clone());
+        // the cmd may have been the missing dependency of some buffered cmds
+        self.apply_pending_cmds(stored_reg);
+        Ok(())
+    }
+
+    // Buffer an edit cmd till its dependencies are satisfied, verifying its signature
+    // since this is the only validation which can be done without the Register state.
+    fn buffer_pending_cmd(&self, cmd: &RegisterCmd, stored_reg: &mut StoredRegister) -> Result<()> {
+        if let RegisterCmd::Edit(SignedRegisterEdit { op, auth }) = cmd {
+            auth.verify_authority(serialize(op).map_err(|e| Error::Bincode(e.to_string()))?)?;
+        }
+
+        if stored_reg.pending.contains(cmd) {
+            return Ok(());
+        }
+        if stored_reg.pending.len() >= MAX_PENDING_CMDS {
+            return Err(Error::TooManyEntries(MAX_PENDING_CMDS));
+        }
+
+        stored_reg.pending.push(cmd.clone());
+        Ok(())
+    }
+
+    // Apply all buffered cmds whose dependencies are now satisfied, till no more can be applied.
+    fn apply_pending_cmds(&self, stored_reg: &mut StoredRegister) {
+        let register = match stored_reg.state.as_mut() {
+            Some(register) => register,
+            None => return,
+        };
+
+        let mut applied_any = true;
+        while applied_any {
+            applied_any = false;
+            for cmd in std::mem::take(&mut stored_reg.pending) {
+                let ready = match &cmd {
+                    RegisterCmd::Edit(SignedRegisterEdit { op, .. }) => {
+                        register.missing_deps(&op.edit).is_empty()
+                    }
+                    RegisterCmd::Create(_) => true,
+                };
+
+                if !ready {
+                    stored_reg.pending.push(cmd);
+                } else if Self::is_applied(&cmd, register) {
+                    trace!(
+                        "Dropping buffered Register cmd already applied: {:?}",
+                        cmd.dst()
+                    );
+                } else if let Err(err) = self.apply(&cmd, register) {
+                    warn!("Discarding buffered Register cmd {cmd:?}: {err:?}");
+                } else {
+                    stored_reg.op_log.push(cmd);
+                    applied_any = true;
                 }
+            }
+        }
+    }
 
-                stored_reg.state = Some(register);
+    // Whether the edit of the cmd is already held by the Register.
+    fn is_applied(cmd: &RegisterCmd, register: &Register) -> bool {
+        match cmd {
+            RegisterCmd::Edit(SignedRegisterEdit { op, .. }) => {
+                register.get(EntryHash(op.edit.crdt_op.hash())).is_ok()
             }
-            (None, _edit_cmd) => { /* we cannot validate it right now, but we'll store it */ }
+            RegisterCmd::Create(_) => true,
         }
+    }
 
-        stored_reg.op_log.push(cmd.clone());
+    // Verify a snapshot received from another replica, and merge its state into ours.
+    // Our own snapshot is then replaced by one of the merged state, dropping our ops log,
+    // since all the ops in it are covered by that state.
+    fn merge_snapshot(
+        &self,
+        addr: RegisterAddress,
+        snapshot: &RegisterSnapshot,
+        stored_reg: &mut StoredRegister,
+    ) -> Result<()> {
+        let RegisterSnapshot { create, register } = snapshot;
+        if create.dst() != addr {
+            return Err(Error::RegisterAddrMismatch {
+                cmd_dst_addr: create.dst(),
+                reg_addr: addr,
+            });
+        }
+        let SignedRegisterCreate { op, auth } = create;
+        auth.verify_authority(serialize(op).map_err(|e| Error::Bincode(e.to_string()))?)?;
+
+        let mismatch = |reg: &Register| {
+            reg.address() != &addr
+                || reg.replica_authority() != op.owner()
+                || reg.policy() != &op.policy
+        };
+        if mismatch(register) || matches!(&stored_reg.state, Some(state) if mismatch(state)) {
+            return Err(Error::RegisterSnapshotMismatch(addr));
+        }
+        if register.size() > MAX_REG_NUM_ENTRIES.into() {
+            return Err(Error::TooManyEntries(register.size() as usize));
+        }
+
+        trace!("Merging snapshot into Register: {addr:?}");
+        let state = stored_reg
+            .state
+            .get_or_insert_with(|| Register::new(op.owner(), op.name, op.tag, op.policy.clone()));
+        state.merge(register.clone());
+        stored_reg.snapshot = Some(RegisterSnapshot {
+            create: create.clone(),
+            register: state.clone(),
+        });
+        stored_reg.op_log.clear();
+        // the snapshot may have held the missing dependencies of some buffered cmds
+        self.apply_pending_cmds(stored_reg);
         Ok(())
     }
 
+    // Compact the ops log into a snapshot of the Register state once it grows long enough,
+    // dropping the cmds the state covers.
+    fn compact_if_needed(&self, stored_reg: &mut StoredRegister) {
+        if stored_reg.op_log.len() < SNAPSHOT_INTERVAL {
+            return;
+        }
+
+        let create = stored_reg
+            .snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.create.clone())
+            .or_else(|| {
+                stored_reg.op_log.iter().find_map(|cmd| match cmd {
+                    RegisterCmd::Create(create) => Some(create.clone()),
+                    RegisterCmd::Edit(_) => None,
+                })
+            });
+        let (create, register) = match (create, &stored_reg.state) {
+            (Some(create), Some(register)) => (create, register.clone()),
+            _ => return,
+        };
+
+        trace!(
+            "Compacted {} cmds into a snapshot of Register {:?}",
+            stored_reg.op_log.len(),
+            create.dst()
+        );
+        stored_reg.op_log.clear();
+        stored_reg.snapshot = Some(RegisterSnapshot { create, register });
+    }
+
     // Try to apply the provided cmd to the register state, performing all op validations
     fn apply(&self, cmd: &RegisterCmd, register: &mut Register) -> Result<()> {
         let addr = cmd.dst();
@@ -252,22 +467,13 @@ impl RegisterStorage {
         }
     }
 
-    // Gets stored register log from disk, trying to reconstruct the Register
-    // Note this doesn't perform any cmd sig/perms validation, it's only used when the log
-    // is read from disk which has already been validated before storing it.
+    // Gets a copy of the stored register, its state has all the ops from its snapshot and log applied.
+    #[cfg(test)]
     async fn try_load_stored_register(&self, addr: &RegisterAddress) -> Result<StoredRegister> {
-        let mut stored_reg = self.register_store.get(addr).await;
-        // if we have the Register creation cmd, apply all ops to reconstruct the Register
-        if let Some(register) = &mut stored_reg.state {
-            for cmd in &stored_reg.op_log {
-                if let RegisterCmd::Edit(SignedRegisterEdit { op, .. }) = cmd {
-                    let EditRegister { edit, .. } = op;
-                    register.apply_op(edit.clone())?;
-                }
-            }
-        }
-
-        Ok(stored_reg)
+        Ok(match self.register_store.get(addr).await {
+            Some(stored_reg) => stored_reg.lock().await.clone(),
+            None => StoredRegister::default(),
+        })
     }
 
     #[cfg(test)]
@@ -275,35 +481,37 @@ impl RegisterStorage {
         self.register_store.addrs().await
     }
 
-    /// Used for replication of data to new nodes.
-    /// Currently only used by the tests.
-    /// TODO: to be used by replication logic.
-    #[cfg(test)]
+    /// Used for replication of data to other nodes.
     async fn get_register_replica(
         &self,
         address: &RegisterAddress,
     ) -> Result<ReplicatedRegisterLog> {
-        let stored_reg = self.try_load_stored_register(address).await?;
+        let stored_reg = match self.register_store.get(address).await {
+            Some(stored_reg) => stored_reg,
+            None => return Err(Error::RegisterNotFound(*address)),
+        };
+        let stored_reg = stored_reg.lock().await;
         // Build the replicated register log assuming ops stored are all valid and correctly
         // signed since we performed such validations before storing them.
         Ok(ReplicatedRegisterLog {
             address: *address,
-            op_log: stored_reg.op_log,
+            snapshot: stored_reg.snapshot.clone(),
+            op_log: stored_reg.op_log.clone(),
         })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::RegisterStorage;
+    use super::{RegisterStorage, SNAPSHOT_INTERVAL};
     use crate::protocol::{
         authority::DataAuthority,
         error::Error,
         messages::{
             CreateRegister, EditRegister, QueryResponse, RegisterCmd, RegisterQuery,
-            SignedRegisterCreate, SignedRegisterEdit,
+            RegisterSnapshot, ReplicatedRegisterLog, SignedRegisterCreate, SignedRegisterEdit,
         },
-        register::{EntryHash, Policy, Register, User},
+        register::{EntryHash, Permissions, Policy, Register, User},
     };
 
     use bincode::serialize;
@@ -385,9 +593,11 @@ mod test {
         store.write(&cmd_edit).await?;
 
         let stored_reg = store.try_load_stored_register(&addr).await?;
-        // it should contain the edit cmd only
+        // it should hold the edit cmd only, buffered till the create cmd is received
         assert_eq!(stored_reg.state, None);
-        assert_eq!(stored_reg.op_log, vec![cmd_edit.clone()]);
+        assert!(stored_reg.op_log.is_empty());
+        assert_eq!(stored_reg.pending, vec![cmd_edit.clone()]);
+        assert!(store.has_pending_cmds(&addr).await);
 
         // and now store the create cmd for the register
         store.write(&cmd_create).await?;
@@ -449,10 +659,10 @@ mod test {
         assert_eq!(stored_reg.state.as_ref().map(|reg| reg.size()), Some(1));
 
         // applying the edit cmd again shouldn't fail or alter the register content,
-        // although the log will contain the edit cmd duplicated
+        // nor push the edit cmd to the log again
         store.try_to_apply_cmd_against_register_state(&cmd_edit, &mut stored_reg)?;
         assert_eq!(stored_reg.state.as_ref(), Some(&register));
-        assert_eq!(stored_reg.op_log.len(), 3);
+        assert_eq!(stored_reg.op_log.len(), 2);
         assert!(
             stored_reg
                 .op_log
@@ -477,25 +687,21 @@ mod test {
         // apply an edit cmd first
         let cmd_edit = edit_register(&mut register, &sk)?;
         store.try_to_apply_cmd_against_register_state(&cmd_edit, &mut stored_reg)?;
-        // it should contain the edit cmd
+        // it should be buffered till the create cmd is applied
         assert_eq!(stored_reg.state, None);
-        assert_eq!(stored_reg.op_log, vec![cmd_edit.clone()]);
+        assert!(stored_reg.op_log.is_empty());
+        assert_eq!(stored_reg.pending, vec![cmd_edit.clone()]);
 
-        // applying the edit cmd again shouldn't fail,
-        // although the log will contain the edit cmd duplicated
+        // applying the edit cmd again shouldn't fail nor buffer it twice
         store.try_to_apply_cmd_against_register_state(&cmd_edit, &mut stored_reg)?;
         assert_eq!(stored_reg.state, None);
-        assert_eq!(stored_reg.op_log.len(), 2);
-        assert!(
-            stored_reg.op_log.iter().all(|op| op == &cmd_edit),
-            "Op log doesn't match"
-        );
+        assert_eq!(stored_reg.pending, vec![cmd_edit.clone()]);
 
         // let's apply the create cmd now
         store.try_to_apply_cmd_against_register_state(&cmd_create, &mut stored_reg)?;
         // it should contain the create and edit cmds
         assert_eq!(stored_reg.state.as_ref(), Some(&register));
-        assert_eq!(stored_reg.op_log.len(), 3);
+        assert_eq!(stored_reg.op_log.len(), 2);
         assert!(
             stored_reg
                 .op_log
@@ -503,6 +709,7 @@ mod test {
                 .all(|op| [&cmd_create, &cmd_edit].contains(&op)),
             "Op log doesn't match"
         );
+        assert!(stored_reg.pending.is_empty());
         assert_eq!(stored_reg.state.as_ref().map(|reg| reg.size()), Some(1));
 
         // apply the create cmd again should change nothing
@@ -517,6 +724,165 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_register_edit_with_missing_parents_is_buffered() -> Result<()> {
+        let store = RegisterStorage::default();
+
+        let (cmd_create, _, sk, name, policy) = create_register()?;
+        let addr = cmd_create.dst();
+        let mut register = Register::new(*policy.owner(), name, 0, policy);
+        store.write(&cmd_create).await?;
+
+        // write an edit atop of another one the store doesn't hold yet
+        let (parent_hash, cmd_parent) = edit_register_atop(&mut register, &sk, BTreeSet::new())?;
+        let (_, cmd_child) = edit_register_atop(&mut register, &sk, [parent_hash].into())?;
+        store.write(&cmd_child).await?;
+
+        let stored_reg = store.try_load_stored_register(&addr).await?;
+        assert_eq!(stored_reg.op_log, vec![cmd_create.clone()]);
+        assert_eq!(stored_reg.pending, vec![cmd_child.clone()]);
+        assert_eq!(stored_reg.state.as_ref().map(|reg| reg.size()), Some(0));
+        assert!(store.has_pending_cmds(&addr).await);
+
+        // once the parent is received, both edits are applied
+        store.write(&cmd_parent).await?;
+
+        let stored_reg = store.try_load_stored_register(&addr).await?;
+        assert_eq!(
+            stored_reg.op_log,
+            vec![cmd_create.clone(), cmd_parent, cmd_child]
+        );
+        assert!(stored_reg.pending.is_empty());
+        assert_eq!(stored_reg.state.as_ref(), Some(&register));
+        assert!(!store.has_pending_cmds(&addr).await);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_ops_log_is_compacted_into_snapshot() -> Result<()> {
+        let store = RegisterStorage::default();
+
+        let (cmd_create, authority, sk, name, policy) = create_register()?;
+        let addr = cmd_create.dst();
+        let mut register = Register::new(*policy.owner(), name, 0, policy);
+
+        // store the register along with enough edit ops to trigger a compaction,
+        // the create cmd and the first edit cmds fill the log, then 10 more are made
+        store.write(&cmd_create).await?;
+        for _ in 0..SNAPSHOT_INTERVAL - 1 + 10 {
+            let cmd_edit = edit_register(&mut register, &sk)?;
+            store.write(&cmd_edit).await?;
+        }
+
+        let stored_reg = store.try_load_stored_register(&addr).await?;
+        assert!(stored_reg.op_log.len() < SNAPSHOT_INTERVAL);
+        assert_eq!(stored_reg.state.as_ref(), Some(&register));
+
+        // the replica shipped to other nodes is the snapshot plus the ops made after it,
+        // the snapshot holding the state of the Register instead of the cmds it covers
+        let replica = store.get_register_replica(&addr).await?;
+        assert_eq!(replica.op_log.len(), 10);
+        match &replica.snapshot {
+            Some(snapshot) => assert_eq!(snapshot.register.size(), SNAPSHOT_INTERVAL as u64 - 1),
+            None => bail!("Expected the ops log to be compacted into a snapshot"),
+        }
+
+        let new_store = RegisterStorage::default();
+        new_store.update(&replica).await?;
+        match new_store.read(&RegisterQuery::Get(addr), authority).await {
+            QueryResponse::GetRegister(Ok(reg)) => assert_eq!(reg, register),
+            e => bail!("Could not read register! {:?}", e),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_snapshot_must_match_its_create_cmd() -> Result<()> {
+        let store = RegisterStorage::default();
+
+        let (cmd_create, authority, sk, name, policy) = create_register()?;
+        let addr = cmd_create.dst();
+        let create = match cmd_create {
+            RegisterCmd::Create(create) => create,
+            RegisterCmd::Edit(_) => bail!("Expected a create cmd"),
+        };
+
+        // a Register state whose policy grants another user write permissions,
+        // sent along with the create cmd signed by the owner
+        let (other_user, other_sk) = random_user();
+        let mut forged_policy = policy;
+        let _ = forged_policy
+            .permissions
+            .insert(other_user, Permissions::new(true));
+        let mut forged = Register::new(authority, name, 0, forged_policy);
+        let _ = edit_register(&mut forged, &other_sk)?;
+        let _ = edit_register(&mut forged, &sk)?;
+
+        let replica = ReplicatedRegisterLog {
+            address: addr,
+            snapshot: Some(RegisterSnapshot {
+                create,
+                register: forged,
+            }),
+            op_log: vec![],
+        };
+        store.update(&replica).await?;
+
+        // the snapshot was discarded
+        match store.read(&RegisterQuery::Get(addr), authority).await {
+            QueryResponse::GetRegister(Err(Error::RegisterNotFound(_))) => {}
+            e => bail!("Register should not have been stored! {:?}", e),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_replication_does_not_grow_the_log() -> Result<()> {
+        let store = RegisterStorage::default();
+
+        let (cmd_create, authority, sk, name, policy) = create_register()?;
+        let addr = cmd_create.dst();
+        let mut register = Register::new(*policy.owner(), name, 0, policy);
+        store.write(&cmd_create).await?;
+        for _ in 0..3 {
+            let cmd_edit = edit_register(&mut register, &sk)?;
+            store.write(&cmd_edit).await?;
+        }
+
+        // the replica being sent back to us, by as many nodes as there are in the close group
+        let replica = store.get_register_replica(&addr).await?;
+        for _ in 0..8 {
+            store.update(&replica).await?;
+        }
+        let stored_reg = store.try_load_stored_register(&addr).await?;
+        assert_eq!(stored_reg.op_log.len(), 4);
+
+        // nor does it when the replica holds a snapshot, its state being merged into ours
+        let other_store = RegisterStorage::default();
+        other_store.write(&cmd_create).await?;
+        for _ in 0..SNAPSHOT_INTERVAL - 1 {
+            let cmd_edit = edit_register(&mut register, &sk)?;
+            other_store.write(&cmd_edit).await?;
+        }
+        let replica = other_store.get_register_replica(&addr).await?;
+        assert!(replica.snapshot.is_some());
+        for _ in 0..8 {
+            store.update(&replica).await?;
+        }
+        let stored_reg = store.try_load_stored_register(&addr).await?;
+        assert!(stored_reg.op_log.is_empty());
+
+        match store.read(&RegisterQuery::Get(addr), authority).await {
+            QueryResponse::GetRegister(Ok(reg)) => assert_eq!(reg, register),
+            e => bail!("Could not read register! {:?}", e),
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_register_write() -> Result<()> {
         // setup store
@@ -546,6 +912,37 @@ mod test {
         }
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_register_concurrent_writes_are_all_applied() -> Result<()> {
+        let store = RegisterStorage::default();
+        let (cmd_create, authority, sk, name, policy) = create_register()?;
+        let address = cmd_create.dst();
+        let mut register = Register::new(*policy.owner(), name, 0, policy);
+        store.write(&cmd_create).await?;
+
+        let edits = (0..64)
+            .map(|_| edit_register(&mut register, &sk))
+            .collect::<Result<Vec<_>>>()?;
+        let handles: Vec<_> = edits
+            .into_iter()
+            .map(|cmd| {
+                let store = store.clone();
+                tokio::spawn(async move { store.write(&cmd).await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await??;
+        }
+
+        // None of the concurrent edits was lost.
+        match store.read(&RegisterQuery::Get(address), authority).await {
+            QueryResponse::GetRegister(Ok(reg)) => assert_eq!(reg.size(), 64),
+            e => bail!("Could not read register! {:?}", e),
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_register_export() -> Result<()> {
         // setup store
@@ -685,23 +1082,34 @@ mod test {
     }
 
     fn edit_register(register: &mut Register, sk: &SecretKey) -> Result<RegisterCmd> {
+        let (_, cmd) = edit_register_atop(register, sk, BTreeSet::default())?;
+        Ok(cmd)
+    }
+
+    fn edit_register_atop(
+        register: &mut Register,
+        sk: &SecretKey,
+        children: BTreeSet<EntryHash>,
+    ) -> Result<(EntryHash, RegisterCmd)> {
         let data = rand::thread_rng()
             .sample_iter(&Alphanumeric)
             .take(15)
             .collect();
-        let (_, edit) = register.write(data, BTreeSet::default())?;
+        let (hash, edit) = register.write(data, children)?;
         let op = EditRegister {
             address: *register.address(),
             edit,
         };
         let signature = sk.sign(serialize(&op)?);
 
-        Ok(RegisterCmd::Edit(SignedRegisterEdit {
+        let cmd = RegisterCmd::Edit(SignedRegisterEdit {
             op,
             auth: DataAuthority {
                 public_key: sk.public_key(),
                 signature,
             },
-        }))
+        });
+
+        Ok((hash, cmd))
     }
 }
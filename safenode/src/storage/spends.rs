@@ -8,14 +8,14 @@
 
 use crate::{
     network_transfers::{Error, Result},
-    protocol::address::DbcAddress,
+    protocol::{address::DbcAddress, messages::ReplicatedData},
     storage::used_space::UsedSpace,
 };
 
-use sn_dbc::{DbcId, SignedSpend};
+use sn_dbc::{DbcId, DbcTransaction, SignedSpend};
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt::{self, Display, Formatter},
     sync::Arc,
 };
@@ -34,6 +34,9 @@ const DOUBLE_SPENDS_CACHE_SIZE: usize = 5 * 1024 * 1024;
 /// A peer will never again store such a spend to `valid_spends`.
 type ValidSpends<V> = Arc<RwLock<BTreeMap<DbcAddress, V>>>;
 type DoubleSpends<V> = Arc<RwLock<BTreeMap<DbcAddress, (V, V)>>>;
+/// The txs the dbcs of the valid spends were created in, as to replicate them along with
+/// the spends, for the nodes they're replicated to to validate their parents.
+type SourceTxs = Arc<RwLock<BTreeMap<DbcAddress, DbcTransaction>>>;
 
 /// Storage of Dbc spends.
 ///
@@ -43,6 +46,7 @@ type DoubleSpends<V> = Arc<RwLock<BTreeMap<DbcAddress, (V, V)>>>;
 pub(crate) struct SpendStorage {
     valid_spends: ValidSpends<SignedSpend>,
     double_spends: DoubleSpends<SignedSpend>,
+    source_txs: SourceTxs,
     valid_spends_cache_size: UsedSpace,
     double_spends_cache_size: UsedSpace,
 }
@@ -52,6 +56,7 @@ impl SpendStorage {
         Self {
             valid_spends: Arc::new(RwLock::new(BTreeMap::new())),
             double_spends: Arc::new(RwLock::new(BTreeMap::new())),
+            source_txs: Arc::new(RwLock::new(BTreeMap::new())),
             valid_spends_cache_size: UsedSpace::new(VALID_SPENDS_CACHE_SIZE),
             double_spends_cache_size: UsedSpace::new(DOUBLE_SPENDS_CACHE_SIZE),
         }
@@ -62,11 +67,27 @@ impl SpendStorage {
         trace!("Getting Spend: {address:?}");
         if let Some(spend) = self.valid_spends.read().await.get(&address) {
             Ok(spend.clone())
+        } else if self.double_spends.read().await.contains_key(&address) {
+            Err(Error::DoubleSpendDetected(address))
         } else {
             Err(Error::SpendNotFound(address))
         }
     }
 
+    // Read the conflicting spends of a double spent Dbc from local store.
+    pub(crate) async fn get_double_spend_proof(
+        &self,
+        address: DbcAddress,
+    ) -> Result<(SignedSpend, SignedSpend)> {
+        trace!("Getting double spend proof: {address:?}");
+        self.double_spends
+            .read()
+            .await
+            .get(&address)
+            .cloned()
+            .ok_or(Error::DoubleSpendNotFound(address))
+    }
+
     /// We need to check that the parent is spent before
     /// we try add here.
     /// If a double spend attempt is detected, a `DoubleSpendAttempt` error
@@ -75,7 +96,11 @@ impl SpendStorage {
     /// NOTE: The `&mut self` signature is necessary to prevent race conditions
     /// and double spent attempts to be missed (as the validation and adding
     /// could otherwise happen in parallel in different threads.)
-    pub(crate) async fn try_add(&mut self, signed_spend: &SignedSpend) -> Result<()> {
+    pub(crate) async fn try_add(
+        &mut self,
+        signed_spend: &SignedSpend,
+        source_tx: &DbcTransaction,
+    ) -> Result<()> {
         self.validate(signed_spend).await?;
 
         let size_of_new = std::mem::size_of_val(signed_spend);
@@ -83,6 +108,11 @@ impl SpendStorage {
 
         let mut valid_spends = self.valid_spends.write().await;
 
+        let _ = self
+            .source_txs
+            .write()
+            .await
+            .insert(address, source_tx.clone());
         let replaced = valid_spends.insert(address, signed_spend.clone());
         if replaced.is_none() {
             self.valid_spends_cache_size.increase(size_of_new);
@@ -137,6 +167,7 @@ impl SpendStorage {
 
                 // The spend is now permanently removed from the valid spends.
                 let removed = valid_spends.remove(&address);
+                let _ = self.source_txs.write().await.remove(&address);
                 if removed.is_some() {
                     self.valid_spends_cache_size.decrease(size_of_existing);
                 }
@@ -153,12 +184,59 @@ impl SpendStorage {
         // It does however verify that the derived key corresponding to
         // the dbc id signed this spend.
         signed_spend.verify(signed_spend.dst_tx_hash())?;
-        // TODO: We want to verify the transaction somehow as well..
-        // signed_spend.spend.tx.verify(blinded_amounts)
+
+        // The dbc must be one of the inputs of the tx it's spent in.
+        // The tx itself can only be verified with the blinded amounts of all its inputs,
+        // which are held by the sibling spends, see `verify_spent_tx`.
+        let spent_tx = &signed_spend.spend.dst_tx;
+        if !spent_tx
+            .inputs
+            .iter()
+            .any(|input| &input.dbc_id() == signed_spend.dbc_id())
+        {
+            return Err(Error::SpendNotInTx(address));
+        }
 
         Ok(())
     }
 
+    /// Verifies the balance proofs of the tx the dbc is spent in, using the blinded amounts
+    /// of all its inputs, i.e. of the spend itself and of its siblings, which are the spends
+    /// of the other inputs of that same tx.
+    /// Siblings not spent in that tx, or not signed by the owner of their dbc, are ignored.
+    pub(crate) fn verify_spent_tx(
+        signed_spend: &SignedSpend,
+        sibling_spends: &BTreeSet<SignedSpend>,
+    ) -> Result<()> {
+        let spent_tx = &signed_spend.spend.dst_tx;
+        let dst_tx_hash = signed_spend.dst_tx_hash();
+
+        let mut blinded_amounts = Vec::with_capacity(spent_tx.inputs.len());
+        let mut missing = Vec::new();
+        for input in &spent_tx.inputs {
+            let dbc_id = input.dbc_id();
+            let input_spend = std::iter::once(signed_spend)
+                .chain(sibling_spends)
+                .find(|spend| {
+                    spend.dbc_id() == &dbc_id
+                        && spend.dst_tx_hash() == dst_tx_hash
+                        && spend.verify(dst_tx_hash).is_ok()
+                });
+            match input_spend {
+                Some(spend) => blinded_amounts.push(spend.spend.blinded_amount),
+                None => missing.push(dbc_address(&dbc_id)),
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(Error::MissingSiblingSpends(missing));
+        }
+
+        spent_tx
+            .verify(&blinded_amounts)
+            .map_err(|err| Error::InvalidSpentTx(dst_tx_hash, err.to_string()))
+    }
+
     /// When data is replicated to a new peer,
     /// it may contain double spends, and thus we need to add that here,
     /// so that we in the future can serve this info to Clients.
@@ -210,6 +288,7 @@ impl SpendStorage {
 
         // The spend is now permanently removed from the valid spends.
         let mut valid_spends = self.valid_spends.write().await;
+        let _ = self.source_txs.write().await.remove(&address);
         if let Some(removed) = valid_spends.remove(&address) {
             self.valid_spends_cache_size
                 .decrease(std::mem::size_of_val(&removed));
@@ -218,6 +297,28 @@ impl SpendStorage {
         Ok(())
     }
 
+    /// All the spends held, valid ones along with the tx their dbc was created in,
+    /// and double ones, as to be replicated to other nodes.
+    pub(crate) async fn replicated_data(&self) -> Vec<ReplicatedData> {
+        let valid_spends = self.valid_spends.read().await;
+        let source_txs = self.source_txs.read().await;
+        let double_spends = self.double_spends.read().await;
+        valid_spends
+            .iter()
+            .filter_map(|(address, spend)| {
+                let source_tx = source_txs.get(address)?;
+                Some(ReplicatedData::ValidSpend((
+                    spend.clone(),
+                    source_tx.clone(),
+                )))
+            })
+            .chain(double_spends.iter().map(|(address, (a_spend, b_spend))| {
+                let spends = BTreeSet::from([a_spend.clone(), b_spend.clone()]);
+                ReplicatedData::DoubleSpend((*address, spends))
+            }))
+            .collect()
+    }
+
     /// Checks if the given DbcId is unspendable.
     async fn is_unspendable(&self, dbc_id: &DbcId) -> bool {
         let address = dbc_address(dbc_id);
@@ -304,6 +304,8 @@ impl Testnet {
             launch_args.push("--root".to_string());
             launch_args.push("--bin".to_string());
             launch_args.push("safenode".to_string());
+            launch_args.push("--features".to_string());
+            launch_args.push("local-testnet".to_string());
             launch_args.push("--".to_string());
         }
 
@@ -607,6 +609,8 @@ mod test {
                     "--root".to_string(),
                     "--bin".to_string(),
                     SAFENODE_BIN_NAME.to_string(),
+                    "--features".to_string(),
+                    "local-testnet".to_string(),
                     "--".to_string(),
                     "--first".to_string(),
                     "10.0.0.1:12000".to_string(),
@@ -831,6 +835,8 @@ mod test {
                         "--root".to_string(),
                         "--bin".to_string(),
                         SAFENODE_BIN_NAME.to_string(),
+                        "--features".to_string(),
+                        "local-testnet".to_string(),
                         "--".to_string(),
                         "--network-contacts-file".to_string(),
                         network_contacts_file.path().to_str().unwrap().to_string(),
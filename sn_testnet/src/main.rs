@@ -41,6 +41,8 @@ use std::{
 use tracing::{debug, info};
 
 const DEFAULT_NODE_COUNT: u32 = 25;
+const FAUCET_DIR_NAME: &str = "faucet";
+const FAUCET_RPC_ADDR: &str = "127.0.0.1:12001";
 
 #[derive(Debug, clap::StructOpt)]
 #[clap(name = "testnet", version)]
@@ -75,14 +77,25 @@ struct Cmd {
     /// Build the node from source.
     ///
     /// This assumes you're running the process from the `safe_network` repository.
+    ///
+    /// The node is built with the `local-testnet` feature, so that the genesis node runs a faucet.
     #[clap(long, short = 'b')]
     build_node: bool,
 
+    /// Build the node with the `chaos` feature, making nodes restart, drop connections and delay
+    /// responses at random, as configured by the `SAFENODE_CHAOS_*` environment variables.
+    ///
+    /// This is the same as building the testnet with the `chaos` feature, and requires --build-node.
+    #[clap(long)]
+    chaos: bool,
+
     /// Optional path to the safenode binary.
     ///
     /// This will take precedence over the --build-node flag and effectively ignore it.
     ///
     /// If not supplied we will assume that safenode is on PATH.
+    ///
+    /// No faucet is run when the node isn't built by us, as it may lack the `local-testnet` feature.
     #[clap(short = 'p', long, value_name = "FILE_PATH")]
     node_path: Option<PathBuf>,
 
@@ -113,11 +126,19 @@ async fn main() -> Result<()> {
         return Err(eyre!("Flamegraph cannot be used on Windows"));
     }
 
+    let chaos = args.chaos || cfg!(feature = "chaos");
+    if chaos && !args.build_node {
+        return Err(eyre!("Chaos mode requires building the node")
+            .suggestion("Please try again using the --build-node argument"));
+    }
+
+    // Only nodes built with the `local-testnet` feature can run the faucet.
+    let run_faucet = args.node_path.is_none() && args.build_node;
     let mut node_bin_path = PathBuf::new();
     if let Some(node_path) = args.node_path {
         node_bin_path.push(node_path);
     } else if args.build_node {
-        build_node().await?;
+        build_node(chaos).await?;
         node_bin_path.push("target");
         node_bin_path.push("release");
         node_bin_path.push(SAFENODE_BIN_NAME);
@@ -149,6 +170,7 @@ async fn main() -> Result<()> {
         args.node_count.unwrap_or(DEFAULT_NODE_COUNT),
         args.node_args,
         args.flame,
+        run_faucet,
     )
     .await?;
 
@@ -181,11 +203,12 @@ async fn check_flamegraph_prerequisites() -> Result<()> {
     Ok(())
 }
 
-async fn build_node() -> Result<()> {
-    let mut args = vec!["build", "--release"];
+async fn build_node(chaos: bool) -> Result<()> {
+    // Nodes of local testnets accept the spend of the testnet genesis dbc, and run its faucet.
+    let mut args = vec!["build", "--release", "--features", "local-testnet"];
 
     // Keep features consistent to avoid recompiling.
-    if cfg!(feature = "chaos") {
+    if chaos {
         println!("*** Building testnet with CHAOS enabled. Watch out. ***");
         args.push("--features");
         args.push("chaos");
@@ -218,6 +241,7 @@ async fn run_network(
     node_count: u32,
     node_args: Vec<String>,
     flamegraph_mode: bool,
+    run_faucet: bool,
 ) -> Result<()> {
     let (mut testnet, network_contacts_path) = Testnet::configure()
         .node_bin_path(node_bin_path)
@@ -225,7 +249,24 @@ async fn run_network(
         .clear_nodes_dir()
         .flamegraph_mode(flamegraph_mode)
         .build()?;
-    testnet.launch_genesis(None, node_args.clone())?;
+    // The genesis node runs the faucet, handing out test tokens over its RPC address.
+    let mut genesis_node_args = node_args.clone();
+    if run_faucet {
+        genesis_node_args.extend([
+            "--faucet-dir".to_string(),
+            testnet
+                .nodes_dir_path
+                .join(FAUCET_DIR_NAME)
+                .to_str()
+                .ok_or_else(|| eyre!("Unable to obtain the faucet directory path"))?
+                .to_string(),
+            "--rpc".to_string(),
+            FAUCET_RPC_ADDR.to_string(),
+        ]);
+    } else {
+        println!("No faucet is run, as it requires building the node with --build-node");
+    }
+    testnet.launch_genesis(None, genesis_node_args)?;
     testnet.launch_nodes(node_count as usize, &network_contacts_path, node_args)?;
 
     // Perform a verification on the nodes launched (if requested) as a last step